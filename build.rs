@@ -1,3 +1,10 @@
 fn main() {
     println!("cargo:rustc-link-arg-bins=-Tlinkall.x");
+
+    println!(
+        "cargo:rustc-link-search={}",
+        std::env::var("CARGO_MANIFEST_DIR").unwrap()
+    );
+    println!("cargo:rustc-link-arg-bins=-Tsize.x");
+    println!("cargo:rerun-if-changed=size.x");
 }
@@ -0,0 +1,2 @@
+/* Section sizes reported at startup, see `src/logging.rs`. */
+_text_size = SIZEOF(.text);
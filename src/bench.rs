@@ -0,0 +1,45 @@
+//! Micro-benchmarks run at startup with the `bench` feature.
+
+use crate::{color::convert_rgb888_to_565, hal::xtensa_lx::timer::get_cycle_count};
+
+/// One 480 pixel line.
+const PIXELS: usize = 480;
+const ROUNDS: u32 = 100;
+
+/// Logs the cycles per pixel of [convert_rgb888_to_565] next to a naive
+/// one-pixel-at-a-time loop, on a line of varied pixels, and panics if the
+/// two disagree.
+pub fn rgb888_to_565() {
+    let mut src = [0u8; PIXELS * 3];
+    for (i, byte) in src.iter_mut().enumerate() {
+        *byte = (i * 37) as u8;
+    }
+    let mut expected = [0u16; PIXELS];
+    let mut dst = [0u16; PIXELS];
+
+    let naive = cycles_per_pixel(|| {
+        for (s, d) in src.chunks_exact(3).zip(expected.iter_mut()) {
+            *d = ((s[0] as u16 >> 3) << 11) | ((s[1] as u16 >> 2) << 5) | (s[2] as u16 >> 3);
+        }
+        core::hint::black_box(&expected);
+    });
+    let fast = cycles_per_pixel(|| {
+        convert_rgb888_to_565(core::hint::black_box(&src), &mut dst);
+        core::hint::black_box(&dst);
+    });
+
+    assert_eq!(
+        dst, expected,
+        "convert_rgb888_to_565 disagrees with the naive loop"
+    );
+    info!("RGB888 to RGB565: {} cycles/pixel, naive {}", fast, naive);
+}
+
+fn cycles_per_pixel(mut f: impl FnMut()) -> f32 {
+    let started = get_cycle_count();
+    for _ in 0..ROUNDS {
+        f();
+    }
+    let cycles = get_cycle_count().wrapping_sub(started);
+    cycles as f32 / (ROUNDS as usize * PIXELS) as f32
+}
@@ -0,0 +1,180 @@
+//! Minimal reader for uncompressed 16-bit BMP files.
+
+/// Errors from [Bmp565::parse].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BmpError {
+    /// The file doesn't start with `BM`, the header is cut short or its
+    /// size is empty or too large to address.
+    InvalidHeader,
+    /// Only 16 bits per pixel are supported.
+    UnsupportedBitDepth(u16),
+    /// The pixel data is shorter than the header says.
+    Truncated,
+}
+
+const HEADER_LEN: usize = 54;
+
+/// A 16-bit BMP image borrowed from its file data.
+///
+/// Pixels are taken as RGB565, the layout of `BI_BITFIELDS` files with the
+/// usual masks, stored little-endian. That's [WireFormat::DEFAULT], so with
+/// it rows could be pushed as they are;
+/// [DmaTxStreamBuf::push_bmp_pixels] converts them to the current format.
+///
+/// [WireFormat::DEFAULT]: crate::color::WireFormat::DEFAULT
+/// [DmaTxStreamBuf::push_bmp_pixels]: crate::dma::DmaTxStreamBuf::push_bmp_pixels
+pub struct Bmp565<'a> {
+    width: usize,
+    height: usize,
+    top_down: bool,
+    stride: usize,
+    pixels: &'a [u8],
+}
+
+impl<'a> Bmp565<'a> {
+    /// Parses the 54-byte header at the start of `data`.
+    pub fn parse(data: &'a [u8]) -> Result<Self, BmpError> {
+        if data.len() < HEADER_LEN || &data[..2] != b"BM" {
+            return Err(BmpError::InvalidHeader);
+        }
+
+        let u16_at = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+        let u32_at = |at: usize| u32::from_le_bytes(data[at..at + 4].try_into().unwrap());
+
+        let offset = u32_at(10) as usize;
+        let width = u32_at(18) as i32;
+        let height = u32_at(22) as i32;
+        let bpp = u16_at(28);
+
+        if bpp != 16 {
+            return Err(BmpError::UnsupportedBitDepth(bpp));
+        }
+        if width <= 0 || height == 0 {
+            return Err(BmpError::InvalidHeader);
+        }
+
+        let width = width as u32;
+        // A negative height marks a top-down image.
+        let top_down = height < 0;
+        let height = height.unsigned_abs();
+        // Rows are padded to 4 bytes. Sizes are checked in `u32`, the
+        // `usize` of the ESP32-S3, so a corrupt header can't wrap them.
+        let stride = width
+            .checked_mul(2)
+            .and_then(|row| row.checked_next_multiple_of(4))
+            .ok_or(BmpError::InvalidHeader)?;
+        let len = stride.checked_mul(height).ok_or(BmpError::InvalidHeader)?;
+
+        let pixels = data
+            .get(offset..)
+            .and_then(|pixels| pixels.get(..len as usize))
+            .ok_or(BmpError::Truncated)?;
+
+        Ok(Self {
+            width: width as usize,
+            height: height as usize,
+            top_down,
+            stride: stride as usize,
+            pixels,
+        })
+    }
+
+    /// Width in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The rows from top to bottom, without padding.
+    pub fn rows(&self) -> impl Iterator<Item = &'a [u8]> + '_ {
+        (0..self.height).map(|y| {
+            // BMP rows are stored bottom to top unless the height is negative.
+            let stored = if self.top_down {
+                y
+            } else {
+                self.height - 1 - y
+            };
+            &self.pixels[stored * self.stride..][..self.width * 2]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+
+    /// A 16-bit BMP with `pixels` as its data, right after the header.
+    fn bmp(width: i32, height: i32, pixels: &[u8]) -> Vec<u8> {
+        let mut data = std::vec![0; HEADER_LEN];
+        data[..2].copy_from_slice(b"BM");
+        data[10..14].copy_from_slice(&(HEADER_LEN as u32).to_le_bytes());
+        data[18..22].copy_from_slice(&width.to_le_bytes());
+        data[22..26].copy_from_slice(&height.to_le_bytes());
+        data[28..30].copy_from_slice(&16u16.to_le_bytes());
+        data.extend_from_slice(pixels);
+        data
+    }
+
+    #[test]
+    fn rows_skip_the_padding_of_odd_widths() {
+        // Three pixels are 6 bytes, padded to 8. Stored bottom row first.
+        let pixels = [
+            0x21, 0x00, 0x22, 0x00, 0x23, 0x00, 0xEE, 0xEE, // bottom
+            0x11, 0x00, 0x12, 0x00, 0x13, 0x00, 0xEE, 0xEE, // top
+        ];
+        let data = bmp(3, 2, &pixels);
+        let image = Bmp565::parse(&data).unwrap();
+
+        assert_eq!((image.width(), image.height()), (3, 2));
+        let rows: Vec<_> = image.rows().collect();
+        assert_eq!(rows, [&pixels[8..14], &pixels[..6]]);
+    }
+
+    #[test]
+    fn top_down_rows_keep_their_order() {
+        let pixels = [1, 0, 0, 0, 2, 0, 0, 0];
+        let data = bmp(1, -2, &pixels);
+        let rows: Vec<_> = Bmp565::parse(&data).unwrap().rows().collect();
+        assert_eq!(rows, [&[1, 0][..], &[2, 0][..]]);
+    }
+
+    #[test]
+    fn rejects_truncated_headers_and_data() {
+        let data = bmp(3, 2, &[0; 16]);
+        assert_eq!(
+            Bmp565::parse(&data[..HEADER_LEN - 1]).err(),
+            Some(BmpError::InvalidHeader)
+        );
+        assert_eq!(
+            Bmp565::parse(&data[..data.len() - 1]).err(),
+            Some(BmpError::Truncated)
+        );
+    }
+
+    #[test]
+    fn rejects_empty_and_oversized_images() {
+        for (width, height) in [(0, 1), (1, 0), (-1, 1), (i32::MAX, 1), (0x1000, 0x10_0000)] {
+            assert_eq!(
+                Bmp565::parse(&bmp(width, height, &[])).err(),
+                Some(BmpError::InvalidHeader),
+                "{width}x{height}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_other_bit_depths() {
+        let mut data = bmp(1, 1, &[0; 4]);
+        data[28] = 24;
+        assert_eq!(
+            Bmp565::parse(&data).err(),
+            Some(BmpError::UnsupportedBitDepth(24))
+        );
+    }
+}
@@ -0,0 +1,424 @@
+//! Pin maps and panel parameters of the boards this example has been run on.
+//!
+//! Pick one with `BOARD` in `main.rs`. When reporting an issue, name the
+//! board const you used, or paste your own [Board] if your wiring differs.
+
+use core::fmt;
+
+#[cfg(target_arch = "xtensa")]
+use crate::hal::{DriverMode, gpio::AnyPin, lcd_cam::lcd::dpi::Dpi};
+use crate::{
+    color::PixelFormat,
+    display::clock::LatchEdge,
+    framebuffer::Orientation,
+    geometry::FrameGeometry,
+    hal::{gpio::Level, lcd_cam::lcd::dpi::FrameTiming},
+};
+
+/// A board: how the panel is wired and how to drive it.
+pub struct Board {
+    pub name: &'static str,
+    pub spi: SpiPins,
+    pub dpi: DpiPins,
+    pub panel: PanelDescriptor,
+    /// Pins the board's module needs for something else, see
+    /// [Board::validate_pins].
+    pub reserved: &'static [ReservedPins],
+}
+
+/// GPIOs a module uses internally or for a peripheral that shouldn't be
+/// given up silently.
+pub struct ReservedPins {
+    /// What the pins are for, shown in the warning.
+    pub what: &'static str,
+    pub pins: &'static [u8],
+}
+
+/// The SPI flash of every ESP32-S3 module.
+pub const FLASH: ReservedPins = ReservedPins {
+    what: "SPI flash",
+    pins: &[26, 27, 28, 29, 30, 31, 32],
+};
+
+/// The extra data lines of modules with octal PSRAM (the R8 variants).
+pub const OCTAL_PSRAM: ReservedPins = ReservedPins {
+    what: "octal PSRAM",
+    pins: &[33, 34, 35, 36, 37],
+};
+
+/// The USB-Serial-JTAG D-/D+ pins. Using them drops the debug and log
+/// connection over USB.
+pub const USB_JTAG: ReservedPins = ReservedPins {
+    what: "USB-Serial-JTAG",
+    pins: &[19, 20],
+};
+
+/// What a GPIO is assigned to in a [Board].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinRole {
+    /// `LCD_DATA_n`.
+    Data(u8),
+    Pclk,
+    Hsync,
+    Vsync,
+    De,
+    SpiCs,
+    SpiSda,
+    SpiScl,
+    SpiRst,
+}
+
+/// The same GPIO is assigned twice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PinConflict {
+    pub pin: u8,
+    pub first: PinRole,
+    pub second: PinRole,
+}
+
+impl fmt::Display for PinConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "GPIO{} is assigned to both {:?} and {:?}",
+            self.pin, self.first, self.second
+        )
+    }
+}
+
+/// The first pin in `roles` that an earlier one already uses.
+fn find_conflict(roles: impl Iterator<Item = (u8, PinRole)> + Clone) -> Result<(), PinConflict> {
+    for (i, (pin, second)) in roles.clone().enumerate() {
+        if let Some((_, first)) = roles.clone().take(i).find(|&(p, _)| p == pin) {
+            return Err(PinConflict { pin, first, second });
+        }
+    }
+    Ok(())
+}
+
+impl Board {
+    /// Checks the pin map before any pin is stolen.
+    ///
+    /// A GPIO assigned twice, across the SPI and DPI pins, is an error. A
+    /// pin in [Board::reserved] is only logged as a warning per pin, since
+    /// giving one up can be deliberate, but the lost USB log or a crash on
+    /// the next flash access is hard to trace back to the pin map. Call it
+    /// once at bringup.
+    pub fn validate_pins(&self) -> Result<(), PinConflict> {
+        let roles = self.spi.roles().chain(self.dpi.roles());
+        find_conflict(roles.clone())?;
+
+        for reserved in self.reserved {
+            for (pin, role) in roles.clone() {
+                if reserved.pins.contains(&pin) {
+                    warn!(
+                        "GPIO{} is reserved for {} on {} but used as {:?}",
+                        pin, reserved.what, self.name, role
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// GPIO numbers of the 3-wire SPI used to initialize the panel.
+pub struct SpiPins {
+    pub cs: u8,
+    pub sda: u8,
+    pub scl: u8,
+    /// Boards without a reset line fall back to a software reset.
+    pub rst: Option<u8>,
+}
+
+impl SpiPins {
+    fn roles(&self) -> impl Iterator<Item = (u8, PinRole)> + Clone + '_ {
+        [
+            (self.cs, PinRole::SpiCs),
+            (self.sda, PinRole::SpiSda),
+            (self.scl, PinRole::SpiScl),
+        ]
+        .into_iter()
+        .chain(self.rst.map(|rst| (rst, PinRole::SpiRst)))
+    }
+}
+
+/// GPIO numbers of the DPI (parallel RGB) interface.
+pub struct DpiPins {
+    /// `data[n]` is connected to `LCD_DATA_n`: 0..=4 blue, 5..=10 green,
+    /// 11..=15 red.
+    pub data: [u8; 16],
+    pub pclk: u8,
+    pub hsync: u8,
+    pub vsync: u8,
+    pub de: u8,
+}
+
+/// Resolution and DPI timing of a panel.
+#[derive(Clone, Copy)]
+pub struct PanelDescriptor {
+    /// Active area and pixel format, matching the active size of `timing`.
+    pub geometry: FrameGeometry,
+    pub pclk_mhz: u32,
+    /// PCLK edge the panel latches on, which picks the DPI clock mode.
+    pub latch: LatchEdge,
+    pub timing: FrameTiming,
+    /// VCOM amplitude (`0xB1` on page BK1), see [St7701::set_vcom].
+    ///
+    /// [St7701::set_vcom]: crate::display::st7701::St7701::set_vcom
+    pub vcom: u8,
+    /// How the application's coordinates are rotated on the panel, see
+    /// [Self::rotated].
+    pub orientation: Orientation,
+    /// Sync and DE polarities, as the levels the lines rest at.
+    pub idle: IdleLevels,
+}
+
+/// Levels HSYNC, VSYNC and DE rest at between their pulses, the inverse of
+/// their polarities.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdleLevels {
+    pub hsync: Level,
+    pub vsync: Level,
+    pub de: Level,
+}
+
+impl IdleLevels {
+    /// Active low syncs and active high DE, what the ST7701 expects.
+    pub const ST7701: Self = Self {
+        hsync: Level::High,
+        vsync: Level::High,
+        de: Level::Low,
+    };
+}
+
+impl PanelDescriptor {
+    /// The same panel at a 2MHz pclk, slow enough to follow every signal on
+    /// a scope.
+    ///
+    /// The resolution and porches stay as they are, the panel needs them, so
+    /// the refresh rate drops by the same factor: 7 to 8Hz on the boards
+    /// here, a frame every 120 to 140ms. Expect visible flicker, and size waits
+    /// with [vsync::frame_timeout] instead of fixed delays. The push
+    /// bandwidth needed drops to a sixth as well, so the ring underruns far
+    /// later.
+    ///
+    /// [vsync::frame_timeout]: crate::display::vsync::frame_timeout
+    pub const fn debug_slow_mode(mut self) -> Self {
+        self.pclk_mhz = 2;
+        self
+    }
+
+    /// The same panel used at `orientation`, for frame buffers to pick up
+    /// with [FrameBuffer::set_orientation], so the orientation is stated
+    /// once, here.
+    ///
+    /// `timing` and `geometry` stay as they are, also on a non-square panel.
+    /// A DPI panel scans its lines in a fixed direction set by its gate and
+    /// source drivers, so the wire always carries physical rows: swapping
+    /// the horizontal and vertical timing of a 480x640 panel would send 640
+    /// pixel lines to a panel that takes 480. No panel register exchanges
+    /// rows and columns either, so 90 and 270 degrees are only ever done in
+    /// software, by [FrameBuffer::set_pixel]. Only [Self::logical_size]
+    /// swaps.
+    ///
+    /// [FrameBuffer::set_orientation]: crate::framebuffer::FrameBuffer::set_orientation
+    /// [FrameBuffer::set_pixel]: crate::framebuffer::FrameBuffer::set_pixel
+    pub const fn rotated(mut self, orientation: Orientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    /// Width and height the application draws in at [Self::orientation].
+    pub const fn logical_size(&self) -> (usize, usize) {
+        let (width, height) = (self.geometry.width, self.geometry.height);
+        match self.orientation {
+            Orientation::Deg0 | Orientation::Deg180 => (width, height),
+            Orientation::Deg90 | Orientation::Deg270 => (height, width),
+        }
+    }
+}
+
+impl DpiPins {
+    fn roles(&self) -> impl Iterator<Item = (u8, PinRole)> + Clone + '_ {
+        let data = self.data.iter().enumerate();
+        data.map(|(n, &pin)| (pin, PinRole::Data(n as u8))).chain([
+            (self.pclk, PinRole::Pclk),
+            (self.hsync, PinRole::Hsync),
+            (self.vsync, PinRole::Vsync),
+            (self.de, PinRole::De),
+        ])
+    }
+
+    /// Fails if a GPIO is assigned to more than one signal.
+    /// [Board::validate_pins] checks the SPI pins as well.
+    pub fn validate(&self) -> Result<(), PinConflict> {
+        find_conflict(self.roles())
+    }
+
+    /// Connects all pins to `dpi`.
+    ///
+    /// # Safety
+    ///
+    /// The pins are stolen, nothing else may be using them. Nothing here
+    /// checks that they're distinct, see [Self::validate].
+    #[cfg(target_arch = "xtensa")]
+    pub unsafe fn attach<'d, Dm: DriverMode>(&self, dpi: Dpi<'d, Dm>) -> Dpi<'d, Dm> {
+        let pin = |n: u8| unsafe { AnyPin::steal(n) };
+        let d = &self.data;
+
+        dpi.with_data0(pin(d[0]))
+            .with_data1(pin(d[1]))
+            .with_data2(pin(d[2]))
+            .with_data3(pin(d[3]))
+            .with_data4(pin(d[4]))
+            .with_data5(pin(d[5]))
+            .with_data6(pin(d[6]))
+            .with_data7(pin(d[7]))
+            .with_data8(pin(d[8]))
+            .with_data9(pin(d[9]))
+            .with_data10(pin(d[10]))
+            .with_data11(pin(d[11]))
+            .with_data12(pin(d[12]))
+            .with_data13(pin(d[13]))
+            .with_data14(pin(d[14]))
+            .with_data15(pin(d[15]))
+            .with_pclk(pin(self.pclk))
+            .with_hsync(pin(self.hsync))
+            .with_vsync(pin(self.vsync))
+            .with_de(pin(self.de))
+    }
+}
+
+/// The board this example was written on.
+pub const MIAO_480X480: Board = Board {
+    name: "miao-480x480",
+    spi: SpiPins {
+        cs: 21,
+        sda: 13,
+        scl: 14,
+        rst: Some(47),
+    },
+    dpi: DpiPins {
+        data: [
+            46, 9, 10, 11, 12, // Blue
+            17, 18, 8, 19, 20, 3, // Green
+            5, 6, 7, 15, 16, // Red
+        ],
+        pclk: 40,
+        hsync: 39,
+        vsync: 38,
+        de: 37,
+    },
+    panel: PanelDescriptor {
+        geometry: FrameGeometry::new(480, 480, PixelFormat::Rgb565),
+        pclk_mhz: 12,
+        latch: LatchEdge::RisingEdgeLatch,
+        timing: FrameTiming {
+            horizontal_active_width: 480,
+            horizontal_total_width: 500,
+            horizontal_blank_front_porch: 10,
+
+            vertical_active_height: 480,
+            vertical_total_height: 493,
+            vertical_blank_front_porch: 2,
+
+            hsync_width: 10,
+            vsync_width: 10,
+
+            hsync_position: 0,
+        },
+        vcom: 0x43,
+        orientation: Orientation::Deg0,
+        idle: IdleLevels::ST7701,
+    },
+    // GPIO19 and 20 are data lines, so USB-Serial-JTAG is given up on
+    // purpose and not listed.
+    reserved: &[FLASH],
+};
+
+/// Sunton ESP32-4848S040, a common 4" 480x480 ST7701 board.
+///
+/// Pin map and porches follow the vendor's Arduino demo; the panel has no
+/// reset line wired to the ESP32.
+pub const SUNTON_4848S040: Board = Board {
+    name: "sunton-4848s040",
+    spi: SpiPins {
+        cs: 39,
+        sda: 47,
+        scl: 48,
+        rst: None,
+    },
+    dpi: DpiPins {
+        data: [
+            4, 5, 6, 7, 15, // Blue
+            8, 20, 3, 46, 9, 10, // Green
+            11, 12, 13, 14, 0, // Red
+        ],
+        pclk: 21,
+        hsync: 16,
+        vsync: 17,
+        de: 18,
+    },
+    panel: PanelDescriptor {
+        geometry: FrameGeometry::new(480, 480, PixelFormat::Rgb565),
+        pclk_mhz: 12,
+        latch: LatchEdge::RisingEdgeLatch,
+        timing: FrameTiming {
+            horizontal_active_width: 480,
+            horizontal_total_width: 548,
+            horizontal_blank_front_porch: 58,
+
+            vertical_active_height: 480,
+            vertical_total_height: 518,
+            vertical_blank_front_porch: 28,
+
+            hsync_width: 8,
+            vsync_width: 8,
+
+            hsync_position: 0,
+        },
+        vcom: 0x43,
+        orientation: Orientation::Deg0,
+        idle: IdleLevels::ST7701,
+    },
+    // GPIO20 is a data line in the vendor's pin map, so USB-Serial-JTAG is
+    // gone there anyway.
+    reserved: &[FLASH, OCTAL_PSRAM],
+};
+
+/// Every board above.
+pub const ALL: &[&Board] = &[&MIAO_480X480, &SUNTON_4848S040];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotating_a_portrait_panel_only_swaps_the_logical_size() {
+        let base = MIAO_480X480.panel;
+        let panel = PanelDescriptor {
+            geometry: FrameGeometry::new(480, 640, PixelFormat::Rgb565),
+            timing: FrameTiming {
+                vertical_active_height: 640,
+                vertical_total_height: 678,
+                ..base.timing
+            },
+            ..base
+        };
+
+        for (orientation, size) in [
+            (Orientation::Deg0, (480, 640)),
+            (Orientation::Deg90, (640, 480)),
+            (Orientation::Deg180, (480, 640)),
+            (Orientation::Deg270, (640, 480)),
+        ] {
+            let rotated = panel.rotated(orientation);
+            assert_eq!(rotated.orientation, orientation);
+            assert_eq!(rotated.logical_size(), size, "{orientation:?}");
+            assert_eq!(rotated.geometry, panel.geometry, "{orientation:?}");
+            assert_eq!(rotated.timing, panel.timing, "{orientation:?}");
+        }
+    }
+}
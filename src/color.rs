@@ -0,0 +1,673 @@
+//! RGB565 pixel helpers.
+//!
+//! Pixels in frame data are two bytes each, laid out for the bus as the
+//! current [WireFormat] says; without [set_wire_format] that's little-endian,
+//! see [FrameBuffer](crate::framebuffer::FrameBuffer).
+
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use crate::hal::lcd_cam::{BitOrder, ByteOrder, lcd::dpi::Format};
+
+/// Number of brightness levels, `0` is black and `BRIGHTNESS_LEVELS - 1` is
+/// full brightness.
+pub const BRIGHTNESS_LEVELS: usize = 32;
+
+const MAX_LEVEL: u8 = BRIGHTNESS_LEVELS as u8 - 1;
+
+// Fixed point with 16 fractional bits, used to build gamma tables in const
+// context.
+const Q: u32 = 16;
+const ONE: u64 = 1 << Q;
+
+const fn mul_q(a: u64, b: u64) -> u64 {
+    (a * b) >> Q
+}
+
+const fn pow_q(x: u64, n: u32) -> u64 {
+    let mut result = ONE;
+    let mut i = 0;
+    while i < n {
+        result = mul_q(result, x);
+        i += 1;
+    }
+    result
+}
+
+/// `x ^ (num / den)` for `x` in `[0, 1]`.
+///
+/// The `den`-th root is found first, by binary search for the largest `y`
+/// with `y ^ den <= x`, so small `x` don't underflow before the root is taken.
+const fn pow_frac_q(x: u64, num: u32, den: u32) -> u64 {
+    let mut lo = 0;
+    let mut hi = ONE;
+    while lo < hi {
+        let mid = (lo + hi).div_ceil(2);
+        if pow_q(mid, den) <= x {
+            lo = mid;
+        } else {
+            hi = mid - 1;
+        }
+    }
+    pow_q(lo, num)
+}
+
+const fn split(color: u16) -> (u16, u16, u16) {
+    (color >> 11, (color >> 5) & 0x3F, color & 0x1F)
+}
+
+const fn join(r: u16, g: u16, b: u16) -> u16 {
+    (r << 11) | (g << 5) | b
+}
+
+/// Dims `color` by shifting every channel right by `shift` bits.
+///
+/// Cheap, but each step halves the light output, so fades built on it stay
+/// bright for a long time and then drop to black.
+pub const fn dim565(color: u16, shift: u32) -> u16 {
+    let (r, g, b) = split(color);
+    join(r >> shift, g >> shift, b >> shift)
+}
+
+/// Swaps the red and blue channels of an RGB565 `color`.
+pub const fn swap_rb(color: u16) -> u16 {
+    let r = (color >> 11) & 0x1F;
+    let b = color & 0x1F;
+    (b << 11) | (color & 0x07E0) | r
+}
+
+/// Composites RGB565 `fg` over `bg` at opacity `alpha`, `255` being fully
+/// `fg`. Each channel is `(fg * alpha + bg * (255 - alpha)) / 255` in `u32`.
+///
+/// Exact but with a division per channel; to blend whole lines against one
+/// color [blend_overlay] is cheaper.
+pub const fn alpha_blend(fg: u16, bg: u16, alpha: u8) -> u16 {
+    let (fr, fg, fb) = split(fg);
+    let (br, bg, bb) = split(bg);
+    let (a, inv) = (alpha as u32, 255 - alpha as u32);
+    join(
+        saturate((fr as u32 * a + br as u32 * inv) / 255, 0x1F),
+        saturate((fg as u32 * a + bg as u32 * inv) / 255, 0x3F),
+        saturate((fb as u32 * a + bb as u32 * inv) / 255, 0x1F),
+    )
+}
+
+/// [alpha_blend] for a foreground whose channels are already multiplied by
+/// `alpha / 255`, e.g. a sprite prepared once and drawn many times, leaving
+/// one multiply per channel: `fg_premult + bg * (255 - alpha) / 255`.
+pub const fn premultiplied_alpha_blend(fg_premult: u16, alpha: u8, bg: u16) -> u16 {
+    let (fr, fg, fb) = split(fg_premult);
+    let (br, bg, bb) = split(bg);
+    let inv = 255 - alpha as u32;
+    join(
+        saturate(fr as u32 + br as u32 * inv / 255, 0x1F),
+        saturate(fg as u32 + bg as u32 * inv / 255, 0x3F),
+        saturate(fb as u32 + bb as u32 * inv / 255, 0x1F),
+    )
+}
+
+/// Clamps a blended channel to its field width, for foregrounds that
+/// weren't premultiplied with the alpha they're blended at.
+const fn saturate(channel: u32, max: u16) -> u16 {
+    if channel > max as u32 {
+        max
+    } else {
+        channel as u16
+    }
+}
+
+/// Byte order of the 16-bit pixels in pushed data.
+///
+/// Which one a DPI `Format` needs, with `rev` meaning each byte is also
+/// bit-reversed ([WireFormat::reverse_bits]):
+///
+/// | `enable_2byte_mode` | `byte_order` | `bit_order` | Pixel in memory         |
+/// |---------------------|--------------|-------------|-------------------------|
+/// | `true`              | `Normal`     | `Normal`    | Little: `[lo, hi]`      |
+/// | `true`              | `Inverted`   | `Normal`    | Big: `[hi, lo]`         |
+/// | `true`              | `Normal`     | `Inverted`  | Big: `[rev hi, rev lo]` |
+/// | `true`              | `Inverted`   | `Inverted`  | Little: `[rev lo, rev hi]` |
+/// | `false`             | `Normal`     | either      | Big, `rev` if `Inverted` |
+/// | `false`             | `Inverted`   | either      | Little, `rev` if `Inverted` |
+///
+/// In 2-byte mode `BitOrder::Inverted` reverses all 16 bits, which moves
+/// the high byte to D0 to D7 and so flips the byte order as well. The MRE in
+/// `main` uses 2-byte mode with `BitOrder::Inverted`: big-endian with
+/// reversed bytes, so neither a plain `to_be_bytes` nor `to_le_bytes` gives
+/// red. [WireFormat::encode] gets both parts right; this is the byte order
+/// alone, for code like R/B swapping that only cares about that. See
+/// [recommended_endianness].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelEndian {
+    Little,
+    Big,
+}
+
+/// How the LCD_CAM turns 16-bit pixels in memory into levels on the data
+/// pins, derived from the DPI [Format].
+///
+/// Every conversion from logical RGB565 values to frame bytes goes through
+/// [Self::encode] with the format set by [set_wire_format], so changing
+/// `byte_order`, `bit_order` or `enable_2byte_mode` in the `Format` keeps
+/// colors right as long as the same `Format` is passed to both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WireFormat {
+    /// `enable_2byte_mode`: a whole pixel per PCLK on D0 to D15. Otherwise
+    /// the high byte goes out first on D0 to D7, then the low byte.
+    pub two_byte: bool,
+    /// `ByteOrder::Inverted`: the two bytes of a pixel are swapped.
+    pub swap_bytes: bool,
+    /// `BitOrder::Inverted`: the bits of a pixel are reversed, all 16 in
+    /// 2-byte mode and each byte on its own otherwise.
+    pub reverse_bits: bool,
+}
+
+impl WireFormat {
+    /// 2-byte mode with native orders, little-endian pixels in memory.
+    pub const DEFAULT: Self = Self {
+        two_byte: true,
+        swap_bytes: false,
+        reverse_bits: false,
+    };
+
+    pub const fn from_format(format: &Format) -> Self {
+        Self {
+            two_byte: format.enable_2byte_mode,
+            swap_bytes: matches!(format.byte_order, ByteOrder::Inverted),
+            reverse_bits: matches!(format.bit_order, BitOrder::Inverted),
+        }
+    }
+
+    /// The bytes to store so that `color` comes out on the pins.
+    pub const fn encode(self, color: u16) -> [u8; 2] {
+        let mut bytes = if self.two_byte {
+            // Reversing all bits and swapping the bytes commute.
+            let color = if self.reverse_bits {
+                color.reverse_bits()
+            } else {
+                color
+            };
+            color.to_le_bytes()
+        } else {
+            let [hi, lo] = color.to_be_bytes();
+            if self.reverse_bits {
+                [hi.reverse_bits(), lo.reverse_bits()]
+            } else {
+                [hi, lo]
+            }
+        };
+        if self.swap_bytes {
+            bytes = [bytes[1], bytes[0]];
+        }
+        bytes
+    }
+
+    /// The color that `bytes` put on the pins, the reverse of
+    /// [Self::encode].
+    pub const fn decode(self, bytes: [u8; 2]) -> u16 {
+        let [a, b] = if self.swap_bytes {
+            [bytes[1], bytes[0]]
+        } else {
+            bytes
+        };
+        if self.two_byte {
+            let color = u16::from_le_bytes([a, b]);
+            if self.reverse_bits {
+                color.reverse_bits()
+            } else {
+                color
+            }
+        } else if self.reverse_bits {
+            u16::from_be_bytes([a.reverse_bits(), b.reverse_bits()])
+        } else {
+            u16::from_be_bytes([a, b])
+        }
+    }
+
+    /// The byte order of pixels in memory, see [PixelEndian].
+    pub const fn endianness(self) -> PixelEndian {
+        let big = if self.two_byte {
+            self.reverse_bits != self.swap_bytes
+        } else {
+            !self.swap_bytes
+        };
+        if big {
+            PixelEndian::Big
+        } else {
+            PixelEndian::Little
+        }
+    }
+
+    const fn to_bits(self) -> u8 {
+        self.two_byte as u8 | ((self.swap_bytes as u8) << 1) | ((self.reverse_bits as u8) << 2)
+    }
+
+    const fn from_bits(bits: u8) -> Self {
+        Self {
+            two_byte: bits & 1 != 0,
+            swap_bytes: bits & 2 != 0,
+            reverse_bits: bits & 4 != 0,
+        }
+    }
+}
+
+static WIRE_FORMAT: AtomicU8 = AtomicU8::new(WireFormat::DEFAULT.to_bits());
+
+/// Sets the [WireFormat] pixel conversions use from now on. Call it with
+/// the `Format` the DPI is configured with, before rendering.
+pub fn set_wire_format(format: WireFormat) {
+    WIRE_FORMAT.store(format.to_bits(), Ordering::Relaxed);
+}
+
+/// The [WireFormat] last set with [set_wire_format].
+pub fn wire_format() -> WireFormat {
+    WireFormat::from_bits(WIRE_FORMAT.load(Ordering::Relaxed))
+}
+
+/// The byte order pixels need for the DPI `format`, see the table on
+/// [PixelEndian]. Bit reversal comes on top; pack pixels with
+/// [WireFormat::encode] to get both.
+pub const fn recommended_endianness(format: &Format) -> PixelEndian {
+    WireFormat::from_format(format).endianness()
+}
+
+/// RGB565 pixels as values, red in the top bits, before they're laid out
+/// for the bus. Only [to_wire] turns them into [WireBytes].
+#[derive(Debug, Clone, Copy)]
+pub struct LogicalPixels<'a>(pub &'a [u16]);
+
+/// Mutable [LogicalPixels], the output of [from_wire].
+#[derive(Debug)]
+pub struct LogicalPixelsMut<'a>(pub &'a mut [u16]);
+
+/// Pixel data in the byte order it's pushed to the DMA, see
+/// [DmaTxStreamBuf::push_wire](crate::dma::DmaTxStreamBuf::push_wire).
+#[derive(Debug, Clone, Copy)]
+pub struct WireBytes<'a>(pub &'a [u8]);
+
+/// Mutable [WireBytes], the output of [to_wire].
+#[derive(Debug)]
+pub struct WireBytesMut<'a>(pub &'a mut [u8]);
+
+impl<'a> From<&'a [u16]> for LogicalPixels<'a> {
+    fn from(pixels: &'a [u16]) -> Self {
+        Self(pixels)
+    }
+}
+
+impl<'a> From<&'a mut [u16]> for LogicalPixelsMut<'a> {
+    fn from(pixels: &'a mut [u16]) -> Self {
+        Self(pixels)
+    }
+}
+
+impl<'a> From<&'a [u8]> for WireBytes<'a> {
+    fn from(bytes: &'a [u8]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl<'a> From<&'a mut [u8]> for WireBytesMut<'a> {
+    fn from(bytes: &'a mut [u8]) -> Self {
+        Self(bytes)
+    }
+}
+
+/// Lays out `src` in `dst` as `wire` says, as many pixels as both hold.
+/// Returns the pixels written.
+///
+/// Frame data in this crate is in the current [wire_format].
+pub fn to_wire(src: LogicalPixels<'_>, dst: WireBytesMut<'_>, wire: WireFormat) -> usize {
+    let pixels = src.0.iter().zip(dst.0.chunks_exact_mut(2));
+    let n = pixels.len();
+    for (&color, dst) in pixels {
+        dst.copy_from_slice(&wire.encode(color));
+    }
+    n
+}
+
+/// The reverse of [to_wire]: reads pixels laid out as `wire` says from
+/// `src` into `dst`. Returns the pixels read.
+pub fn from_wire(src: WireBytes<'_>, dst: LogicalPixelsMut<'_>, wire: WireFormat) -> usize {
+    let pixels = src.0.chunks_exact(2).zip(dst.0.iter_mut());
+    let n = pixels.len();
+    for (src, dst) in pixels {
+        *dst = wire.decode([src[0], src[1]]);
+    }
+    n
+}
+
+/// Pixel `i` of the byte identity pattern: high byte `i`, low byte `!i`.
+///
+/// 256 of them carry every byte value in both halves of a pixel, so any
+/// swap, reversal or shift of bytes or bits shows up as a mismatch, see
+/// [DmaTxStreamBuf::check_wire_format](crate::dma::DmaTxStreamBuf::check_wire_format).
+pub const fn identity_pixel(i: u8) -> u16 {
+    u16::from_be_bytes([i, !i])
+}
+
+/// Byte layout of pixels on the DPI bus.
+///
+/// Each format has to match both the DPI [Format] and the panel's pixel
+/// format, set with COLMOD (`0x3A`). [St7701::init] currently writes `0x60`
+/// (18-bit) while the DPI sends 16-bit pixels; that works because the panel
+/// wires its unused low bits, but [PixelFormat::Rgb565] with `0x50` is the
+/// consistent pairing.
+///
+/// [Format]: esp_hal::lcd_cam::lcd::dpi::Format
+/// [St7701::init]: crate::display::st7701::St7701::init
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// Two bytes per pixel, RGB565 in the current [WireFormat]. Pairs with
+    /// `enable_2byte_mode: true` and COLMOD `0x50`.
+    Rgb565,
+    /// Three bytes per pixel, red, green, blue, each with its 6 bits in the
+    /// top of the byte. Pairs with `enable_2byte_mode: false`, which clocks
+    /// one byte per PCLK on D0 to D7, and COLMOD `0x60`: serial RGB over 8
+    /// lanes, one channel per PCLK. That isn't the 18-bit parallel
+    /// interface, which needs all 18 bits in a single PCLK.
+    Rgb666Packed,
+}
+
+impl PixelFormat {
+    /// Bytes one pixel takes in the frame data.
+    pub const fn bytes_per_pixel(self) -> usize {
+        match self {
+            Self::Rgb565 => 2,
+            Self::Rgb666Packed => 3,
+        }
+    }
+
+    /// Writes the 8-bit per channel color `(r, g, b)` to the start of `dst`,
+    /// dropping the low bits that don't fit. Returns the bytes written.
+    ///
+    /// Panics if `dst` is shorter than [Self::bytes_per_pixel].
+    pub fn encode(self, r: u8, g: u8, b: u8, dst: &mut [u8]) -> usize {
+        match self {
+            Self::Rgb565 => {
+                let color = join(r as u16 >> 3, g as u16 >> 2, b as u16 >> 3);
+                dst[..2].copy_from_slice(&wire_format().encode(color));
+            }
+            Self::Rgb666Packed => dst[..3].copy_from_slice(&pack666(r, g, b)),
+        }
+        self.bytes_per_pixel()
+    }
+}
+
+/// The [PixelFormat::Rgb666Packed] bytes for `(r, g, b)`.
+pub const fn pack666(r: u8, g: u8, b: u8) -> [u8; 3] {
+    [r & 0xFC, g & 0xFC, b & 0xFC]
+}
+
+/// Converts packed RGB888 pixels (red, green, blue bytes) in `src` to
+/// RGB565 in `dst`, as many as both hold.
+///
+/// The main loop reads four pixels as three `u32` words and writes four
+/// results at a time, with fixed-size chunks so the compiler drops the
+/// per-pixel bounds checks; the remaining up to three pixels are converted
+/// one by one.
+pub fn convert_rgb888_to_565(src: &[u8], dst: &mut [u16]) {
+    let pixels = (src.len() / 3).min(dst.len());
+    let (src, dst) = (&src[..pixels * 3], &mut dst[..pixels]);
+
+    let mut src_quads = src.chunks_exact(12);
+    let mut dst_quads = dst.chunks_exact_mut(4);
+    for (s, d) in (&mut src_quads).zip(&mut dst_quads) {
+        let word = |i: usize| u32::from_le_bytes([s[i], s[i + 1], s[i + 2], s[i + 3]]);
+        // r0 g0 b0 r1 | g1 b1 r2 g2 | b2 r3 g3 b3, first byte lowest.
+        let (w0, w1, w2) = (word(0), word(4), word(8));
+        let pack = |r: u32, g: u32, b: u32| {
+            (((r & 0xF8) << 8) | ((g & 0xFC) << 3) | ((b & 0xFF) >> 3)) as u16
+        };
+
+        d[0] = pack(w0, w0 >> 8, w0 >> 16);
+        d[1] = pack(w0 >> 24, w1, w1 >> 8);
+        d[2] = pack(w1 >> 16, w1 >> 24, w2);
+        d[3] = pack(w2 >> 8, w2 >> 16, w2 >> 24);
+    }
+
+    let tail = src_quads.remainder().chunks_exact(3);
+    for (s, d) in tail.zip(dst_quads.into_remainder()) {
+        *d = join(s[0] as u16 >> 3, s[1] as u16 >> 2, s[2] as u16 >> 3);
+    }
+}
+
+/// Brightness scale factors for each of the [BRIGHTNESS_LEVELS], following a
+/// gamma curve so that equal level steps look like equal brightness steps.
+pub struct GammaLut {
+    // `(level / 31) ^ gamma` with 8 fractional bits.
+    scale: [u16; BRIGHTNESS_LEVELS],
+}
+
+impl GammaLut {
+    /// Builds the table for a gamma of `num / den`, e.g. `GammaLut::new(11, 5)`
+    /// for 2.2. Integer-only, meant to be evaluated into a `static`:
+    ///
+    /// ```ignore
+    /// static GAMMA: GammaLut = GammaLut::new(11, 5);
+    /// ```
+    pub const fn new(num: u32, den: u32) -> Self {
+        let mut scale = [0; BRIGHTNESS_LEVELS];
+
+        let mut level = 0;
+        while level < BRIGHTNESS_LEVELS {
+            let x = level as u64 * ONE / MAX_LEVEL as u64;
+            scale[level] = (pow_frac_q(x, num, den) >> (Q - 8)) as u16;
+            level += 1;
+        }
+
+        Self { scale }
+    }
+
+    /// Per channel lookup tables for dimming to `level`.
+    pub fn channels(&self, level: u8) -> ChannelLuts {
+        let scale = self.scale[level.min(MAX_LEVEL) as usize];
+        let dim = |value: usize| ((value as u16 * scale + 0x80) >> 8) as u8;
+
+        ChannelLuts {
+            red_blue: core::array::from_fn(dim),
+            green: core::array::from_fn(dim),
+        }
+    }
+}
+
+/// Dimmed channel values for one brightness level, see [GammaLut::channels].
+pub struct ChannelLuts {
+    red_blue: [u8; 32],
+    green: [u8; 64],
+}
+
+impl ChannelLuts {
+    /// Arbitrary per channel tables, e.g. for color correction. Red and blue
+    /// share `red_blue`.
+    pub const fn new(red_blue: [u8; 32], green: [u8; 64]) -> Self {
+        Self { red_blue, green }
+    }
+
+    /// Dims `color` with three table lookups.
+    pub fn dim(&self, color: u16) -> u16 {
+        let (r, g, b) = split(color);
+        join(
+            self.red_blue[r as usize] as u16,
+            self.green[g as usize] as u16,
+            self.red_blue[b as usize] as u16,
+        )
+    }
+}
+
+/// How fade helpers dim pixels.
+#[derive(Clone, Copy)]
+pub enum DimMode<'a> {
+    /// Bit shifts, see [dim565]. Only six distinct steps.
+    Fast,
+    /// Gamma-correct table lookups, see [GammaLut].
+    Gamma(&'a GammaLut),
+}
+
+/// One brightness level of a [DimMode], ready to apply per pixel.
+enum Dimmer {
+    Black,
+    Shift(u32),
+    Lut(ChannelLuts),
+}
+
+impl Dimmer {
+    fn new(level: u8, mode: DimMode<'_>) -> Self {
+        match mode {
+            DimMode::Fast if level == 0 => Self::Black,
+            // 31 maps to no shift, 1 to a shift of 5 which leaves only the
+            // top green bit.
+            DimMode::Fast => Self::Shift((MAX_LEVEL - level.min(MAX_LEVEL)).div_ceil(6) as u32),
+            DimMode::Gamma(lut) => Self::Lut(lut.channels(level)),
+        }
+    }
+
+    fn dim(&self, color: u16) -> u16 {
+        match self {
+            Self::Black => 0,
+            Self::Shift(shift) => dim565(color, *shift),
+            Self::Lut(channels) => channels.dim(color),
+        }
+    }
+}
+
+/// Writes `src` dimmed to `level` into `dst`, where
+/// [BRIGHTNESS_LEVELS]` - 1` leaves it unchanged and `0` is black.
+///
+/// Works on whole frames or single scanlines; stepping `level` down once per
+/// frame fades to black. Both slices hold RGB565 pixels in the current
+/// [WireFormat] and only the overlapping whole pixels are written.
+pub fn fade_to_black(src: &[u8], dst: &mut [u8], level: u8, mode: DimMode<'_>) {
+    let dimmer = Dimmer::new(level, mode);
+    let wire = wire_format();
+
+    for (src, dst) in src.chunks_exact(2).zip(dst.chunks_exact_mut(2)) {
+        let color = wire.decode([src[0], src[1]]);
+        dst.copy_from_slice(&wire.encode(dimmer.dim(color)));
+    }
+}
+
+/// [fade_to_black] with `pixels` as both source and destination.
+pub fn fade_in_place(pixels: &mut [u8], level: u8, mode: DimMode<'_>) {
+    let dimmer = Dimmer::new(level, mode);
+    let wire = wire_format();
+
+    for pixel in pixels.chunks_exact_mut(2) {
+        let color = wire.decode([pixel[0], pixel[1]]);
+        pixel.copy_from_slice(&wire.encode(dimmer.dim(color)));
+    }
+}
+
+/// Writes `src` with `overlay` blended on top at opacity `alpha` into `dst`,
+/// for dimming or tinting a scanline as it's streamed, e.g. from the render
+/// callback of [stream_frames](crate::stream::stream_frames).
+///
+/// `alpha` is scaled to `0..=256` so that `255` is fully opaque, and each
+/// channel is `(src * (256 - a) + overlay * a) >> 8` in 16-bit integers,
+/// with the overlay term computed once per call. That's two multiplies per
+/// channel plus packing, roughly 20 cycles per pixel; at 480 pixels and an
+/// 18MHz pclk a line leaves about 30 cycles per pixel at 240MHz, so check the
+/// margin with a [LineBudget](crate::stream::LineBudget) if the callback
+/// does more. Both slices hold RGB565 pixels in the current [WireFormat].
+pub fn blend_overlay(src: &[u8], dst: &mut [u8], overlay: u16, alpha: u8) {
+    let a = alpha as u16 + (alpha >> 7) as u16;
+    let inv = 256 - a;
+    let (r, g, b) = split(overlay);
+    let (r, g, b) = (r * a, g * a, b * a);
+    let wire = wire_format();
+
+    for (src, dst) in src.chunks_exact(2).zip(dst.chunks_exact_mut(2)) {
+        let (sr, sg, sb) = split(wire.decode([src[0], src[1]]));
+        let blended = join(
+            (sr * inv + r) >> 8,
+            (sg * inv + g) >> 8,
+            (sb * inv + b) >> 8,
+        );
+        dst.copy_from_slice(&wire.encode(blended));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_rgb888_to_565_matches_naive() {
+        // xorshift32, so the pixels are the same on every run.
+        let mut state = 0x1234_5678u32;
+        let src: [u8; 3001 * 3] = core::array::from_fn(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        });
+
+        // Lengths around the four pixel main loop, to cover every tail.
+        for pixels in [0, 1, 2, 3, 4, 5, 7, 8, 3001] {
+            let src = &src[..pixels * 3];
+            let mut dst = [0; 3001];
+            convert_rgb888_to_565(src, &mut dst);
+
+            for (i, s) in src.chunks_exact(3).enumerate() {
+                let expected = join(s[0] as u16 >> 3, s[1] as u16 >> 2, s[2] as u16 >> 3);
+                assert_eq!(dst[i], expected, "pixel {i} of {pixels}");
+            }
+            assert!(dst[pixels..].iter().all(|&d| d == 0), "wrote past {pixels}");
+        }
+    }
+
+    /// [blend_overlay] on a single pixel.
+    fn blend(src: u16, overlay: u16, alpha: u8) -> u16 {
+        let wire = wire_format();
+        let mut dst = [0; 2];
+        blend_overlay(&wire.encode(src), &mut dst, overlay, alpha);
+        wire.decode(dst)
+    }
+
+    #[test]
+    fn blend_overlay_known_values() {
+        const WHITE: u16 = 0xFFFF;
+        const RED: u16 = 0xF800;
+
+        // Transparent leaves the source, opaque replaces it.
+        assert_eq!(blend(WHITE, RED, 0), WHITE);
+        assert_eq!(blend(0x1234, RED, 255), RED);
+        // Half black over white: alpha 128 scales to 129/256, so each
+        // channel keeps 127/256 of its maximum, rounded down.
+        assert_eq!(blend(WHITE, 0, 128), join(15, 31, 15));
+        // Half red over black: only red rises, to 31 * 129 / 256.
+        assert_eq!(blend(0, RED, 128), join(15, 0, 0));
+    }
+
+    #[test]
+    fn blend_overlay_stops_at_the_shorter_slice() {
+        let wire = wire_format();
+        let src = [wire.encode(0xFFFF); 3].concat();
+        let mut dst = [0xAA; 4];
+        blend_overlay(&src, &mut dst, 0, 255);
+        assert_eq!(dst, [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn mre_format_sends_red_reversed_and_big_endian() {
+        // The `Format` `main` configures.
+        let format = Format {
+            enable_2byte_mode: true,
+            bit_order: BitOrder::Inverted,
+            ..Default::default()
+        };
+        assert_eq!(recommended_endianness(&format), PixelEndian::Big);
+        assert_eq!(
+            WireFormat::from_format(&format).encode(0xF800),
+            [0x1F, 0x00]
+        );
+    }
+
+    #[test]
+    fn rgb666_packs_red_into_the_top_bits() {
+        assert_eq!(pack666(0xFF, 0, 0), [0xFC, 0x00, 0x00]);
+
+        let mut dst = [0xAA; 4];
+        assert_eq!(PixelFormat::Rgb666Packed.encode(0xFF, 0, 0, &mut dst), 3);
+        assert_eq!(dst, [0xFC, 0x00, 0x00, 0xAA]);
+    }
+}
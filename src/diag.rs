@@ -0,0 +1,96 @@
+//! Diagnostics that survive a soft reset, kept in RTC fast memory.
+//!
+//! RTC fast memory keeps its contents through software, watchdog and panic
+//! resets and is only lost with power. A record is only trusted if its magic
+//! and check word match, so whatever is found there after power-on counts
+//! as a first boot.
+
+use core::ptr::addr_of_mut;
+
+use crate::hal::ram;
+
+const MAGIC: u32 = u32::from_le_bytes(*b"BOOT");
+
+// Magic, boot count and the count inverted.
+#[ram(rtc_fast, persistent)]
+static mut BOOT_RECORD: [u32; 3] = [0; 3];
+
+/// Counts this boot and returns the count, `1` after power-on. Call once,
+/// early in `main`.
+pub fn count_boot() -> u32 {
+    let count = boot_count().map_or(1, |count| count.wrapping_add(1));
+    // SAFETY: Only accessed from the main thread, with volatile accesses so
+    // the record is actually written before a reset can hit.
+    unsafe { addr_of_mut!(BOOT_RECORD).write_volatile([MAGIC, count, !count]) };
+    count
+}
+
+/// The count of the last [count_boot], or `None` if the record isn't valid,
+/// i.e. nothing has counted since power-on.
+pub fn boot_count() -> Option<u32> {
+    // SAFETY: See `count_boot`.
+    let [magic, count, check] = unsafe { addr_of_mut!(BOOT_RECORD).read_volatile() };
+    (magic == MAGIC && check == !count).then_some(count)
+}
+
+/// A fixed-size binary status report, for builds without logging or
+/// `core::fmt`.
+///
+/// Sent as [Self::SIZE] little-endian bytes in field order, which a host
+/// script decodes with [STATUS_LAYOUT]. `magic` marks the start of a record
+/// in a byte stream; bump `version` when fields change.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatusRecord {
+    pub magic: [u8; 4],
+    pub version: u16,
+    /// Frames per second times 10.
+    pub fps_x10: u16,
+    pub underruns: u32,
+    pub heap_used: u32,
+    /// A checksum of the last frame, to tell a frozen picture from a live
+    /// one.
+    pub frame_crc: u32,
+}
+
+/// [StatusRecord] as a Python `struct` format: `struct.unpack(STATUS_LAYOUT,
+/// data)` yields the fields in order.
+pub const STATUS_LAYOUT: &str = "<4sHHIII";
+
+impl StatusRecord {
+    pub const MAGIC: [u8; 4] = *b"STAT";
+    pub const SIZE: usize = core::mem::size_of::<Self>();
+    pub const VERSION: u16 = 1;
+
+    /// A record of the current version, with the heap usage read now.
+    pub fn new(fps_x10: u16, underruns: u32, frame_crc: u32) -> Self {
+        Self {
+            magic: Self::MAGIC,
+            version: Self::VERSION,
+            fps_x10,
+            underruns,
+            heap_used: esp_alloc::HEAP.used() as u32,
+            frame_crc,
+        }
+    }
+
+    /// The record's wire bytes.
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        const { assert!(StatusRecord::SIZE == 20, "StatusRecord has padding") };
+
+        let mut bytes = [0; Self::SIZE];
+        bytes[0..4].copy_from_slice(&self.magic);
+        bytes[4..6].copy_from_slice(&self.version.to_le_bytes());
+        bytes[6..8].copy_from_slice(&self.fps_x10.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.underruns.to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.heap_used.to_le_bytes());
+        bytes[16..20].copy_from_slice(&self.frame_crc.to_le_bytes());
+        bytes
+    }
+
+    /// Hands the record's bytes to `sink` in one call, e.g. a UART or RTT
+    /// write.
+    pub fn emit_status(&self, sink: &mut impl FnMut(&[u8])) {
+        sink(&self.to_bytes());
+    }
+}
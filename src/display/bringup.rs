@@ -0,0 +1,282 @@
+//! Panel bringup split into short steps, for firmware that can't block.
+
+use crate::{
+    color::WireBytes,
+    display::st7701::{InitEntry, SpiProvider, St7701},
+    dma::DmaTxStreamBuf,
+    hal::{
+        DriverMode,
+        lcd_cam::lcd::dpi::{Dpi, DpiTransfer},
+        time::{Duration, Instant},
+    },
+};
+
+/// Bytes pushed into the ring per [BringupStateMachine::poll] while
+/// prefilling.
+const PREFILL_BYTES_PER_POLL: usize = 4096;
+
+/// Where a [BringupStateMachine] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BringupState {
+    Reset,
+    Init,
+    SleepOut,
+    DisplayOn,
+    Prefill,
+    Start,
+    Backlight,
+    Done,
+    /// An SPI or DMA error stopped the bringup, see
+    /// [BringupStateMachine::take_error].
+    Failed,
+}
+
+/// Errors that stop a [BringupStateMachine].
+#[derive(Debug)]
+pub enum BringupError<E> {
+    Spi(E),
+    Dma(crate::hal::dma::DmaError),
+}
+
+/// The steps of [St7701::init] plus prefill, stream start and a backlight
+/// fade, advanced a little on every [Self::poll] instead of in one blocking
+/// call.
+///
+/// Delays become deadlines checked on the next poll, so a superloop can keep
+/// running other tasks in between. The panel registers come from a table of
+/// [InitEntry]s, `entries_per_poll` at a time; the table should hold neither
+/// a reset nor sleep-out and display-on, the machine does those itself and
+/// skips [InitEntry::Reset].
+///
+/// Prefilling the ring doesn't need the panel, so every poll that would
+/// otherwise only wait for a reset or sleep-out deadline pushes a chunk of
+/// `prefill` instead, and the stream starts right after display-on. Reset
+/// uses the ST7701 minimums: a 10ms low pulse, 5ms before the first command
+/// and 120ms before sleep-out, the latter running in parallel with the init
+/// table. Each phase change is logged with the time since the first poll.
+///
+/// A poll takes as long as its SPI commands. With [ManualSpi] that's about
+/// 2ms per command with its default chip select delays, so keep
+/// `entries_per_poll` at 1 there unless they're shortened with
+/// `ManualSpi::with_cs_timing`; a hardware SPI stays well below 1ms.
+///
+/// [ManualSpi]: crate::display::st7701::ManualSpi
+pub struct BringupStateMachine<'a, 'd, 's, S: SpiProvider, Dm: DriverMode, B> {
+    panel: St7701<'a, S>,
+    sequence: &'s [InitEntry],
+    entries_per_poll: usize,
+    prefill: &'s [u8],
+    backlight: B,
+    fade: Duration,
+
+    state: BringupState,
+    // Progress within the state: the reset phase or the next init entry.
+    step: usize,
+    wait_until: Option<Instant>,
+    // Sleep-out has to wait 120ms after reset, however fast the init table.
+    sleep_out_at: Option<Instant>,
+    started: Option<Instant>,
+    prefilled: bool,
+    fade_start: Option<Instant>,
+
+    dpi: Option<Dpi<'d, Dm>>,
+    buf: Option<DmaTxStreamBuf>,
+    transfer: Option<DpiTransfer<'d, DmaTxStreamBuf, Dm>>,
+    error: Option<BringupError<<S as SpiProvider>::Error>>,
+}
+
+impl<'a, 'd, 's, S, Dm, B> BringupStateMachine<'a, 'd, 's, S, Dm, B>
+where
+    S: SpiProvider,
+    Dm: DriverMode,
+    B: FnMut(u8),
+{
+    /// `prefill` is repeated into `buf` until it's full before the stream
+    /// starts, then `backlight` is ramped from 0 to 255 over `fade`.
+    pub fn new(
+        panel: St7701<'a, S>,
+        sequence: &'s [InitEntry],
+        dpi: Dpi<'d, Dm>,
+        buf: DmaTxStreamBuf,
+        prefill: &'s [u8],
+        backlight: B,
+        fade: Duration,
+    ) -> Self {
+        Self {
+            panel,
+            sequence,
+            entries_per_poll: 1,
+            prefill,
+            backlight,
+            fade,
+            state: BringupState::Reset,
+            step: 0,
+            wait_until: None,
+            sleep_out_at: None,
+            started: None,
+            prefilled: false,
+            fade_start: None,
+            dpi: Some(dpi),
+            buf: Some(buf),
+            transfer: None,
+            error: None,
+        }
+    }
+
+    /// Sets how many init table entries are sent per poll.
+    pub fn with_entries_per_poll(mut self, n: usize) -> Self {
+        self.entries_per_poll = n.max(1);
+        self
+    }
+
+    /// Advances the bringup as far as it can without waiting.
+    pub fn poll(&mut self, now: Instant) -> BringupState {
+        self.started.get_or_insert(now);
+
+        if let Some(until) = self.wait_until {
+            if now < until {
+                if self.buf.is_some() && !self.prefilled {
+                    self.prefill_step(now);
+                }
+                return self.state;
+            }
+            self.wait_until = None;
+        }
+
+        if let Err(err) = self.advance(now) {
+            self.error = Some(err);
+            self.state = BringupState::Failed;
+        }
+
+        self.state
+    }
+
+    fn advance(&mut self, now: Instant) -> Result<(), BringupError<S::Error>> {
+        let wait = |ms| Some(now + Duration::from_millis(ms));
+
+        match self.state {
+            BringupState::Reset => {
+                let step = self.step;
+                self.step += 1;
+                match (self.panel.reset_pin(), step) {
+                    (None, _) => {
+                        self.panel.command(0x01, &[]).map_err(BringupError::Spi)?; // Software Reset
+                        self.sleep_out_at = wait(120);
+                        self.wait_until = wait(5);
+                        self.enter(BringupState::Init, now);
+                    }
+                    (Some(rst), 0) => {
+                        rst.set_low();
+                        self.wait_until = wait(10);
+                    }
+                    (Some(rst), _) => {
+                        rst.set_high();
+                        self.sleep_out_at = wait(120);
+                        self.wait_until = wait(5);
+                        self.enter(BringupState::Init, now);
+                    }
+                }
+            }
+            BringupState::Init => {
+                let end = (self.step + self.entries_per_poll).min(self.sequence.len());
+                while self.step < end {
+                    let entry = &self.sequence[self.step];
+                    self.step += 1;
+                    match entry {
+                        InitEntry::WriteReg { cmd, data } => {
+                            self.panel.command(*cmd, data).map_err(BringupError::Spi)?;
+                        }
+                        InitEntry::DelayMs(ms) => {
+                            self.wait_until = wait(*ms as u64);
+                            return Ok(());
+                        }
+                        InitEntry::Reset => {}
+                    }
+                }
+
+                if self.step == self.sequence.len() {
+                    match self.sleep_out_at {
+                        Some(at) if now < at => self.wait_until = Some(at),
+                        _ => {
+                            self.panel.command(0x11, &[]).map_err(BringupError::Spi)?; // Sleep Out
+                            self.wait_until = wait(120);
+                            self.enter(BringupState::SleepOut, now);
+                        }
+                    }
+                }
+            }
+            BringupState::SleepOut => {
+                self.panel.command(0x29, &[]).map_err(BringupError::Spi)?; // Display On
+                self.enter(BringupState::DisplayOn, now);
+            }
+            BringupState::DisplayOn => self.enter(BringupState::Prefill, now),
+            BringupState::Prefill => {
+                if self.prefilled {
+                    self.enter(BringupState::Start, now);
+                } else {
+                    self.prefill_step(now);
+                }
+            }
+            BringupState::Start => {
+                let dpi = self.dpi.take().unwrap();
+                let buf = self.buf.take().unwrap();
+                let transfer = dpi
+                    .send(true, buf)
+                    .map_err(|(err, ..)| BringupError::Dma(err))?;
+                self.transfer = Some(transfer);
+                self.fade_start = Some(now);
+                self.enter(BringupState::Backlight, now);
+            }
+            BringupState::Backlight => {
+                let elapsed = now - self.fade_start.unwrap();
+                if elapsed >= self.fade {
+                    (self.backlight)(u8::MAX);
+                    self.enter(BringupState::Done, now);
+                } else {
+                    let level = elapsed.as_micros() * 255 / self.fade.as_micros().max(1);
+                    (self.backlight)(level as u8);
+                }
+            }
+            BringupState::Done | BringupState::Failed => {}
+        }
+
+        Ok(())
+    }
+
+    /// Pushes up to [PREFILL_BYTES_PER_POLL] bytes of `prefill` into the
+    /// ring, noting when it's full.
+    fn prefill_step(&mut self, now: Instant) {
+        let buf = self.buf.as_mut().unwrap();
+        let mut pushed = 0;
+        while pushed < PREFILL_BYTES_PER_POLL {
+            let n = buf.push_wire(WireBytes(self.prefill));
+            pushed += n;
+            if n < self.prefill.len() {
+                self.prefilled = true;
+                info!("Bringup: ring prefilled at {}ms", self.elapsed_ms(now));
+                break;
+            }
+        }
+    }
+
+    fn enter(&mut self, state: BringupState, now: Instant) {
+        self.state = state;
+        self.step = 0;
+        info!("Bringup: {:?} at {}ms", state, self.elapsed_ms(now));
+    }
+
+    fn elapsed_ms(&self, now: Instant) -> u64 {
+        self.started
+            .map_or(0, |started| (now - started).as_millis())
+    }
+
+    /// The error that moved the machine to [BringupState::Failed].
+    pub fn take_error(&mut self) -> Option<BringupError<S::Error>> {
+        self.error.take()
+    }
+
+    /// Hands back the panel and, once started, the running transfer.
+    pub fn finish(self) -> (St7701<'a, S>, Option<DpiTransfer<'d, DmaTxStreamBuf, Dm>>) {
+        (self.panel, self.transfer)
+    }
+}
@@ -0,0 +1,212 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::hal::{
+    lcd_cam::lcd::{ClockMode, Phase, Polarity},
+    time::Rate,
+};
+#[cfg(target_arch = "xtensa")]
+use crate::{
+    display::vsync,
+    hal::{DriverMode, clock::Clocks, lcd_cam::lcd::dpi::Dpi, peripherals::LCD_CAM},
+};
+
+/// All four pixel clock polarity/phase combinations, in the order
+/// [ClockModeExt::try_all] tries them.
+///
+/// What a wrong combination typically looks like on a panel that latches data
+/// on the rising edge of PCLK (the ST7701 default):
+///
+/// - `IdleLow` / `ShiftLow`: data changes on the edge the panel samples on.
+///   Pixels sparkle or shimmer, colors flicker between neighbouring values,
+///   often worse at higher pclk.
+/// - `IdleLow` / `ShiftHigh`: correct; the mode this example uses.
+/// - `IdleHigh` / `ShiftLow`: stable colors but every line is shifted by one
+///   pixel, with a garbage column at the left or right edge.
+/// - `IdleHigh` / `ShiftHigh`: both of the above, a one pixel shift plus
+///   sparkling pixels.
+///
+/// On a panel that latches on the falling edge the first two swap roles, and
+/// the symptom of the wrong one is shimmering or smeared verticals, a one
+/// pixel horizontal smear on sharp edges. That means the wrong latch edge,
+/// see [LatchEdge].
+pub const CLOCK_MODES: [ClockMode; 4] = [
+    ClockMode {
+        polarity: Polarity::IdleLow,
+        phase: Phase::ShiftLow,
+    },
+    ClockMode {
+        polarity: Polarity::IdleLow,
+        phase: Phase::ShiftHigh,
+    },
+    ClockMode {
+        polarity: Polarity::IdleHigh,
+        phase: Phase::ShiftLow,
+    },
+    ClockMode {
+        polarity: Polarity::IdleHigh,
+        phase: Phase::ShiftHigh,
+    },
+];
+
+/// Which PCLK edge a panel latches data on.
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LatchEdge {
+    /// Latches on the rising edge, the ST7701 default.
+    RisingEdgeLatch,
+    /// Latches on the falling edge.
+    FallingEdgeLatch,
+}
+
+impl LatchEdge {
+    /// The [ClockMode] that changes data on the opposite edge, so it is
+    /// stable when the panel samples it.
+    pub const fn clock_mode(self) -> ClockMode {
+        match self {
+            Self::RisingEdgeLatch => ClockMode {
+                polarity: Polarity::IdleLow,
+                phase: Phase::ShiftHigh,
+            },
+            Self::FallingEdgeLatch => ClockMode {
+                polarity: Polarity::IdleLow,
+                phase: Phase::ShiftLow,
+            },
+        }
+    }
+}
+
+/// Bring-up helpers for [ClockMode].
+pub trait ClockModeExt: Sized {
+    /// Calls `check` with each of [CLOCK_MODES] until it returns `true`, and
+    /// returns that mode.
+    ///
+    /// `check` is expected to apply the mode to the DPI config, show a test
+    /// pattern and decide whether it came out right, either by asking the
+    /// user or with a loopback check.
+    fn try_all(check: impl FnMut(ClockMode) -> bool) -> Option<Self>;
+}
+
+impl ClockModeExt for ClockMode {
+    fn try_all(mut check: impl FnMut(ClockMode) -> bool) -> Option<Self> {
+        CLOCK_MODES.into_iter().find(|mode| check(*mode))
+    }
+}
+
+/// Root clock of the LCD_CAM pixel clock (`LCD_CLK_SEL`).
+///
+/// PCLK is the source divided by an integer from 2 to 256 and then by 2 (the
+/// errata minimum), so each source reaches a different range:
+///
+/// - [ClockSource::Xtal], 40MHz: 78kHz to 10MHz, lowest jitter
+/// - [ClockSource::Cpu], 240MHz with `CpuClock::max()`: 469kHz to 60MHz
+/// - [ClockSource::Pll160], 160MHz: 313kHz to 40MHz
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockSource {
+    Xtal   = 1,
+    Cpu    = 2,
+    Pll160 = 3,
+}
+
+#[cfg(target_arch = "xtensa")]
+impl ClockSource {
+    /// Frequency of the source with the current clock configuration.
+    pub fn frequency(self) -> Rate {
+        let clocks = Clocks::get();
+        match self {
+            Self::Xtal => clocks.xtal_clock,
+            Self::Cpu => clocks.cpu_clock,
+            Self::Pll160 => clocks.crypto_pwm_clock,
+        }
+    }
+}
+
+/// The requested pixel clock can't be divided down from the chosen
+/// [ClockSource].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnreachableClock {
+    pub min: Rate,
+    pub max: Rate,
+}
+
+static CLOCK_GATED: AtomicBool = AtomicBool::new(false);
+
+/// Whether [DpiClockExt::set_clock_gating] last enabled gating.
+pub fn is_clock_gated() -> bool {
+    CLOCK_GATED.load(Ordering::Relaxed)
+}
+
+/// Clock control for [Dpi] that esp-hal doesn't expose.
+pub trait DpiClockExt {
+    /// Lets the LCD_CAM gate its register clock when idle (`CLK_EN` in
+    /// `LCD_CAM_LCD_CLOCK_REG`, cleared to gate).
+    ///
+    /// This is the only clock gate the ESP32-S3 LCD_CAM documents. It does
+    /// not stop PCLK: in DPI mode the pixel clock keeps running through
+    /// blanking, and `LCD_CLK_EQU_SYSCLK` only bypasses the divider. Expect a
+    /// small power saving and no change in EMI.
+    ///
+    /// The clock must not be gated while a descriptor is mid-transfer, so
+    /// gating may only be enabled from the VSYNC interrupt handler, before it
+    /// clears the VSYNC flag, and must be disabled before the next
+    /// `Dpi::send`. Both are checked with debug assertions; the send side by
+    /// the helpers in [looped](crate::looped).
+    fn set_clock_gating(&mut self, enable: bool);
+
+    /// Derives PCLK from `source` instead of the root esp-hal picked, with
+    /// the integer divider closest to `pclk`. Returns the resulting PCLK.
+    ///
+    /// esp-hal's DPI `Config` has no clock source option and always picks
+    /// the first root that can hit its frequency, so this is applied on the
+    /// [Dpi] instead: call it after `Dpi::new`, whose divider it overrides,
+    /// and before `Dpi::send`.
+    fn set_clock_source(
+        &mut self,
+        source: ClockSource,
+        pclk: Rate,
+    ) -> Result<Rate, UnreachableClock>;
+}
+
+#[cfg(target_arch = "xtensa")]
+impl<Dm: DriverMode> DpiClockExt for Dpi<'_, Dm> {
+    fn set_clock_gating(&mut self, enable: bool) {
+        debug_assert!(
+            !enable || vsync::is_pending(),
+            "clock gating enabled outside of VSYNC"
+        );
+
+        LCD_CAM::regs()
+            .lcd_clock()
+            .modify(|_, w| w.clk_en().bit(!enable));
+        CLOCK_GATED.store(enable, Ordering::Relaxed);
+    }
+
+    fn set_clock_source(
+        &mut self,
+        source: ClockSource,
+        pclk: Rate,
+    ) -> Result<Rate, UnreachableClock> {
+        // LCD_CLK is divided by 2 again to get PCLK.
+        let source_hz = source.frequency().as_hz() / 2;
+        let unreachable = UnreachableClock {
+            min: Rate::from_hz(source_hz / 256),
+            max: Rate::from_hz(source_hz / 2),
+        };
+
+        let div = (source_hz + pclk.as_hz() / 2) / pclk.as_hz().max(1);
+        if !(2..=256).contains(&div) {
+            return Err(unreachable);
+        }
+
+        let regs = LCD_CAM::regs();
+        regs.lcd_clock().modify(|_, w| unsafe {
+            w.lcd_clk_sel().bits(source as u8);
+            // 0 means 256.
+            w.lcd_clkm_div_num().bits(div as u8);
+            w.lcd_clkm_div_b().bits(0);
+            w.lcd_clkm_div_a().bits(0)
+        });
+        regs.lcd_user().modify(|_, w| w.lcd_update().set_bit());
+
+        Ok(Rate::from_hz(source_hz / div))
+    }
+}
@@ -0,0 +1,76 @@
+//! Callbacks on frame completion, from the LCD VSYNC interrupt.
+//!
+//! The interrupt handler clears the VSYNC flag, so this doesn't mix with the
+//! polling helpers in [vsync](crate::display::vsync).
+
+use alloc::boxed::Box;
+use core::cell::RefCell;
+
+use critical_section::Mutex;
+
+use crate::{
+    display::vsync,
+    hal::{
+        DriverMode,
+        dma::DmaTxBuffer,
+        handler,
+        interrupt::{self, Priority},
+        lcd_cam::lcd::dpi::DpiTransfer,
+        peripherals::{Interrupt, LCD_CAM},
+    },
+    heap::{self, AllocError},
+};
+
+type Callback = Box<dyn FnMut() + Send>;
+
+static CALLBACK: Mutex<RefCell<Option<Callback>>> = Mutex::new(RefCell::new(None));
+
+/// Frame completion callbacks for a running [DpiTransfer].
+pub trait FrameCompleteExt {
+    /// Installs `cb` to run from the VSYNC interrupt after every complete
+    /// frame, replacing any previous one.
+    ///
+    /// `cb` runs in interrupt context inside a critical section; setting a
+    /// flag or sending to a channel is about as much as it should do. It's
+    /// boxed, which fails if its captures don't fit in the heap.
+    fn on_frame_complete<F: FnMut() + Send + 'static>(&mut self, cb: F) -> Result<(), AllocError>;
+
+    /// Removes the installed callback and returns it.
+    fn take_completion_callback(&mut self) -> Option<impl FnMut()>;
+}
+
+impl<BUF: DmaTxBuffer, Dm: DriverMode> FrameCompleteExt for DpiTransfer<'_, BUF, Dm> {
+    fn on_frame_complete<F: FnMut() + Send + 'static>(&mut self, cb: F) -> Result<(), AllocError> {
+        heap::check("frame completion callback", size_of::<F>())?;
+        let cb: Callback = Box::new(cb);
+        critical_section::with(|cs| CALLBACK.borrow_ref_mut(cs).replace(cb));
+
+        unsafe { interrupt::bind_interrupt(Interrupt::LCD_CAM, on_vsync.handler()) };
+        interrupt::enable(Interrupt::LCD_CAM, on_vsync.priority()).unwrap();
+        LCD_CAM::regs()
+            .lc_dma_int_ena()
+            .modify(|_, w| w.lcd_vsync_int_ena().set_bit());
+        Ok(())
+    }
+
+    fn take_completion_callback(&mut self) -> Option<impl FnMut()> {
+        LCD_CAM::regs()
+            .lc_dma_int_ena()
+            .modify(|_, w| w.lcd_vsync_int_ena().clear_bit());
+        critical_section::with(|cs| CALLBACK.borrow_ref_mut(cs).take())
+    }
+}
+
+#[handler(priority = Priority::Priority1)]
+fn on_vsync() {
+    if !vsync::is_pending() {
+        return;
+    }
+    vsync::clear();
+
+    critical_section::with(|cs| {
+        if let Some(cb) = CALLBACK.borrow_ref_mut(cs).as_mut() {
+            cb();
+        }
+    });
+}
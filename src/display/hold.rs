@@ -0,0 +1,78 @@
+//! Keeping the last frame on screen from a descriptor loop while the CPU
+//! idles.
+//!
+//! # Sleep modes
+//!
+//! A DPI panel has no frame memory, it needs PCLK and data every frame. So
+//! only sleep that leaves the LCD_CAM and GDMA clocked can hold a picture:
+//!
+//! - Halting the core, e.g. `waiti` until the next interrupt, keeps every
+//!   peripheral running. The loop refreshes from internal RAM with no CPU
+//!   involvement, which is what [HoldExt::enter_hold] is for.
+//! - Light sleep gates the peripheral clocks and powers down the PLL. The
+//!   LCD_CAM and GDMA keep their registers and internal RAM is retained, but
+//!   nothing is sent, so the panel fades or shows garbage until wakeup. Restart
+//!   the transfer with [HoldExt::exit_hold] or `enter_hold` after waking.
+//! - Deep sleep powers down both and only keeps RTC memory.
+//!
+//! The ring has to be in internal DRAM, which [DmaTxStreamBuf::new] already
+//! insists on, and large enough to hold one whole frame, 460800 bytes at
+//! 480x480 RGB565. PSRAM would also need its clock through any sleep.
+
+use crate::{
+    dma::DmaTxStreamBuf,
+    hal::{
+        DriverMode,
+        dma::DmaError,
+        lcd_cam::lcd::dpi::{Dpi, DpiTransfer},
+    },
+};
+
+/// Errors of [HoldExt::enter_hold].
+#[derive(Debug)]
+pub enum HoldError {
+    /// The ring doesn't hold a complete frame, everything between the last
+    /// two EOF pushes, so there's nothing to repeat.
+    NoCompleteFrame,
+    Dma(DmaError),
+}
+
+/// Holding the current frame of a streaming [DpiTransfer].
+pub trait HoldExt<'d, Dm: DriverMode>: Sized {
+    /// Restarts the transfer on a loop of the most recent complete frame in
+    /// the ring, after which it needs nothing from the CPU.
+    ///
+    /// The restart cuts the frame being sent short, so the panel may show
+    /// one torn frame. Nothing may be pushed while holding.
+    fn enter_hold(
+        self,
+    ) -> Result<DpiTransfer<'d, DmaTxStreamBuf, Dm>, (HoldError, Dpi<'d, Dm>, DmaTxStreamBuf)>;
+
+    /// Ends [Self::enter_hold]: restarts the transfer replaying the held frame
+    /// once, then streaming whatever is pushed after it.
+    fn exit_hold(
+        self,
+    ) -> Result<DpiTransfer<'d, DmaTxStreamBuf, Dm>, (DmaError, Dpi<'d, Dm>, DmaTxStreamBuf)>;
+}
+
+impl<'d, Dm: DriverMode> HoldExt<'d, Dm> for DpiTransfer<'d, DmaTxStreamBuf, Dm> {
+    fn enter_hold(
+        self,
+    ) -> Result<DpiTransfer<'d, DmaTxStreamBuf, Dm>, (HoldError, Dpi<'d, Dm>, DmaTxStreamBuf)> {
+        let (dpi, mut buf) = self.stop();
+        if !buf.hold_resumed_frame() {
+            return Err((HoldError::NoCompleteFrame, dpi, buf));
+        }
+
+        dpi.send(true, buf)
+            .map_err(|(err, dpi, buf)| (HoldError::Dma(err), dpi, buf))
+    }
+
+    fn exit_hold(
+        self,
+    ) -> Result<DpiTransfer<'d, DmaTxStreamBuf, Dm>, (DmaError, Dpi<'d, Dm>, DmaTxStreamBuf)> {
+        let (dpi, mut buf) = self.stop();
+        buf.disable_loop();
+        dpi.send(true, buf)
+    }
+}
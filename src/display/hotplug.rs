@@ -0,0 +1,195 @@
+//! Detecting a panel that's unplugged and plugged back in while running.
+//!
+//! The DPI bus has no way to tell whether anything is listening, so the
+//! panel is polled over SPI instead. A panel that's gone reads all zeros or
+//! all ones, depending on how the SDA line floats, or errors with an SPI
+//! provider that can notice; one that was plugged back in reads its reset
+//! power mode. Either way it has lost its registers and needs a full
+//! [St7701::init].
+
+use embedded_hal::delay::DelayNs;
+use heapless::Vec;
+
+use crate::{
+    display::st7701::{SpiProvider, St7701},
+    dma::DmaTxStreamBuf,
+    hal::{
+        DriverMode,
+        dma::DmaError,
+        lcd_cam::lcd::dpi::{Dpi, DpiTransfer},
+        time::{Duration, Instant},
+    },
+    looped::SolidFrame,
+};
+
+/// Sleep out and display on in RDDPM, both set by [St7701::init] and both
+/// clear in the reset default, `0x08`.
+const POWER_MODE_RUNNING: u8 = 0x14;
+
+/// What a [HotplugMonitor] reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanelEvent {
+    /// The panel stopped answering or came back from a reset on its own.
+    Disconnected,
+    /// The panel answers again and has been re-initialized.
+    Reconnected,
+}
+
+/// [EventRegistry::register] found every slot taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegistryFull;
+
+/// Up to `N` callbacks that are called, in the order they were registered,
+/// with every event a monitor emits.
+///
+/// Callbacks are borrowed rather than boxed, so they run in whatever context
+/// calls the monitor and can capture locals.
+pub struct EventRegistry<'a, E, const N: usize> {
+    callbacks: Vec<&'a mut dyn FnMut(E), N>,
+}
+
+impl<'a, E: Copy, const N: usize> EventRegistry<'a, E, N> {
+    pub const fn new() -> Self {
+        Self {
+            callbacks: Vec::new(),
+        }
+    }
+
+    /// Adds `cb` after the callbacks already registered.
+    pub fn register(&mut self, cb: &'a mut dyn FnMut(E)) -> Result<(), RegistryFull> {
+        self.callbacks.push(cb).map_err(|_| RegistryFull)
+    }
+
+    /// Calls every registered callback with `event`.
+    pub fn emit(&mut self, event: E) {
+        for cb in &mut self.callbacks {
+            cb(event);
+        }
+    }
+}
+
+impl<E: Copy, const N: usize> Default for EventRegistry<'_, E, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Checks every `interval` that the panel is still there, see the
+/// [module docs](self).
+///
+/// A connected panel counts as gone when [St7701::read_power_mode] fails,
+/// reads all zeros or ones, or no longer reads sleep out and display on,
+/// which includes its reset default. A gone panel counts as back as soon as
+/// it reads anything else; the monitor then runs [St7701::init] before
+/// reporting [PanelEvent::Reconnected], and tries again next interval if
+/// that fails.
+///
+/// The monitor doesn't own the stream. On [PanelEvent::Disconnected] stop
+/// pushing and switch to black with [blank], on [PanelEvent::Reconnected]
+/// switch back with [unblank].
+pub struct HotplugMonitor<'a, const N: usize = 4> {
+    interval: Duration,
+    next_check: Option<Instant>,
+    connected: bool,
+    events: EventRegistry<'a, PanelEvent, N>,
+}
+
+impl<'a, const N: usize> HotplugMonitor<'a, N> {
+    /// Creates a monitor for an initialized, connected panel.
+    pub const fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            next_check: None,
+            connected: true,
+            events: EventRegistry::new(),
+        }
+    }
+
+    /// Changes the check interval, starting with the next check.
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    /// Where to register callbacks for [PanelEvent]s.
+    pub fn events(&mut self) -> &mut EventRegistry<'a, PanelEvent, N> {
+        &mut self.events
+    }
+
+    /// Whether the panel was there at the last check.
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Checks `panel` if an interval has passed since the last check, and
+    /// emits and returns the event if its state changed.
+    pub fn poll<S: SpiProvider>(
+        &mut self,
+        panel: &mut St7701<'_, S>,
+        delay: &mut impl DelayNs,
+        now: Instant,
+    ) -> Option<PanelEvent> {
+        if self.next_check.is_some_and(|at| now < at) {
+            return None;
+        }
+        self.next_check = Some(now + self.interval);
+
+        let mode = panel
+            .read_power_mode()
+            .ok()
+            .filter(|mode| !matches!(mode, 0x00 | 0xFF));
+
+        let event = match (self.connected, mode) {
+            (true, None) => PanelEvent::Disconnected,
+            (true, Some(mode)) if mode & POWER_MODE_RUNNING != POWER_MODE_RUNNING => {
+                PanelEvent::Disconnected
+            }
+            (false, Some(_)) => {
+                if panel.init(delay).is_err() {
+                    warn!("Hotplug: re-init failed, retrying next check");
+                    return None;
+                }
+                PanelEvent::Reconnected
+            }
+            _ => return None,
+        };
+
+        self.connected = event == PanelEvent::Reconnected;
+        info!("Hotplug: {:?}, power mode {:?}", event, mode);
+        self.events.emit(event);
+        Some(event)
+    }
+}
+
+/// Stops `transfer` and loops `black` instead, so the DMA keeps a valid
+/// stream while nothing is pushed. The ring is handed back as it was, with
+/// any complete frame left in it replayed by [unblank].
+#[allow(clippy::type_complexity)]
+pub fn blank<Dm: DriverMode>(
+    transfer: DpiTransfer<'_, DmaTxStreamBuf, Dm>,
+    black: SolidFrame,
+) -> Result<
+    (DpiTransfer<'_, SolidFrame, Dm>, DmaTxStreamBuf),
+    (DmaError, Dpi<'_, Dm>, SolidFrame, DmaTxStreamBuf),
+> {
+    let (dpi, buf) = transfer.stop();
+    match dpi.send(true, black) {
+        Ok(transfer) => Ok((transfer, buf)),
+        Err((err, dpi, black)) => Err((err, dpi, black, buf)),
+    }
+}
+
+/// Undoes [blank], streaming from `buf` again.
+#[allow(clippy::type_complexity)]
+pub fn unblank<Dm: DriverMode>(
+    transfer: DpiTransfer<'_, SolidFrame, Dm>,
+    buf: DmaTxStreamBuf,
+) -> Result<
+    (DpiTransfer<'_, DmaTxStreamBuf, Dm>, SolidFrame),
+    (DmaError, Dpi<'_, Dm>, DmaTxStreamBuf, SolidFrame),
+> {
+    let (dpi, black) = transfer.stop();
+    match dpi.send(true, buf) {
+        Ok(transfer) => Ok((transfer, black)),
+        Err((err, dpi, buf)) => Err((err, dpi, buf, black)),
+    }
+}
@@ -0,0 +1,87 @@
+use crate::hal::{gpio::Level, peripherals::GPIO};
+
+/// GPIO matrix output signal index of `LCD_DATA_0`, the others follow in
+/// order.
+const LCD_DATA_0_IDX: u16 = 133;
+/// GPIO matrix output signal index that hands a pin to the GPIO_OUT register.
+const SIG_GPIO_OUT_IDX: u16 = 256;
+
+/// Forces individual DPI data lanes to a fixed level for probing them.
+///
+/// Strictly a bring-up tool: a forced lane no longer follows the DMA output,
+/// so colors on the panel are wrong until it is restored. It reroutes the
+/// pin in the GPIO matrix behind the back of the
+/// [Dpi](esp_hal::lcd_cam::lcd::dpi::Dpi) driver, which still believes it owns
+/// the pin.
+pub struct DataLanes {
+    // GPIO number for each of LCD_DATA_0..=LCD_DATA_15.
+    pins: [u8; 16],
+    forced: u16,
+}
+
+impl DataLanes {
+    /// `pins[n]` is the GPIO number passed to `with_data{n}`.
+    pub const fn new(pins: [u8; 16]) -> Self {
+        Self { pins, forced: 0 }
+    }
+
+    /// Drives data lane `lane` to `level`, regardless of the pixel data.
+    ///
+    /// # Safety
+    ///
+    /// `pins` must match how the DPI was set up; any other pin listed gets
+    /// its output rerouted as well.
+    pub unsafe fn force(&mut self, lane: usize, level: Level) {
+        let pin = self.pins[lane];
+        Self::set_output(pin, level);
+        Self::route(pin, SIG_GPIO_OUT_IDX);
+        self.forced |= 1 << lane;
+    }
+
+    /// Hands data lane `lane` back to the LCD peripheral.
+    pub fn restore(&mut self, lane: usize) {
+        if self.forced & (1 << lane) == 0 {
+            return;
+        }
+        Self::route(self.pins[lane], LCD_DATA_0_IDX + lane as u16);
+        self.forced &= !(1 << lane);
+    }
+
+    /// Hands every forced lane back to the LCD peripheral.
+    pub fn restore_all(&mut self) {
+        for lane in 0..self.pins.len() {
+            self.restore(lane);
+        }
+    }
+
+    /// Whether data lane `lane` is currently forced.
+    pub fn is_forced(&self, lane: usize) -> bool {
+        self.forced & (1 << lane) != 0
+    }
+
+    fn route(pin: u8, signal: u16) {
+        GPIO::regs()
+            .func_out_sel_cfg(pin as usize)
+            .modify(|_, w| unsafe { w.out_sel().bits(signal) });
+    }
+
+    fn set_output(pin: u8, level: Level) {
+        let regs = GPIO::regs();
+        let high = level == Level::High;
+        if pin < 32 {
+            let mask = 1 << pin;
+            if high {
+                regs.out_w1ts().write(|w| unsafe { w.bits(mask) });
+            } else {
+                regs.out_w1tc().write(|w| unsafe { w.bits(mask) });
+            }
+        } else {
+            let mask = 1 << (pin - 32);
+            if high {
+                regs.out1_w1ts().write(|w| unsafe { w.bits(mask) });
+            } else {
+                regs.out1_w1tc().write(|w| unsafe { w.bits(mask) });
+            }
+        }
+    }
+}
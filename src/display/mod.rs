@@ -1 +1,13 @@
+pub mod bringup;
+pub mod clock;
+#[cfg(target_arch = "xtensa")]
+pub mod frame_done;
+pub mod hold;
+pub mod hotplug;
+#[cfg(target_arch = "xtensa")]
+pub mod lanes;
+#[cfg(target_arch = "xtensa")]
+pub mod pause;
 pub mod st7701;
+pub mod sync;
+pub mod vsync;
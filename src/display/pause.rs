@@ -0,0 +1,82 @@
+//! Halting DPI output in place, e.g. while reconfiguring the panel.
+
+use core::{
+    mem::ManuallyDrop,
+    ops::{Deref, DerefMut},
+};
+
+use crate::hal::{
+    DriverMode, dma::DmaTxBuffer, lcd_cam::lcd::dpi::DpiTransfer, peripherals::LCD_CAM,
+};
+
+/// Pausing for a running [DpiTransfer].
+pub trait PauseExt<'d, B: DmaTxBuffer, Dm: DriverMode> {
+    /// Stops the LCD from clocking out pixels without ending the transfer,
+    /// until [PausedTransfer::resume] or the [PausedTransfer] is dropped.
+    ///
+    /// Clears `LCD_START`, which the LCD_CAM acts on at its next PCLK
+    /// edge, one period or less, 84ns at 12MHz, plus the register write
+    /// itself. PCLK, HSYNC and VSYNC stop with the data, so the panel sees
+    /// the frame cut short and may show a black frame, or a torn one once
+    /// output resumes; the DMA stays set up and stalls on the full LCD FIFO.
+    ///
+    /// The buffer can be refilled through the [PausedTransfer] meanwhile.
+    /// `DpiTransfer::is_done` reads `LCD_START`, so it reports `true` while
+    /// paused.
+    fn pause(&mut self) -> PausedTransfer<'_, 'd, B, Dm>;
+}
+
+impl<'d, B: DmaTxBuffer, Dm: DriverMode> PauseExt<'d, B, Dm> for DpiTransfer<'d, B, Dm> {
+    fn pause(&mut self) -> PausedTransfer<'_, 'd, B, Dm> {
+        LCD_CAM::regs()
+            .lcd_user()
+            .modify(|_, w| w.lcd_start().clear_bit());
+        PausedTransfer { transfer: self }
+    }
+}
+
+/// A [DpiTransfer] halted by [PauseExt::pause]. Dereferences to the
+/// transfer's buffer view; dropping it resumes output like
+/// [Self::resume].
+pub struct PausedTransfer<'a, 'd, B: DmaTxBuffer, Dm: DriverMode> {
+    transfer: &'a mut DpiTransfer<'d, B, Dm>,
+}
+
+impl<'a, 'd, B: DmaTxBuffer, Dm: DriverMode> PausedTransfer<'a, 'd, B, Dm> {
+    /// Restarts the LCD where it stopped and hands the transfer back. Takes
+    /// effect within a PCLK period, like [PauseExt::pause].
+    pub fn resume(self) -> &'a mut DpiTransfer<'d, B, Dm> {
+        let this = ManuallyDrop::new(self);
+        start();
+        // SAFETY: `this` is never used or dropped again, so the reference is
+        // moved out exactly once.
+        unsafe { core::ptr::read(&this.transfer) }
+    }
+}
+
+impl<B: DmaTxBuffer, Dm: DriverMode> Deref for PausedTransfer<'_, '_, B, Dm> {
+    type Target = B::View;
+
+    fn deref(&self) -> &Self::Target {
+        &**self.transfer
+    }
+}
+
+impl<B: DmaTxBuffer, Dm: DriverMode> DerefMut for PausedTransfer<'_, '_, B, Dm> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut **self.transfer
+    }
+}
+
+impl<B: DmaTxBuffer, Dm: DriverMode> Drop for PausedTransfer<'_, '_, B, Dm> {
+    fn drop(&mut self) {
+        start();
+    }
+}
+
+fn start() {
+    LCD_CAM::regs().lcd_user().modify(|_, w| {
+        w.lcd_update().set_bit();
+        w.lcd_start().set_bit()
+    });
+}
@@ -1,42 +1,517 @@
 use core::convert::Infallible;
 
-use embedded_hal::delay::DelayNs;
-use esp_backtrace as _;
-use esp_hal::{
-    DriverMode,
-    delay::Delay,
-    gpio::{Flex, Output},
-    spi::{
-        DataMode, Error,
-        master::{Address, Command, Spi},
+use embedded_hal::{delay::DelayNs, digital::InputPin};
+#[cfg(feature = "embassy")]
+use embedded_hal_async::digital::Wait;
+use heapless::Vec;
+
+use crate::{
+    color::PixelFormat,
+    geometry::FrameGeometry,
+    hal::{
+        DriverMode,
+        delay::Delay,
+        gpio::{Flex, Level, Output, Pull},
+        spi::{
+            DataMode, Error,
+            master::{Address, Command, Spi},
+        },
+        time::{Duration, Instant},
     },
 };
 
+#[cfg(test)]
+pub mod mock;
+
 const MSB_MASK: u8 = 0b1000_0000;
 
+/// MADCTL and COLMOD as written by [St7701::init].
+const INIT_MADCTL: u8 = 0x08;
+const INIT_COLMOD: u8 = 0x60;
+
 fn ser(is_command: bool, byte: u8) -> Command {
     // First bit: 0 for command, 1 for parameter
     let first_bit = (!is_command as u16) << 15;
     // 1-bit C/D followed by 8-bit data
-    let data = (byte as u16) << 7 | first_bit;
+    let data = ((byte as u16) << 7) | first_bit;
 
     Command::_9Bit(data, DataMode::Single)
 }
 
+/// The panel's tearing effect signal didn't arrive in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TeTimeout;
+
+/// Busy-waits until the TE (tearing effect) pin `gpio` is high, i.e. the
+/// panel is in vertical blanking.
+///
+/// Read errors count as low. TE output has to be enabled on the panel with
+/// TEON (`0x35`).
+pub fn wait_for_te(gpio: &mut impl InputPin, timeout: Duration) -> Result<(), TeTimeout> {
+    let start = Instant::now();
+    while !gpio.is_high().unwrap_or(false) {
+        if start.elapsed() > timeout {
+            return Err(TeTimeout);
+        }
+    }
+    Ok(())
+}
+
+/// Waits until the TE pin `gpio` is high without blocking the executor.
+///
+/// There's no deadline of its own; this only fails if the pin reports an
+/// error, so wrap it in a timeout if the panel may never raise TE.
+#[cfg(feature = "embassy")]
+pub async fn wait_for_te_async(gpio: &mut impl Wait) -> Result<(), TeTimeout> {
+    gpio.wait_for_high().await.map_err(|_| TeTimeout)
+}
+
+/// One step of a panel init sequence, see [St7701::init_from_sequence].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InitEntry {
+    WriteReg { cmd: u8, data: Vec<u8, 32> },
+    DelayMs(u32),
+    Reset,
+}
+
+impl InitEntry {
+    const TAG_DELAY_MS: u8 = 0x01;
+    const TAG_RESET: u8 = 0x02;
+    const TAG_WRITE_REG: u8 = 0x00;
+
+    /// Decodes one entry from the start of `blob`, returning it and the rest.
+    ///
+    /// Each entry is a tag byte followed by its payload:
+    ///
+    /// - `0x00 cmd len data[len]`: [InitEntry::WriteReg], `len` at most 32
+    /// - `0x01 ms[4]`: [InitEntry::DelayMs], little-endian
+    /// - `0x02`: [InitEntry::Reset]
+    ///
+    /// Returns `None` if `blob` is empty or the entry is malformed.
+    pub fn decode(blob: &[u8]) -> Option<(Self, &[u8])> {
+        let (&tag, rest) = blob.split_first()?;
+
+        match tag {
+            Self::TAG_WRITE_REG => {
+                let (&[cmd, len], rest) = rest.split_first_chunk::<2>()?;
+                let (data, rest) = rest.split_at_checked(len as usize)?;
+                let data = Vec::from_slice(data).ok()?;
+                Some((Self::WriteReg { cmd, data }, rest))
+            }
+            Self::TAG_DELAY_MS => {
+                let (ms, rest) = rest.split_first_chunk::<4>()?;
+                Some((Self::DelayMs(u32::from_le_bytes(*ms)), rest))
+            }
+            Self::TAG_RESET => Some((Self::Reset, rest)),
+            _ => None,
+        }
+    }
+}
+
+/// Width in bits of each gamma table entry, from V0 to V255.
+const GAMMA_FIELD_BITS: [u32; 16] = [4, 6, 6, 5, 5, 4, 6, 4, 4, 6, 4, 5, 5, 6, 6, 5];
+
+/// Entries holding the midtone references, V52 to V203.
+const GAMMA_MIDTONES: core::ops::RangeInclusive<usize> = 5..=10;
+
+const SRGB_POSITIVE: [u8; 16] = [
+    0x02, 0x13, 0x1B, 0x0D, 0x10, 0x05, 0x08, 0x07, 0x07, 0x24, 0x04, 0x11, 0x0E, 0x2C, 0x33, 0x1D,
+];
+const SRGB_NEGATIVE: [u8; 16] = [
+    0x05, 0x13, 0x1B, 0x0D, 0x11, 0x05, 0x08, 0x07, 0x07, 0x24, 0x04, 0x11, 0x0E, 0x2C, 0x33, 0x1D,
+];
+
+/// Scales the midtone entries of `table` by `num / den`, clamped to the
+/// width of each entry. The end points are left alone.
+const fn scale_midtones(table: [u8; 16], num: u32, den: u32) -> [u8; 16] {
+    let mut out = table;
+    let mut i = *GAMMA_MIDTONES.start();
+    while i <= *GAMMA_MIDTONES.end() {
+        let max = (1 << GAMMA_FIELD_BITS[i]) - 1;
+        let scaled = (table[i] as u32 * num + den / 2) / den;
+        out[i] = (if scaled > max { max } else { scaled }) as u8;
+        i += 1;
+    }
+    out
+}
+
+static GAMMA_TABLES: [[[u8; 16]; 2]; 3] = [
+    [
+        scale_midtones(SRGB_POSITIVE, 1, 2),
+        scale_midtones(SRGB_NEGATIVE, 1, 2),
+    ],
+    [SRGB_POSITIVE, SRGB_NEGATIVE],
+    [
+        scale_midtones(SRGB_POSITIVE, 3, 2),
+        scale_midtones(SRGB_NEGATIVE, 3, 2),
+    ],
+];
+
+/// Ready-made gamma curves for [St7701::set_gamma_preset].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GammaPreset {
+    /// [GammaPreset::Srgb] with the midtone references (V52 to V203) halved,
+    /// flattening the curve towards a straight line.
+    Linear,
+    /// The tables from the panel vendor's init code, which [St7701::init]
+    /// writes.
+    Srgb,
+    /// [GammaPreset::Srgb] with the midtone references raised by half,
+    /// clamped to each register field, for a steeper curve.
+    HighContrast,
+}
+
+impl GammaPreset {
+    /// The positive (`0xB0`) and negative (`0xB1`) gamma tables.
+    ///
+    /// [GammaPreset::Linear] and [GammaPreset::HighContrast] are derived from
+    /// the vendor tables, not measured; treat them as starting points.
+    pub fn tables(self) -> (&'static [u8; 16], &'static [u8; 16]) {
+        let [positive, negative] = &GAMMA_TABLES[self as usize];
+        (positive, negative)
+    }
+}
+
+/// Readable status registers of the panel, see [St7701::snapshot].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterSnapshot {
+    /// RDDID (`0x04`): manufacturer, version and module ID.
+    pub id: [u8; 3],
+    /// RDDST (`0x09`): display status.
+    pub status: [u8; 4],
+    /// RDDPM (`0x0A`): power mode.
+    pub power_mode: u8,
+    /// RDDMADCTL (`0x0B`): memory access control.
+    pub madctl: u8,
+    /// RDDCOLMOD (`0x0C`): pixel format.
+    pub colmod: u8,
+    /// RDDIM (`0x0D`): image mode.
+    pub image_mode: u8,
+    /// RDDSM (`0x0E`): signal mode.
+    pub signal_mode: u8,
+    /// RDDSDR (`0x0F`): self-diagnostic result.
+    pub self_diagnostic: u8,
+}
+
+/// Decoded RDDST response, see [St7701::read_status].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayStatus {
+    pub booster_voltage_ok: bool,
+    /// In sleep mode, i.e. Sleep Out (`0x11`) hasn't been sent.
+    pub sleep: bool,
+    pub normal: bool,
+    pub partial: bool,
+    pub vscroll: bool,
+    pub inverted: bool,
+    pub display_on: bool,
+    /// Interface pixel format, as in the upper nibble of COLMOD.
+    pub pixel_format: u8,
+    pub tearing_effect_on: bool,
+}
+
+impl DisplayStatus {
+    /// Decodes the four RDDST bytes, `D31..D24` first.
+    pub fn from_bytes(bytes: [u8; 4]) -> Self {
+        let bit = |byte: usize, n: u8| bytes[byte] & (1 << n) != 0;
+
+        Self {
+            booster_voltage_ok: bit(0, 7),
+            pixel_format: (bytes[1] >> 4) & 0b111,
+            partial: bit(1, 2),
+            sleep: !bit(1, 1),
+            normal: bit(1, 0),
+            vscroll: bit(2, 7),
+            inverted: bit(2, 5),
+            display_on: bit(2, 2),
+            tearing_effect_on: bit(2, 1),
+        }
+    }
+}
+
+/// Decoded RDDSDR (`0x0F`), the checks the panel runs on Sleep Out. Both
+/// bits read 0 after reset and are set when the check passes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelfDiagnostic {
+    /// D7, register values were loaded correctly from NV memory.
+    pub register_loading_ok: bool,
+    /// D6, the display functionality check passed.
+    pub functionality_ok: bool,
+}
+
+impl SelfDiagnostic {
+    pub fn from_byte(byte: u8) -> Self {
+        Self {
+            register_loading_ok: byte & 0x80 != 0,
+            functionality_ok: byte & 0x40 != 0,
+        }
+    }
+}
+
+/// Errors of [St7701::post_init_check].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagError<E> {
+    Spi(E),
+    /// At least one self-diagnostic check failed, or the panel hasn't left
+    /// sleep mode yet.
+    Failed(SelfDiagnostic),
+}
+
+/// Content adaptive brightness control modes, see [St7701::set_cabc].
+///
+/// CABC dims the backlight according to the average luminance of the image
+/// to save power, more aggressively for content where it's less visible.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CabcMode {
+    Off         = 0,
+    Ui          = 1,
+    StillImage  = 2,
+    MovingImage = 3,
+}
+
+/// Command2 register banks, selected with `0xFF` and `0x77 0x01 0x00 0x00`
+/// followed by the bank byte. See [St7701::with_page].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandPage {
+    /// Gamma, porches and inversion.
+    Bk0 = 0x10,
+    /// Power, VCOM and GIP.
+    Bk1 = 0x11,
+    /// NV memory and charge pump configuration.
+    ///
+    /// There's deliberately no typed booster setter: the ST7701 datasheets
+    /// this driver follows don't document the page's registers, let alone
+    /// which booster frequencies and ratios they take, so enums for them
+    /// would be guesses that could overdrive the charge pump. If the panel
+    /// vendor supplied values, write them raw with [St7701::command] inside
+    /// [St7701::with_page].
+    Bk2 = 0x12,
+    /// Vendor test registers, only `0xEF` is written by [St7701::init].
+    Bk3 = 0x13,
+}
+
+/// Gate-in-Panel signal routing and timing, page BK1 registers `0xE0` to
+/// `0xEF`. Panel specific, see [St7701::configure_gip].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GipConfig {
+    pub e0: [u8; 3],
+    pub e1: [u8; 11],
+    pub e2: [u8; 13],
+    pub e3: [u8; 4],
+    pub e4: [u8; 2],
+    pub e5: [u8; 16],
+    pub e6: [u8; 4],
+    pub e7: [u8; 2],
+    pub e8: [u8; 16],
+    pub eb: [u8; 7],
+    pub ed: [u8; 16],
+    pub ef: [u8; 6],
+}
+
+impl GipConfig {
+    /// The values [St7701::init] writes.
+    pub const DEFAULT: Self = Self {
+        e0: [0x00, 0x00, 0x02],
+        e1: [
+            0x03, 0xA0, 0x00, 0x00, 0x04, 0xA0, 0x00, 0x00, 0x00, 0x20, 0x20,
+        ],
+        e2: [
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ],
+        e3: [0x00, 0x00, 0x11, 0x00],
+        e4: [0x22, 0x00],
+        e5: [
+            0x05, 0xEC, 0xA0, 0xA0, 0x07, 0xEE, 0xA0, 0xA0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+        e6: [0x00, 0x00, 0x11, 0x00],
+        e7: [0x22, 0x00],
+        e8: [
+            0x06, 0xED, 0xA0, 0xA0, 0x08, 0xEF, 0xA0, 0xA0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+        eb: [0x00, 0x00, 0x40, 0x40, 0x00, 0x00, 0x00],
+        ed: [
+            0xFF, 0xFF, 0xFF, 0xBA, 0x0A, 0xBF, 0x45, 0xFF, 0xFF, 0x54, 0xFB, 0xA0, 0xAB, 0xFF,
+            0xFF, 0xFF,
+        ],
+        ef: [0x10, 0x0D, 0x04, 0x08, 0x3F, 0x1F],
+    };
+}
+
+/// The first three GIP registers, `0xE0` to `0xE2`, see
+/// [St7701::set_equalization].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EqualizationConfig {
+    pub e0: [u8; 3],
+    pub e1: [u8; 11],
+    pub e2: [u8; 13],
+}
+
+impl EqualizationConfig {
+    /// The values [St7701::init] writes, from [GipConfig::DEFAULT]. Known
+    /// good at 60Hz on the boards in [boards](crate::boards).
+    ///
+    /// There's no documented "low crosstalk" set to offer next to it: the
+    /// datasheet gives these registers as panel vendor data without field
+    /// descriptions. For smearing on text, tune [St7701::set_vcom] first,
+    /// then ask the panel vendor for their equalization values.
+    pub const DEFAULT: Self = Self {
+        e0: GipConfig::DEFAULT.e0,
+        e1: GipConfig::DEFAULT.e1,
+        e2: GipConfig::DEFAULT.e2,
+    };
+}
+
+/// Per-unit calibration, applied in one go with [St7701::apply_profile].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PanelProfile {
+    /// Positive and negative gamma tables, see [St7701::set_gamma].
+    pub gamma: ([u8; 16], [u8; 16]),
+    /// VCOM amplitude, see [St7701::set_vcom].
+    pub vcom: u8,
+    /// VGH voltage, page BK1 `0xB2`.
+    pub vgh: u8,
+    /// VGL voltage, page BK1 `0xB5`.
+    pub vgl: u8,
+    /// Display brightness (WRDISBV, `0x51`). Only has an effect if the
+    /// backlight is driven from the panel's LEDPWM output.
+    pub brightness: u8,
+}
+
+impl PanelProfile {
+    /// The values [St7701::init] writes, with full brightness.
+    pub const DEFAULT: Self = Self {
+        gamma: (SRGB_POSITIVE, SRGB_NEGATIVE),
+        vcom: 0x43,
+        vgh: 0x81,
+        vgl: 0x43,
+        brightness: 0xFF,
+    };
+}
+
 pub struct St7701<'a, S> {
     spi: S,
-    rst: Output<'a>,
+    rst: Option<Output<'a>>,
+    idle: bool,
+    cabc: CabcMode,
+    geometry: FrameGeometry,
 }
 
 pub struct ManualSpi<'a> {
     pub cs: Output<'a>,
     pub sda: Flex<'a>,
     pub scl: Output<'a>,
+    /// Data/command select, low for commands. Only used with
+    /// [WordSize::Bits8], which can't be set up without it.
+    dc: Option<Output<'a>>,
+    word_size: WordSize,
+    /// Delay between asserting CS and the first clock, in microseconds.
+    pub cs_setup_us: u32,
+    /// Delay between the last clock and releasing CS, in microseconds.
+    pub cs_hold_us: u32,
+}
+
+/// Frame format of [ManualSpi].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordSize {
+    /// Plain 8-bit frames, data/command is signalled on the `dc` pin.
+    Bits8,
+    /// 1 data/command bit followed by 8 data bits (3-wire SPI).
+    Bits9,
+}
+
+/// [ManualSpi::with_word_size] was given [WordSize::Bits8] without a
+/// data/command pin, which the panel would misparse every command without.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MissingDcPin;
+
+impl<'a> ManualSpi<'a> {
+    /// Creates a [ManualSpi] sending [WordSize::Bits9] frames.
+    pub fn new(cs: Output<'a>, sda: Flex<'a>, scl: Output<'a>) -> Self {
+        Self {
+            cs,
+            sda,
+            scl,
+            dc: None,
+            word_size: WordSize::Bits9,
+            cs_setup_us: 1000,
+            cs_hold_us: 1000,
+        }
+    }
+
+    /// Creates a [ManualSpi] sending `word_size` frames. [WordSize::Bits8]
+    /// needs `dc`, [WordSize::Bits9] ignores it.
+    pub fn with_word_size(
+        cs: Output<'a>,
+        sda: Flex<'a>,
+        scl: Output<'a>,
+        word_size: WordSize,
+        dc: Option<Output<'a>>,
+    ) -> Result<Self, MissingDcPin> {
+        if word_size == WordSize::Bits8 && dc.is_none() {
+            return Err(MissingDcPin);
+        }
+
+        Ok(Self {
+            dc,
+            word_size,
+            ..Self::new(cs, sda, scl)
+        })
+    }
+
+    /// Sets the CS setup and hold delays, 1ms each by default.
+    ///
+    /// The ST7701S SPI timing table asks for CS setup and hold times in the
+    /// tens of nanoseconds, so the default is very conservative and makes up
+    /// most of the time an init takes: about 2ms per command. A few
+    /// microseconds each is still well above the minimums.
+    pub fn with_cs_timing(mut self, setup_us: u32, hold_us: u32) -> Self {
+        self.cs_setup_us = setup_us;
+        self.cs_hold_us = hold_us;
+        self
+    }
 }
 
 impl<'a, S> St7701<'a, S> {
-    pub fn new(spi: S, rst: Output<'a>) -> Self {
-        Self { spi, rst }
+    /// Without a reset pin, [St7701::reset] sends a software reset instead.
+    pub fn new(spi: S, rst: Option<Output<'a>>) -> Self {
+        Self {
+            spi,
+            rst,
+            idle: false,
+            cabc: CabcMode::Off,
+            // What `init` sets up: 480 lines in LNESET, RGB565 over the DPI.
+            geometry: FrameGeometry::new(480, 480, PixelFormat::Rgb565),
+        }
+    }
+
+    /// Sets the size reported by [Self::geometry], for panels whose init
+    /// sequence sets up another resolution.
+    pub fn with_geometry(mut self, geometry: FrameGeometry) -> Self {
+        self.geometry = geometry;
+        self
+    }
+
+    pub fn geometry(&self) -> FrameGeometry {
+        self.geometry
+    }
+
+    pub(crate) fn reset_pin(&mut self) -> Option<&mut Output<'a>> {
+        self.rst.as_mut()
+    }
+
+    /// Gives back the SPI and reset pin, e.g. to rebuild the display in
+    /// another mode.
+    ///
+    /// To tear down without leaving the panel showing garbage or stuck
+    /// driving its gates:
+    ///
+    /// 1. [St7701::display_off], so the panel blanks while still being fed
+    /// 2. Stop the DPI transfer with `DpiTransfer::stop`
+    /// 3. [St7701::sleep_in], which stops the panel's own charge pumps
+    /// 4. `release`, leaving the reset pin high so the panel stays put
+    pub fn release(self) -> (S, Option<Output<'a>>) {
+        (self.spi, self.rst)
     }
 }
 
@@ -53,6 +528,23 @@ pub trait SpiProvider {
         self.while_cs(|s| data.iter().try_for_each(|byte| s.write_byte(false, *byte)))
     }
 
+    /// Writes `command` followed by its `params` within a single CS assertion.
+    fn write_sequence(&mut self, command: u8, params: &[u8]) -> Result<(), Self::Error> {
+        self.while_cs(|s| {
+            s.write_byte(true, command)?;
+            params
+                .iter()
+                .try_for_each(|byte| s.write_byte(false, *byte))
+        })
+    }
+
+    /// Sends `command` and reads `buf.len()` bytes of its response. Reads of
+    /// more than one byte are preceded by a dummy clock cycle.
+    ///
+    /// There's no default: a provider without a way to read should fail
+    /// here, which fails the read-backs like [St7701::init_with_retry].
+    fn read(&mut self, command: u8, buf: &mut [u8]) -> Result<(), Self::Error>;
+
     fn while_cs<F, R>(&mut self, func: F) -> R
     where
         F: FnOnce(&mut Self) -> R,
@@ -91,6 +583,17 @@ impl<Dm: DriverMode> SpiProvider for Spi<'_, Dm> {
 
         Ok(())
     }
+
+    fn read(&mut self, command: u8, buf: &mut [u8]) -> Result<(), Self::Error> {
+        let dummy = if buf.len() > 1 { 1 } else { 0 };
+        self.half_duplex_read(
+            DataMode::Single,
+            ser(true, command),
+            Address::None,
+            dummy,
+            buf,
+        )
+    }
 }
 
 impl SpiProvider for ManualSpi<'_> {
@@ -101,9 +604,9 @@ impl SpiProvider for ManualSpi<'_> {
         F: FnOnce(&mut Self) -> R,
     {
         self.cs.set_low();
-        Delay::new().delay_ms(1);
+        Delay::new().delay_micros(self.cs_setup_us);
         let result = func(self);
-        Delay::new().delay_ms(1);
+        Delay::new().delay_micros(self.cs_hold_us);
         self.cs.set_high();
         result
     }
@@ -113,14 +616,27 @@ impl SpiProvider for ManualSpi<'_> {
 
         let mut data = byte;
 
-        self.scl.set_low();
-        // First bit: 0 for command, 1 for parameter
-        if is_command {
-            self.sda.set_low()
-        } else {
-            self.sda.set_high()
+        match self.word_size {
+            WordSize::Bits8 => {
+                if let Some(dc) = &mut self.dc {
+                    if is_command {
+                        dc.set_low()
+                    } else {
+                        dc.set_high()
+                    }
+                }
+            }
+            WordSize::Bits9 => {
+                self.scl.set_low();
+                // First bit: 0 for command, 1 for parameter
+                if is_command {
+                    self.sda.set_low()
+                } else {
+                    self.sda.set_high()
+                }
+                self.scl.set_high();
+            }
         }
-        self.scl.set_high();
 
         for _ in 0..u8::BITS {
             Delay::new().delay_ns(100);
@@ -144,21 +660,59 @@ impl SpiProvider for ManualSpi<'_> {
 
         Ok(())
     }
+
+    fn read(&mut self, command: u8, buf: &mut [u8]) -> Result<(), Self::Error> {
+        self.while_cs(|s| {
+            s.write_byte(true, command)?;
+            s.sda.set_as_input(Pull::None);
+
+            if buf.len() > 1 {
+                s.scl.set_low();
+                Delay::new().delay_ns(100);
+                s.scl.set_high();
+            }
+
+            for byte in buf.iter_mut() {
+                *byte = 0;
+                for _ in 0..u8::BITS {
+                    s.scl.set_low();
+                    Delay::new().delay_ns(100);
+                    s.scl.set_high();
+                    *byte = (*byte << 1) | s.sda.is_high() as u8;
+                    Delay::new().delay_ns(100);
+                }
+            }
+
+            Ok(())
+        })
+    }
 }
 
 impl<S: SpiProvider> St7701<'_, S> {
-    pub fn reset(&mut self, delay: &mut impl DelayNs) {
-        self.rst.set_high();
+    pub fn reset(&mut self, delay: &mut impl DelayNs) -> Result<(), S::Error> {
+        let Some(rst) = &mut self.rst else {
+            self.spi.write_command(0x01)?; // Software Reset
+            delay.delay_ms(120);
+            return Ok(());
+        };
+
+        rst.set_high();
         delay.delay_ms(100);
-        self.rst.set_low();
+        rst.set_low();
         delay.delay_ms(100);
-        self.rst.set_high();
+        rst.set_high();
         delay.delay_ms(100);
+
+        Ok(())
     }
 
     pub fn init(&mut self, delay: &mut impl DelayNs) -> Result<(), S::Error> {
-        self.reset(delay);
+        self.reset(delay)?;
+        self.init_registers(delay)
+    }
 
+    /// [Self::init] after the reset.
+    fn init_registers(&mut self, delay: &mut impl DelayNs) -> Result<(), S::Error> {
         self.spi.write_command(0xFF)?;
         self.spi.write_data(&[0x77, 0x01, 0x00, 0x00, 0x10])?;
 
@@ -174,17 +728,11 @@ impl<S: SpiProvider> St7701<'_, S> {
         self.spi.write_command(0xCD)?;
         self.spi.write_data(&[0x08])?;
 
+        let (positive, negative) = GammaPreset::Srgb.tables();
         self.spi.write_command(0xB0)?; // Positive Voltage Gamma Control
-        self.spi.write_data(&[
-            0x02, 0x13, 0x1B, 0x0D, 0x10, 0x05, 0x08, 0x07, 0x07, 0x24, 0x04, 0x11, 0x0E, 0x2C,
-            0x33, 0x1D,
-        ])?;
-
+        self.spi.write_data(positive)?;
         self.spi.write_command(0xB1)?; // Negative Voltage Gamma Control
-        self.spi.write_data(&[
-            0x05, 0x13, 0x1B, 0x0D, 0x11, 0x05, 0x08, 0x07, 0x07, 0x24, 0x04, 0x11, 0x0E, 0x2C,
-            0x33, 0x1D,
-        ])?;
+        self.spi.write_data(negative)?;
 
         self.spi.write_command(0xFF)?;
         self.spi.write_data(&[0x77, 0x01, 0x00, 0x00, 0x11])?;
@@ -214,78 +762,761 @@ impl<S: SpiProvider> St7701<'_, S> {
         self.spi.write_command(0xD0)?;
         self.spi.write_data(&[0x88])?;
 
-        self.spi.write_command(0xE0)?;
-        self.spi.write_data(&[0x00, 0x00, 0x02])?;
+        self.write_gip(&GipConfig::DEFAULT)?;
 
-        self.spi.write_command(0xE1)?;
-        self.spi.write_data(&[
-            0x03, 0xA0, 0x00, 0x00, 0x04, 0xA0, 0x00, 0x00, 0x00, 0x20, 0x20,
-        ])?;
+        self.spi.write_command(0xFF)?;
+        self.spi.write_data(&[0x77, 0x01, 0x00, 0x00, 0x13])?;
 
-        self.spi.write_command(0xE2)?;
-        self.spi.write_data(&[
-            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-        ])?;
+        self.spi.write_command(0xEF)?;
+        self.spi.write_data(&[0x08])?;
 
-        self.spi.write_command(0xE3)?;
-        self.spi.write_data(&[0x00, 0x00, 0x11, 0x00])?;
+        self.spi.write_command(0xFF)?;
+        self.spi.write_data(&[0x77, 0x01, 0x00, 0x00, 0x00])?;
 
-        self.spi.write_command(0xE4)?;
-        self.spi.write_data(&[0x22, 0x00])?;
+        self.spi.write_command(0x36)?;
+        self.spi.write_data(&[INIT_MADCTL])?;
+        self.spi.write_command(0x3A)?;
+        self.spi.write_data(&[INIT_COLMOD])?; // 0x70 RGB888, 0x60 RGB666, 0x50 RGB565
 
-        self.spi.write_command(0xE5)?;
-        self.spi.write_data(&[
-            0x05, 0xEC, 0xA0, 0xA0, 0x07, 0xEE, 0xA0, 0xA0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            0x00, 0x00,
-        ])?;
+        self.spi.write_command(0x11)?; // Sleep Out
 
-        self.spi.write_command(0xE6)?;
-        self.spi.write_data(&[0x00, 0x00, 0x11, 0x00])?;
+        delay.delay_ms(100);
 
-        self.spi.write_command(0xE7)?;
-        self.spi.write_data(&[0x22, 0x00])?;
+        self.spi.write_command(0x29)?; // Display On
 
-        self.spi.write_command(0xE8)?;
-        self.spi.write_data(&[
-            0x06, 0xED, 0xA0, 0xA0, 0x08, 0xEF, 0xA0, 0xA0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            0x00, 0x00,
-        ])?;
+        delay.delay_ms(50);
+
+        if self.idle {
+            self.spi.write_command(0x39)?; // Idle Mode On
+        }
 
-        self.spi.write_command(0xEB)?;
+        Ok(())
+    }
+
+    /// Runs [Self::init] and reads back MADCTL and COLMOD to check it took,
+    /// repeating the whole sequence up to `attempts` times on a mismatch.
+    ///
+    /// Meant for hand-wired SPI that occasionally drops a command and leaves
+    /// the panel dark. Each attempt costs a full init, about half a second
+    /// with [ManualSpi] and a reset pin, mostly in reset and sleep-out
+    /// delays. Returns whether an attempt verified.
+    pub fn init_with_retry(
+        &mut self,
+        delay: &mut impl DelayNs,
+        attempts: usize,
+    ) -> Result<bool, S::Error> {
+        for attempt in 1..=attempts {
+            self.init(delay)?;
+            if self.verify_init()? {
+                return Ok(true);
+            }
+            warn!("Init attempt {} of {} didn't verify", attempt, attempts);
+        }
+
+        Ok(false)
+    }
+
+    fn verify_init(&mut self) -> Result<bool, S::Error> {
+        let mut madctl = 0;
+        let mut colmod = 0;
+        self.spi.read(0x0B, core::slice::from_mut(&mut madctl))?;
+        self.spi.read(0x0C, core::slice::from_mut(&mut colmod))?;
+        Ok(madctl == INIT_MADCTL && colmod == INIT_COLMOD)
+    }
+
+    /// Initializes the panel from `sequence` instead of the built-in one, e.g.
+    /// one decoded with [InitEntry::decode] from a flash partition.
+    ///
+    /// Unlike [Self::init] this doesn't reset the panel on its own, the
+    /// sequence should start with [InitEntry::Reset].
+    pub fn init_from_sequence(
+        &mut self,
+        sequence: &[InitEntry],
+        delay: &mut impl DelayNs,
+    ) -> Result<(), S::Error> {
+        for entry in sequence {
+            match entry {
+                InitEntry::WriteReg { cmd, data } => {
+                    self.spi.write_command(*cmd)?;
+                    if !data.is_empty() {
+                        self.spi.write_data(data)?;
+                    }
+                }
+                InitEntry::DelayMs(ms) => delay.delay_ms(*ms),
+                InitEntry::Reset => self.reset(delay)?,
+            }
+        }
+
+        if self.idle {
+            self.spi.write_command(0x39)?; // Idle Mode On
+        }
+
+        Ok(())
+    }
+
+    /// Selects `page`, runs `f` and returns to the regular command set, also
+    /// when `f` fails. The first error is returned.
+    pub fn with_page<R>(
+        &mut self,
+        page: CommandPage,
+        f: impl FnOnce(&mut Self) -> Result<R, S::Error>,
+    ) -> Result<R, S::Error> {
         self.spi
-            .write_data(&[0x00, 0x00, 0x40, 0x40, 0x00, 0x00, 0x00])?;
+            .write_sequence(0xFF, &[0x77, 0x01, 0x00, 0x00, page as u8])?;
+        let result = f(self);
+        let unlocked = self
+            .spi
+            .write_sequence(0xFF, &[0x77, 0x01, 0x00, 0x00, 0x00]);
+        let value = result?;
+        unlocked.map(|()| value)
+    }
 
-        self.spi.write_command(0xED)?;
-        self.spi.write_data(&[
-            0xFF, 0xFF, 0xFF, 0xBA, 0x0A, 0xBF, 0x45, 0xFF, 0xFF, 0x54, 0xFB, 0xA0, 0xAB, 0xFF,
-            0xFF, 0xFF,
-        ])?;
+    /// Writes the positive (`0xB0`) and negative (`0xB1`) voltage gamma tables
+    /// on command page BK0, then returns to the regular command set.
+    pub fn set_gamma(&mut self, positive: &[u8; 16], negative: &[u8; 16]) -> Result<(), S::Error> {
+        self.with_page(CommandPage::Bk0, |panel| {
+            panel.spi.write_sequence(0xB0, positive)?;
+            panel.spi.write_sequence(0xB1, negative)
+        })
+    }
 
-        self.spi.write_command(0xEF)?;
-        self.spi.write_data(&[0x10, 0x0D, 0x04, 0x08, 0x3F, 0x1F])?;
+    /// Writes the tables of `preset`, see [Self::set_gamma].
+    pub fn set_gamma_preset(&mut self, preset: GammaPreset) -> Result<(), S::Error> {
+        let (positive, negative) = preset.tables();
+        self.set_gamma(positive, negative)
+    }
 
-        self.spi.write_command(0xFF)?;
-        self.spi.write_data(&[0x77, 0x01, 0x00, 0x00, 0x13])?;
+    /// Writes all GIP registers on command page BK1, then returns to the
+    /// regular command set.
+    pub fn configure_gip(&mut self, config: &GipConfig) -> Result<(), S::Error> {
+        self.with_page(CommandPage::Bk1, |panel| panel.write_gip(config))
+    }
 
-        self.spi.write_command(0xEF)?;
-        self.spi.write_data(&[0x08])?;
+    // Expects page BK1 to be selected.
+    fn write_gip(&mut self, config: &GipConfig) -> Result<(), S::Error> {
+        self.spi.write_sequence(0xE0, &config.e0)?;
+        self.spi.write_sequence(0xE1, &config.e1)?;
+        self.spi.write_sequence(0xE2, &config.e2)?;
+        self.spi.write_sequence(0xE3, &config.e3)?;
+        self.spi.write_sequence(0xE4, &config.e4)?;
+        self.spi.write_sequence(0xE5, &config.e5)?;
+        self.spi.write_sequence(0xE6, &config.e6)?;
+        self.spi.write_sequence(0xE7, &config.e7)?;
+        self.spi.write_sequence(0xE8, &config.e8)?;
+        self.spi.write_sequence(0xEB, &config.eb)?;
+        self.spi.write_sequence(0xED, &config.ed)?;
+        self.spi.write_sequence(0xEF, &config.ef)?;
+        Ok(())
+    }
 
-        self.spi.write_command(0xFF)?;
-        self.spi.write_data(&[0x77, 0x01, 0x00, 0x00, 0x00])?;
+    /// Writes `config` to `0xE0` to `0xE2` on command page BK1, then returns
+    /// to the regular command set.
+    ///
+    /// These are the registers with the sizes of [EqualizationConfig], and
+    /// the ones [Self::init] writes; they're the start of the GIP block, the
+    /// same as the first fields of [GipConfig]. Page BK0 has no
+    /// equalization registers there: its `0xE0` to `0xE2` are image
+    /// enhancement (sunlight readability, noise reduction, sharpness), which
+    /// `init` leaves at their reset values.
+    pub fn set_equalization(&mut self, config: &EqualizationConfig) -> Result<(), S::Error> {
+        self.with_page(CommandPage::Bk1, |panel| {
+            panel.spi.write_sequence(0xE0, &config.e0)?;
+            panel.spi.write_sequence(0xE1, &config.e1)?;
+            panel.spi.write_sequence(0xE2, &config.e2)
+        })
+    }
 
-        self.spi.write_command(0x36)?;
-        self.spi.write_data(&[0x08])?;
-        self.spi.write_command(0x3A)?;
-        self.spi.write_data(&[0x60])?; // 0x70 RGB888, 0x60 RGB666, 0x50 RGB565
+    /// Sends a raw DCS or manufacturer command with its parameters in one go.
+    pub fn command(&mut self, cmd: u8, params: &[u8]) -> Result<(), S::Error> {
+        self.spi.write_sequence(cmd, params)
+    }
 
-        self.spi.write_command(0x11)?; // Sleep Out
+    /// Reads the self-diagnostic result (RDDSDR) and fails if a check didn't
+    /// pass. Meant for after [Self::init], before streaming.
+    pub fn post_init_check(&mut self) -> Result<(), DiagError<S::Error>> {
+        let mut byte = 0;
+        self.spi
+            .read(0x0F, core::slice::from_mut(&mut byte))
+            .map_err(DiagError::Spi)?;
+
+        let diag = SelfDiagnostic::from_byte(byte);
+        if diag.register_loading_ok && diag.functionality_ok {
+            Ok(())
+        } else {
+            Err(DiagError::Failed(diag))
+        }
+    }
 
-        Delay::new().delay_ms(100);
+    /// Reads the panel's status registers.
+    pub fn snapshot(&mut self) -> Result<RegisterSnapshot, S::Error> {
+        let mut snapshot = RegisterSnapshot::default();
+
+        self.spi.read(0x04, &mut snapshot.id)?;
+        self.spi.read(0x09, &mut snapshot.status)?;
+        for (cmd, value) in [
+            (0x0A, &mut snapshot.power_mode),
+            (0x0B, &mut snapshot.madctl),
+            (0x0C, &mut snapshot.colmod),
+            (0x0D, &mut snapshot.image_mode),
+            (0x0E, &mut snapshot.signal_mode),
+            (0x0F, &mut snapshot.self_diagnostic),
+        ] {
+            self.spi.read(cmd, core::slice::from_mut(value))?;
+        }
 
-        self.spi.write_command(0x29)?; // Display On
+        Ok(snapshot)
+    }
 
-        Delay::new().delay_ms(50);
+    /// Reads the display status (RDDST) from the panel.
+    ///
+    /// Unlike [Self::is_idle] this is what the panel reports, so it also
+    /// catches a panel that reset or dropped out of sleep on its own. RDDST
+    /// is `0x09`; `0x05` is the DSI error count and doesn't apply here.
+    pub fn read_status(&mut self) -> Result<DisplayStatus, S::Error> {
+        let mut bytes = [0; 4];
+        self.spi.read(0x09, &mut bytes)?;
+        Ok(DisplayStatus::from_bytes(bytes))
+    }
 
+    /// Reads the power mode (RDDPM, `0x0A`). After [Self::init] it reads
+    /// `0x9C`, booster on, sleep out, normal mode and display on; straight
+    /// out of reset `0x08`.
+    pub fn read_power_mode(&mut self) -> Result<u8, S::Error> {
+        let mut mode = 0;
+        self.spi.read(0x0A, core::slice::from_mut(&mut mode))?;
+        Ok(mode)
+    }
+
+    /// Logs `snapshot` as hex, flagging registers that differ from what
+    /// [Self::init] leaves behind. Returns the number of differences.
+    ///
+    /// Only the power mode, MADCTL and COLMOD have a known expected value,
+    /// the rest are logged for reference.
+    pub fn diff(&self, snapshot: &RegisterSnapshot) -> usize {
+        // Booster on, sleep out, normal mode, display on, plus idle if set.
+        let power_mode = 0x9C | if self.idle { 0x40 } else { 0x00 };
+        let expected = [
+            (0x0A, "RDDPM", snapshot.power_mode, power_mode),
+            (0x0B, "RDDMADCTL", snapshot.madctl, INIT_MADCTL),
+            (0x0C, "RDDCOLMOD", snapshot.colmod, INIT_COLMOD),
+        ];
+
+        info!("0x04 RDDID: {:02X?}", snapshot.id);
+        info!("0x09 RDDST: {:02X?}", snapshot.status);
+
+        let mut differences = 0;
+        for (cmd, name, value, expected) in expected {
+            if value == expected {
+                info!("{cmd:#04X} {name}: {value:#04X}");
+            } else {
+                info!("{cmd:#04X} {name}: {value:#04X}, expected {expected:#04X} <<<");
+                differences += 1;
+            }
+        }
+
+        info!("0x0D RDDIM: {:#04X}", snapshot.image_mode);
+        info!("0x0E RDDSM: {:#04X}", snapshot.signal_mode);
+        info!("0x0F RDDSDR: {:#04X}", snapshot.self_diagnostic);
+
+        differences
+    }
+
+    /// Sets the VCOM amplitude (`0xB1` on page BK1), which [Self::init] sets
+    /// to `0x43`. Tuning it removes flicker on some panels.
+    pub fn set_vcom(&mut self, vcom: u8) -> Result<(), S::Error> {
+        self.with_page(CommandPage::Bk1, |panel| {
+            panel.spi.write_sequence(0xB1, &[vcom])
+        })
+    }
+
+    /// Writes all of `profile`: the gamma tables on page BK0, VCOM, VGH and
+    /// VGL on page BK1 and the brightness on the regular command set, in that
+    /// order.
+    ///
+    /// Going through the individual setters would select a page and return
+    /// to the regular command set for each of them. This switches from BK0
+    /// straight to BK1 and back only once at the end, three page selects
+    /// instead of four for [Self::set_gamma] and [Self::set_vcom] alone. The
+    /// page is restored also when a write fails, see [Self::with_page].
+    pub fn apply_profile(&mut self, profile: &PanelProfile) -> Result<(), S::Error> {
+        self.with_page(CommandPage::Bk0, |panel| {
+            panel.spi.write_sequence(0xB0, &profile.gamma.0)?; // Positive Voltage Gamma Control
+            panel.spi.write_sequence(0xB1, &profile.gamma.1)?; // Negative Voltage Gamma Control
+
+            panel
+                .spi
+                .write_sequence(0xFF, &[0x77, 0x01, 0x00, 0x00, CommandPage::Bk1 as u8])?;
+            panel.spi.write_sequence(0xB1, &[profile.vcom])?; // VCOM amplitude setting
+            panel.spi.write_sequence(0xB2, &[profile.vgh])?; // VGH Voltage setting
+            panel.spi.write_sequence(0xB5, &[profile.vgl]) // VGL Voltage setting
+        })?;
+
+        self.spi.write_sequence(0x51, &[profile.brightness]) // Write Display Brightness
+    }
+
+    /// Turns the display output off (DISPOFF, `0x28`); the panel keeps
+    /// running but shows blank.
+    pub fn display_off(&mut self) -> Result<(), S::Error> {
+        self.spi.write_command(0x28) // Display Off
+    }
+
+    /// Enters sleep mode (SLPIN, `0x10`) and waits for it to settle. Only
+    /// [Self::init] brings the panel back.
+    pub fn sleep_in(&mut self, delay: &mut impl DelayNs) -> Result<(), S::Error> {
+        self.spi.write_command(0x10)?; // Sleep In
+        delay.delay_ms(120);
+        Ok(())
+    }
+
+    /// Whether the panel is in idle mode.
+    pub fn is_idle(&self) -> bool {
+        self.idle
+    }
+
+    /// Puts the panel into idle mode (IDMON, `0x39`).
+    ///
+    /// The panel halves its internal refresh rate and drops its color depth
+    /// from 18 to 8 bits to save power. The DPI stream from the ESP32 keeps
+    /// running at the same rate; only the panel's own refresh slows down.
+    ///
+    /// The mode is remembered and re-applied by [Self::init].
+    pub fn enter_idle_mode(&mut self) -> Result<(), S::Error> {
+        self.spi.write_command(0x39)?; // Idle Mode On
+        self.idle = true;
         Ok(())
     }
+
+    /// Leaves idle mode (IDMOFF, `0x38`), restoring full refresh rate and
+    /// color depth.
+    pub fn exit_idle_mode(&mut self) -> Result<(), S::Error> {
+        self.spi.write_command(0x38)?; // Idle Mode Off
+        self.idle = false;
+        Ok(())
+    }
+
+    /// Sets the color shown in blanking and partial-off regions, if the
+    /// panel has a register for it. Returns whether it was set.
+    ///
+    /// None of the ST7701 variants this driver knows of (identified with
+    /// RDDID) document such a register, so this currently logs a warning and
+    /// leaves the panel untouched; those regions stay black.
+    pub fn set_blanking_color(&mut self, r: u8, g: u8, b: u8) -> Result<bool, S::Error> {
+        let mut id = [0; 3];
+        self.spi.read(0x04, &mut id)?;
+
+        warn!(
+            "Panel {:02X?} has no blanking color register, ignoring ({}, {}, {})",
+            id, r, g, b
+        );
+        Ok(false)
+    }
+
+    /// Limits the panel to the lines `first..=last` (PTLAR, `0x30`, then
+    /// PTLON, `0x12`), with the rest of the panel undriven.
+    ///
+    /// `background` is set first with [Self::set_blanking_color], so that the
+    /// undriven band can match the UI instead of being black where the panel
+    /// supports it; the return value says whether it did. The DPI stream
+    /// keeps sending whole frames, the panel only ignores the lines outside
+    /// the area. [Self::exit_partial_mode] goes back to the full panel.
+    pub fn enter_partial_mode(
+        &mut self,
+        first: u16,
+        last: u16,
+        background: (u8, u8, u8),
+    ) -> Result<bool, S::Error> {
+        let (r, g, b) = background;
+        let background_set = self.set_blanking_color(r, g, b)?;
+
+        let [first_hi, first_lo] = first.to_be_bytes();
+        let [last_hi, last_lo] = last.to_be_bytes();
+        self.spi
+            .write_sequence(0x30, &[first_hi, first_lo, last_hi, last_lo])?; // Partial Area
+        self.spi.write_command(0x12)?; // Partial Mode On
+
+        Ok(background_set)
+    }
+
+    /// Leaves partial mode (NORON, `0x13`), driving the whole panel again.
+    pub fn exit_partial_mode(&mut self) -> Result<(), S::Error> {
+        self.spi.write_command(0x13) // Normal Display Mode On
+    }
+
+    /// The CABC mode last set with [Self::set_cabc].
+    pub fn cabc(&self) -> CabcMode {
+        self.cabc
+    }
+
+    /// Sets content adaptive brightness control (WRCABC, `0x55`).
+    pub fn set_cabc(&mut self, mode: CabcMode) -> Result<(), S::Error> {
+        self.spi.write_sequence(0x55, &[mode as u8])?;
+        self.cabc = mode;
+        Ok(())
+    }
+
+    /// Sets the lowest brightness CABC may dim to (WRCABCMB, `0x5E`).
+    pub fn set_cabc_minimum_brightness(&mut self, min: u8) -> Result<(), S::Error> {
+        self.spi.write_sequence(0x5E, &[min])
+    }
+
+    /// Reads back the CABC minimum brightness (RDCABCMB, `0x5F`).
+    pub fn read_cabc_minimum(&mut self) -> Result<u8, S::Error> {
+        let mut min = 0;
+        self.spi.read(0x5F, core::slice::from_mut(&mut min))?;
+        Ok(min)
+    }
+}
+
+/// The panel's [St7701::geometry], so embedded-graphics code can lay out
+/// with `bounding_box()`. `Dimensions` comes with it through
+/// embedded-graphics' blanket impl, a box at the origin of this size.
+#[cfg(feature = "embedded-graphics")]
+impl<S> embedded_graphics_core::geometry::OriginDimensions for St7701<'_, S> {
+    fn size(&self) -> embedded_graphics_core::geometry::Size {
+        embedded_graphics_core::geometry::Size::new(
+            self.geometry.width as u32,
+            self.geometry.height as u32,
+        )
+    }
+}
+
+/// `N` panels on one SPI bus, each with its own chip select and reset line,
+/// brought up together.
+///
+/// Every panel's [SpiProvider] drives its own CS. The DPI signals are wired
+/// to all panels in parallel, and the ST7701 has no way to take only part of
+/// a DPI line, so they all show the same image: a frame `N` panels wide
+/// needs a DPI bus per panel, or a controller that splits the stream.
+pub struct St7701Chain<'a, S, const N: usize> {
+    pub displays: [St7701<'a, S>; N],
+}
+
+impl<'a, S: SpiProvider, const N: usize> St7701Chain<'a, S, N> {
+    pub fn new(displays: [St7701<'a, S>; N]) -> Self {
+        Self { displays }
+    }
+
+    /// Resets all panels at once, so they leave reset together.
+    ///
+    /// Reset pins are toggled back to back, a few ns apart, with one set of
+    /// delays for all. Panels without a reset pin get a software reset each,
+    /// then a shared 120ms wait.
+    pub fn reset_all(&mut self, delay: &mut impl DelayNs) -> Result<(), S::Error> {
+        if self.displays.iter().any(|d| d.rst.is_some()) {
+            for level in [Level::High, Level::Low, Level::High] {
+                for rst in self.displays.iter_mut().filter_map(|d| d.rst.as_mut()) {
+                    rst.set_level(level);
+                }
+                delay.delay_ms(100);
+            }
+        }
+
+        let mut software = false;
+        for display in self.displays.iter_mut().filter(|d| d.rst.is_none()) {
+            display.spi.write_command(0x01)?; // Software Reset
+            software = true;
+        }
+        if software {
+            delay.delay_ms(120);
+        }
+
+        Ok(())
+    }
+
+    /// [Self::reset_all], then the [St7701::init] sequence on each panel in
+    /// turn, stopping at the first error.
+    pub fn init_all(&mut self, delay: &mut impl DelayNs) -> Result<(), S::Error> {
+        self.reset_all(delay)?;
+        for display in &mut self.displays {
+            display.init_registers(delay)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::{mock::*, *};
+
+    const MS: u64 = 1_000_000;
+
+    fn panel() -> (St7701<'static, MockSpi>, MockClock) {
+        let spi = MockSpi::new();
+        let clock = spi.clock.clone();
+        (St7701::new(spi, None), clock)
+    }
+
+    /// The pages selected with `0xFF`, in order.
+    fn pages(spi: &MockSpi) -> Vec<u8> {
+        spi.commands()
+            .iter()
+            .filter(|(cmd, _)| *cmd == 0xFF)
+            .map(|(_, data)| {
+                assert_eq!(
+                    data[..4],
+                    [0x77, 0x01, 0x00, 0x00],
+                    "page unlock {data:02X?}"
+                );
+                assert_eq!(data.len(), 5, "page unlock {data:02X?}");
+                data[4]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn init_unlocks_each_page_and_returns_to_the_regular_set() {
+        let (mut panel, mut clock) = panel();
+        panel.init(&mut clock).unwrap();
+
+        assert_eq!(pages(&panel.spi), [0x10, 0x11, 0x13, 0x00]);
+
+        let commands = panel.spi.commands();
+        let last_select = commands.iter().rposition(|(cmd, _)| *cmd == 0xFF).unwrap();
+        let after: Vec<_> = commands[last_select + 1..]
+            .iter()
+            .map(|(cmd, data)| (*cmd, data.as_slice()))
+            .collect();
+        assert_eq!(
+            after,
+            [
+                (0x36, &[INIT_MADCTL][..]),
+                (0x3A, &[INIT_COLMOD][..]),
+                (0x11, &[][..]),
+                (0x29, &[][..]),
+            ]
+        );
+    }
+
+    #[test]
+    fn init_waits_for_reset_and_sleep_out() {
+        let (mut panel, mut clock) = panel();
+        panel.init(&mut clock).unwrap();
+
+        let spi = &panel.spi;
+        assert_eq!(spi.command_times[0].0, 0x01, "software reset first");
+        assert!(spi.command_times[1].1 - spi.command_times[0].1 >= 120 * MS);
+
+        // The reproduction's own wait, shorter than the bringup state
+        // machine's 120ms.
+        let sleep_out = spi.time_of(0x11).unwrap();
+        let display_on = spi.time_of(0x29).unwrap();
+        assert_eq!(display_on - sleep_out, 100 * MS);
+    }
+
+    #[test]
+    fn blanking_color_is_a_logged_no_op() {
+        let (mut panel, _) = panel();
+        panel.spi.read_response.extend([0x88, 0x02, 0x00]);
+
+        assert_eq!(panel.set_blanking_color(0x10, 0x20, 0x30), Ok(false));
+        panel.spi.assert_command_sequence(&[(0x04, &[])]);
+    }
+
+    #[test]
+    fn partial_mode_sets_the_area_and_returns_to_normal() {
+        let (mut panel, _) = panel();
+
+        assert_eq!(panel.enter_partial_mode(40, 439, (0, 0, 0xFF)), Ok(false));
+        panel.exit_partial_mode().unwrap();
+
+        panel.spi.assert_command_sequence(&[
+            (0x04, &[]),
+            (0x30, &[0x00, 0x28, 0x01, 0xB7]),
+            (0x12, &[]),
+            (0x13, &[]),
+        ]);
+    }
+
+    #[test]
+    fn sleep_in_waits_before_returning() {
+        let (mut panel, mut clock) = panel();
+        panel.sleep_in(&mut clock).unwrap();
+
+        let sleep_in = panel.spi.time_of(0x10).unwrap();
+        assert!(clock.now_ns() - sleep_in >= 120 * MS);
+    }
+
+    #[test]
+    fn init_reapplies_idle_mode() {
+        let (mut panel, mut clock) = panel();
+        panel.enter_idle_mode().unwrap();
+        panel.spi.writes.clear();
+
+        panel.init(&mut clock).unwrap();
+
+        assert_eq!(panel.spi.commands().last().unwrap().0, 0x39);
+    }
+
+    #[test]
+    fn with_page_returns_to_the_regular_set_on_error() {
+        let (mut panel, _) = panel();
+        panel.spi.fail_on = Some(0xB1);
+
+        assert_eq!(panel.set_vcom(0x40), Err(MockError));
+        assert_eq!(pages(&panel.spi), [0x11, 0x00]);
+    }
+
+    fn inits(spi: &MockSpi) -> usize {
+        spi.commands()
+            .iter()
+            .filter(|(cmd, _)| *cmd == 0x11)
+            .count()
+    }
+
+    #[test]
+    fn init_with_retry_repeats_after_a_bad_read_back() {
+        let (mut panel, mut clock) = panel();
+        // MADCTL and COLMOD for each attempt, the first with MADCTL lost.
+        panel
+            .spi
+            .read_response
+            .extend([0x00, INIT_COLMOD, INIT_MADCTL, INIT_COLMOD]);
+
+        assert_eq!(panel.init_with_retry(&mut clock, 3), Ok(true));
+        assert_eq!(inits(&panel.spi), 2);
+        assert!(panel.spi.read_response.is_empty());
+    }
+
+    #[test]
+    fn init_with_retry_gives_up_after_the_last_attempt() {
+        let (mut panel, mut clock) = panel();
+
+        assert_eq!(panel.init_with_retry(&mut clock, 3), Ok(false));
+        assert_eq!(inits(&panel.spi), 3);
+    }
+
+    #[test]
+    fn gamma_presets_fit_their_fields() {
+        for preset in [
+            GammaPreset::Linear,
+            GammaPreset::Srgb,
+            GammaPreset::HighContrast,
+        ] {
+            let (positive, negative) = preset.tables();
+            for table in [positive, negative] {
+                for (i, (&code, bits)) in table.iter().zip(GAMMA_FIELD_BITS).enumerate() {
+                    assert!(code < 1 << bits, "{preset:?} entry {i}: {code:#04X}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn gamma_presets_scale_only_the_midtones() {
+        assert_eq!(GammaPreset::Srgb.tables(), (&SRGB_POSITIVE, &SRGB_NEGATIVE));
+        assert_eq!(
+            GammaPreset::Linear.tables().0,
+            &[
+                0x02, 0x13, 0x1B, 0x0D, 0x10, 0x03, 0x04, 0x04, 0x04, 0x12, 0x02, 0x11, 0x0E, 0x2C,
+                0x33, 0x1D,
+            ]
+        );
+        assert_eq!(
+            GammaPreset::HighContrast.tables().0,
+            &[
+                0x02, 0x13, 0x1B, 0x0D, 0x10, 0x08, 0x0C, 0x0B, 0x0B, 0x36, 0x06, 0x11, 0x0E, 0x2C,
+                0x33, 0x1D,
+            ]
+        );
+    }
+
+    #[test]
+    fn set_gamma_preset_writes_distinct_tables_on_bk0() {
+        let presets = [
+            GammaPreset::Linear,
+            GammaPreset::Srgb,
+            GammaPreset::HighContrast,
+        ];
+        for (i, a) in presets.iter().enumerate() {
+            for b in &presets[i + 1..] {
+                assert_ne!(a.tables(), b.tables(), "{a:?} and {b:?}");
+            }
+        }
+
+        for preset in presets {
+            let (mut panel, _) = panel();
+            panel.set_gamma_preset(preset).unwrap();
+
+            let (positive, negative) = preset.tables();
+            panel.spi.assert_command_sequence(&[
+                (0xFF, &[0x77, 0x01, 0x00, 0x00, 0x10]),
+                (0xB0, positive),
+                (0xB1, negative),
+                (0xFF, &[0x77, 0x01, 0x00, 0x00, 0x00]),
+            ]);
+        }
+    }
+
+    #[test]
+    fn apply_profile_selects_each_page_once() {
+        let (mut panel, _) = panel();
+        let profile = PanelProfile {
+            gamma: ([0x01; 16], [0x02; 16]),
+            vcom: 0x40,
+            vgh: 0x45,
+            vgl: 0x47,
+            brightness: 0x80,
+        };
+        panel.apply_profile(&profile).unwrap();
+
+        panel.spi.assert_command_sequence(&[
+            (0xFF, &[0x77, 0x01, 0x00, 0x00, 0x10]),
+            (0xB0, &[0x01; 16]),
+            (0xB1, &[0x02; 16]),
+            (0xFF, &[0x77, 0x01, 0x00, 0x00, 0x11]),
+            (0xB1, &[0x40]),
+            (0xB2, &[0x45]),
+            (0xB5, &[0x47]),
+            (0xFF, &[0x77, 0x01, 0x00, 0x00, 0x00]),
+            (0x51, &[0x80]),
+        ]);
+    }
+
+    #[test]
+    fn post_init_check_needs_both_diagnostic_bits() {
+        let diag = |register_loading_ok, functionality_ok| SelfDiagnostic {
+            register_loading_ok,
+            functionality_ok,
+        };
+        for (byte, expected) in [
+            (0xC0, Ok(())),
+            // The reserved low bits don't matter.
+            (0xFF, Ok(())),
+            (0x40, Err(DiagError::Failed(diag(false, true)))),
+            (0x80, Err(DiagError::Failed(diag(true, false)))),
+            (0x00, Err(DiagError::Failed(diag(false, false)))),
+        ] {
+            let (mut panel, _) = panel();
+            panel.spi.read_response.push_back(byte);
+
+            assert_eq!(panel.post_init_check(), expected, "RDDSDR {byte:#04X}");
+            panel.spi.assert_command_sequence(&[(0x0F, &[])]);
+        }
+    }
+
+    #[test]
+    fn command_sends_its_params_in_one_cs_assertion() {
+        let (mut panel, _) = panel();
+        panel.command(0xE0, &[0x00, 0x00, 0x02]).unwrap();
+        panel.command(0x29, &[]).unwrap();
+
+        panel
+            .spi
+            .assert_command_sequence(&[(0xE0, &[0x00, 0x00, 0x02]), (0x29, &[])]);
+        assert_eq!(panel.spi.cs_assertions, 2);
+    }
+
+    #[test]
+    fn release_returns_the_spi_and_reset_pin() {
+        let (mut panel, mut clock) = panel();
+        panel.display_off().unwrap();
+        panel.sleep_in(&mut clock).unwrap();
+
+        let (spi, rst) = panel.release();
+        assert!(rst.is_none());
+        // The SPI that was handed in, with the teardown on it.
+        spi.assert_command_sequence(&[(0x28, &[]), (0x10, &[])]);
+    }
 }
@@ -0,0 +1,128 @@
+//! An [SpiProvider] that records instead of talking to a panel, for checking
+//! command sequences without hardware.
+
+use alloc::{collections::VecDeque, rc::Rc, vec::Vec};
+use core::cell::Cell;
+
+use embedded_hal::delay::DelayNs;
+
+use super::SpiProvider;
+
+/// A [DelayNs] that only advances a counter, shared between its clones.
+#[derive(Debug, Default, Clone)]
+pub struct MockClock(Rc<Cell<u64>>);
+
+impl MockClock {
+    /// Nanoseconds waited so far.
+    pub fn now_ns(&self) -> u64 {
+        self.0.get()
+    }
+}
+
+impl DelayNs for MockClock {
+    fn delay_ns(&mut self, ns: u32) {
+        self.0.set(self.0.get() + ns as u64);
+    }
+}
+
+/// The error [MockSpi] fails with, see [MockSpi::fail_on].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MockError;
+
+/// Records every byte written and answers reads from a queue.
+#[derive(Debug, Default)]
+pub struct MockSpi {
+    /// Bytes written, with whether each was a command.
+    pub writes: Vec<(bool, u8)>,
+    /// Every command, with the time of [Self::clock] when it was written.
+    pub command_times: Vec<(u8, u64)>,
+    /// Bytes handed out by reads, in order. Reads past the end get `0`.
+    pub read_response: VecDeque<u8>,
+    /// The clock [Self::command_times] are taken from, hand a clone of it to
+    /// the code under test as its delay.
+    pub clock: MockClock,
+    /// A command that fails instead of being recorded.
+    pub fail_on: Option<u8>,
+    /// Times CS was asserted, see [SpiProvider::while_cs].
+    pub cs_assertions: usize,
+}
+
+impl MockSpi {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The writes grouped into commands, each with the data bytes after it.
+    /// Data before the first command is dropped.
+    pub fn commands(&self) -> Vec<(u8, Vec<u8>)> {
+        let mut commands: Vec<(u8, Vec<u8>)> = Vec::new();
+        for &(is_command, byte) in &self.writes {
+            if is_command {
+                commands.push((byte, Vec::new()));
+            } else if let Some((_, data)) = commands.last_mut() {
+                data.push(byte);
+            }
+        }
+        commands
+    }
+
+    /// When `command` was first written, if at all.
+    pub fn time_of(&self, command: u8) -> Option<u64> {
+        self.command_times
+            .iter()
+            .find(|(cmd, _)| *cmd == command)
+            .map(|(_, at)| *at)
+    }
+
+    /// Panics unless the recorded commands are exactly `expected`, naming
+    /// the first one that differs.
+    pub fn assert_command_sequence(&self, expected: &[(u8, &[u8])]) {
+        let commands = self.commands();
+        for (i, (cmd, data)) in expected.iter().enumerate() {
+            let Some((got_cmd, got_data)) = commands.get(i) else {
+                panic!("command {i}: expected {cmd:#04X}, got nothing");
+            };
+            assert!(
+                got_cmd == cmd && got_data.as_slice() == *data,
+                "command {i}: expected {cmd:#04X} {data:02X?}, got {got_cmd:#04X} {got_data:02X?}"
+            );
+        }
+        assert_eq!(
+            commands.len(),
+            expected.len(),
+            "{} commands more than expected",
+            commands.len().saturating_sub(expected.len())
+        );
+    }
+}
+
+impl SpiProvider for MockSpi {
+    type Error = MockError;
+
+    fn write_byte(&mut self, is_command: bool, byte: u8) -> Result<(), Self::Error> {
+        if is_command {
+            if self.fail_on == Some(byte) {
+                return Err(MockError);
+            }
+            self.command_times.push((byte, self.clock.now_ns()));
+        }
+        self.writes.push((is_command, byte));
+        Ok(())
+    }
+
+    fn read(&mut self, command: u8, buf: &mut [u8]) -> Result<(), Self::Error> {
+        self.write_byte(true, command)?;
+        for byte in buf {
+            *byte = self.read_response.pop_front().unwrap_or(0);
+        }
+        Ok(())
+    }
+
+    fn while_cs<F, R>(&mut self, func: F) -> R
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        self.cs_assertions += 1;
+        func(self)
+    }
+}
@@ -0,0 +1,301 @@
+//! Starting DPI frames on an external trigger, for several boards that drive
+//! panels in lockstep or a panel following an incoming video source.
+//!
+//! # Genlock on the ESP32-S3
+//!
+//! The LCD_CAM's LCD side only generates sync, it has no mode where HSYNC
+//! or VSYNC are inputs that pace its timing generator, and the DPI frame
+//! can't be nudged once it runs. So there's no true genlock, only what
+//! [Genlock] does with a GPIO input:
+//!
+//! - Frame start: the first frame starts within a few µs of an external VSYNC
+//!   edge, see [DpiSyncExt::send_on_trigger].
+//! - Phase monitoring: [Genlock::phase] measures how far the LCD's own VSYNC
+//!   has drifted from the external one.
+//! - Re-alignment: restart the transfer on the next edge when it has drifted
+//!   too far. The restart costs a dropped frame.
+//!
+//! The frame rates have to match to begin with: set the same frame timing as
+//! the source and a pclk that's as close as the integer divider allows. An
+//! external HSYNC can't be followed at all, lines stay on the ESP32's
+//! crystal, so only the external VSYNC is wired.
+
+use embedded_hal::digital::InputPin;
+
+use crate::{
+    display::vsync::{self, Timeout},
+    hal::{
+        DriverMode,
+        dma::{DmaError, DmaTxBuffer},
+        lcd_cam::lcd::dpi::{Dpi, DpiTransfer},
+        time::{Duration, Instant},
+    },
+};
+
+/// Errors of [DpiSyncExt::send_on_trigger].
+#[derive(Debug)]
+pub enum SyncError {
+    /// No rising edge on the trigger pin in time.
+    Timeout,
+    Dma(DmaError),
+}
+
+impl From<Timeout> for SyncError {
+    fn from(_: Timeout) -> Self {
+        Self::Timeout
+    }
+}
+
+/// Busy-waits for a rising edge on `trigger`: low, then high.
+///
+/// Read errors count as low, so a pin that keeps failing times out.
+pub fn wait_for_rising_edge(trigger: &mut impl InputPin, timeout: Duration) -> Result<(), Timeout> {
+    wait_for_edge(trigger, SyncPolarity::ActiveHigh, timeout)
+}
+
+/// Busy-waits for the leading edge of a sync pulse of `polarity` on
+/// `trigger`. Read errors count as low.
+fn wait_for_edge(
+    trigger: &mut impl InputPin,
+    polarity: SyncPolarity,
+    timeout: Duration,
+) -> Result<(), Timeout> {
+    wait_for_edge_on(trigger, polarity, timeout, Instant::now)
+}
+
+/// [wait_for_edge] on the clock `now`.
+fn wait_for_edge_on(
+    trigger: &mut impl InputPin,
+    polarity: SyncPolarity,
+    timeout: Duration,
+    now: impl FnMut() -> Instant,
+) -> Result<(), Timeout> {
+    let active = polarity == SyncPolarity::ActiveHigh;
+    // A pin already active at the start has to go inactive first, or the
+    // wait would end in the middle of a pulse.
+    let mut armed = false;
+    let edge = || {
+        let level = trigger.is_high().unwrap_or(false);
+        armed |= level != active;
+        armed && level == active
+    };
+    vsync::poll_until_on(edge, timeout, now)
+}
+
+/// Level of an external sync pulse. Analog video and most DPI sources use
+/// active low sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncPolarity {
+    ActiveLow,
+    ActiveHigh,
+}
+
+/// Slaves the DPI frame start to an external VSYNC on a GPIO input, as far
+/// as the ESP32-S3 allows, see the [module docs](self).
+///
+/// The pin is anything implementing [InputPin], e.g. an
+/// `esp_hal::gpio::Input` with the pull matching the source's idle level.
+pub struct Genlock<P> {
+    vsync: P,
+    polarity: SyncPolarity,
+    timeout: Duration,
+}
+
+impl<P: InputPin> Genlock<P> {
+    /// Follows active low sync on `vsync`, waiting at most 100ms for an
+    /// edge, enough for sources down to 10Hz.
+    pub fn new(vsync: P) -> Self {
+        Self {
+            vsync,
+            polarity: SyncPolarity::ActiveLow,
+            timeout: Duration::from_millis(100),
+        }
+    }
+
+    /// Sets the polarity of the external VSYNC.
+    pub fn with_polarity(mut self, polarity: SyncPolarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+
+    /// Sets how long to wait for an external edge before giving up.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Starts sending `buf` continuously on the next external VSYNC.
+    pub fn start<'d, B: DmaTxBuffer, Dm: DriverMode>(
+        &mut self,
+        dpi: Dpi<'d, Dm>,
+        buf: B,
+    ) -> Result<DpiTransfer<'d, B, Dm>, (SyncError, Dpi<'d, Dm>, B)> {
+        if let Err(err) = wait_for_edge(&mut self.vsync, self.polarity, self.timeout) {
+            return Err((err.into(), dpi, buf));
+        }
+
+        dpi.send(true, buf)
+            .map_err(|(err, dpi, buf)| (SyncError::Dma(err), dpi, buf))
+    }
+
+    /// Time from the next external VSYNC to the LCD's own, while a transfer
+    /// started with [Self::start] runs.
+    ///
+    /// Right after the start this is however far into the frame the LCD
+    /// timing puts its VSYNC pulse; note it as the reference. Drifting away
+    /// from it means the frame rates differ, and past a whole frame it wraps
+    /// around. Clears the VSYNC interrupt flag, see [vsync].
+    #[cfg(target_arch = "xtensa")]
+    pub fn phase(&mut self) -> Result<Duration, Timeout> {
+        wait_for_edge(&mut self.vsync, self.polarity, self.timeout)?;
+        vsync::clear();
+        let edge = Instant::now();
+        vsync::wait_timeout(self.timeout)?;
+        Ok(edge.elapsed())
+    }
+
+    /// Hands back the pin.
+    pub fn release(self) -> P {
+        self.vsync
+    }
+}
+
+/// Trigger synchronization for [Dpi].
+pub trait DpiSyncExt<'d, Dm: DriverMode>: Sized {
+    /// Waits for a rising edge on `trigger`, then starts sending `buf` like
+    /// `Dpi::send`.
+    ///
+    /// Drive `trigger` on every board from one source, e.g. a GPIO or LEDC
+    /// output of one of them, and all panels start their first frame
+    /// together. The spread between boards is the edge detection, a poll
+    /// loop of well under 1µs at 240MHz, plus the differences in how long
+    /// `Dpi::send` takes to prepare `buf`'s descriptors; call this with
+    /// interrupts disabled and the same kind of buffer everywhere and that
+    /// stays within a few µs, a fraction of a line.
+    ///
+    /// With `next_frame_en` the LCD then runs free on each board's own
+    /// crystal. Two ±10ppm crystals drift apart by up to 20µs per second,
+    /// about half a line at 12MHz and 500 pixels, so stop and restart on the
+    /// trigger every few seconds if that matters. Without it only one frame
+    /// is sent, and a loop of `send_on_trigger` and `DpiTransfer::wait`
+    /// aligns every frame at the cost of the blanking the restart adds.
+    ///
+    /// Gives `dpi` and `buf` back if the edge doesn't come within `timeout`.
+    fn send_on_trigger<B: DmaTxBuffer>(
+        self,
+        next_frame_en: bool,
+        buf: B,
+        trigger: &mut impl InputPin,
+        timeout: Duration,
+    ) -> Result<DpiTransfer<'d, B, Dm>, (SyncError, Self, B)>;
+}
+
+impl<'d, Dm: DriverMode> DpiSyncExt<'d, Dm> for Dpi<'d, Dm> {
+    fn send_on_trigger<B: DmaTxBuffer>(
+        self,
+        next_frame_en: bool,
+        buf: B,
+        trigger: &mut impl InputPin,
+        timeout: Duration,
+    ) -> Result<DpiTransfer<'d, B, Dm>, (SyncError, Self, B)> {
+        if let Err(err) = wait_for_rising_edge(trigger, timeout) {
+            return Err((err.into(), self, buf));
+        }
+
+        self.send(next_frame_en, buf)
+            .map_err(|(err, dpi, buf)| (SyncError::Dma(err), dpi, buf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use embedded_hal::digital::{ErrorKind, ErrorType};
+
+    use super::*;
+
+    /// Reads `levels` in order, then keeps reading the last one.
+    struct Trigger {
+        levels: Vec<Result<bool, ErrorKind>>,
+        reads: usize,
+    }
+
+    impl Trigger {
+        fn new(levels: &[Result<bool, ErrorKind>]) -> Self {
+            Self {
+                levels: levels.to_vec(),
+                reads: 0,
+            }
+        }
+    }
+
+    impl ErrorType for Trigger {
+        type Error = ErrorKind;
+    }
+
+    impl InputPin for Trigger {
+        fn is_high(&mut self) -> Result<bool, Self::Error> {
+            let level = self.levels[self.reads.min(self.levels.len() - 1)];
+            self.reads += 1;
+            level
+        }
+
+        fn is_low(&mut self) -> Result<bool, Self::Error> {
+            self.is_high().map(|high| !high)
+        }
+    }
+
+    /// A clock that moves 1ms on every read.
+    fn ticking_clock() -> impl FnMut() -> Instant {
+        let mut now = Instant::EPOCH;
+        move || {
+            now += Duration::from_millis(1);
+            now
+        }
+    }
+
+    fn wait(levels: &[Result<bool, ErrorKind>], polarity: SyncPolarity) -> Result<(), Timeout> {
+        let mut trigger = Trigger::new(levels);
+        wait_for_edge_on(
+            &mut trigger,
+            polarity,
+            Duration::from_millis(20),
+            ticking_clock(),
+        )
+    }
+
+    #[test]
+    fn rising_edge_ends_the_wait() {
+        let levels = [Ok(false), Ok(false), Ok(true)];
+        assert_eq!(wait(&levels, SyncPolarity::ActiveHigh), Ok(()));
+    }
+
+    #[test]
+    fn falling_edge_ends_an_active_low_wait() {
+        let levels = [Ok(true), Ok(false)];
+        assert_eq!(wait(&levels, SyncPolarity::ActiveLow), Ok(()));
+    }
+
+    #[test]
+    fn pulse_in_progress_is_not_an_edge() {
+        assert_eq!(wait(&[Ok(true)], SyncPolarity::ActiveHigh), Err(Timeout));
+        let levels = [Ok(true), Ok(true), Ok(false), Ok(true)];
+        assert_eq!(wait(&levels, SyncPolarity::ActiveHigh), Ok(()));
+    }
+
+    #[test]
+    fn missing_trigger_times_out() {
+        assert_eq!(wait(&[Ok(false)], SyncPolarity::ActiveHigh), Err(Timeout));
+    }
+
+    #[test]
+    fn read_errors_count_as_low() {
+        let levels = [Err(ErrorKind::Other), Ok(true)];
+        assert_eq!(wait(&levels, SyncPolarity::ActiveHigh), Ok(()));
+        assert_eq!(
+            wait(&[Err(ErrorKind::Other)], SyncPolarity::ActiveHigh),
+            Err(Timeout)
+        );
+    }
+}
@@ -0,0 +1,278 @@
+//! Polling the LCD VSYNC interrupt flag.
+//!
+//! The flag is set once per frame while a DPI transfer runs. Everything here
+//! polls and clears the raw flag, so nothing else should be listening for or
+//! clearing it at the same time.
+
+#[cfg(target_arch = "xtensa")]
+use crate::hal::peripherals::LCD_CAM;
+use crate::hal::{
+    lcd_cam::lcd::dpi::FrameTiming,
+    time::{Duration, Instant, Rate},
+};
+
+/// A blocking wait hit its deadline, e.g. because the transfer stalled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeout;
+
+/// Whether a VSYNC happened since the flag was last cleared.
+#[cfg(target_arch = "xtensa")]
+pub fn is_pending() -> bool {
+    LCD_CAM::regs()
+        .lc_dma_int_raw()
+        .read()
+        .lcd_vsync_int_raw()
+        .bit()
+}
+
+/// Clears the VSYNC flag.
+#[cfg(target_arch = "xtensa")]
+pub fn clear() {
+    LCD_CAM::regs()
+        .lc_dma_int_clr()
+        .write(|w| w.lcd_vsync_int_clr().set_bit());
+}
+
+/// The VSYNC flag as frame counting sees it, so the counting can also run
+/// against a simulated flag.
+pub trait VsyncFlag {
+    fn is_pending(&mut self) -> bool;
+    fn clear(&mut self);
+}
+
+/// The LCD_CAM's VSYNC flag, see [is_pending] and [clear].
+#[cfg(target_arch = "xtensa")]
+pub struct LcdVsync;
+
+#[cfg(target_arch = "xtensa")]
+impl VsyncFlag for LcdVsync {
+    fn is_pending(&mut self) -> bool {
+        is_pending()
+    }
+
+    fn clear(&mut self) {
+        clear()
+    }
+}
+
+/// Blocks until `flag` has been set `n` times, clearing it after each.
+///
+/// A flag already pending counts as the first frame, [VsyncFlag::clear] it
+/// beforehand to count only frames from now on.
+pub fn wait_frames(flag: &mut impl VsyncFlag, n: u32) {
+    for _ in 0..n {
+        while !flag.is_pending() {}
+        flag.clear();
+    }
+}
+
+/// Blocks until the next VSYNC and clears the flag.
+#[cfg(target_arch = "xtensa")]
+pub fn wait() {
+    wait_frames(&mut LcdVsync, 1);
+}
+
+/// [wait] with a deadline, so a stalled transfer can't hang the caller.
+#[cfg(target_arch = "xtensa")]
+pub fn wait_timeout(timeout: Duration) -> Result<(), Timeout> {
+    poll_until(is_pending, timeout)?;
+    clear();
+    Ok(())
+}
+
+/// Busy-polls `done` until it returns `true`, or fails once `timeout` has
+/// passed. The shared deadline of the waits here and in
+/// [psram](crate::psram).
+pub fn poll_until(done: impl FnMut() -> bool, timeout: Duration) -> Result<(), Timeout> {
+    poll_until_on(done, timeout, Instant::now)
+}
+
+/// [poll_until] on the clock `now`.
+pub(crate) fn poll_until_on(
+    mut done: impl FnMut() -> bool,
+    timeout: Duration,
+    mut now: impl FnMut() -> Instant,
+) -> Result<(), Timeout> {
+    let start = now();
+    while !done() {
+        if now() - start > timeout {
+            return Err(Timeout);
+        }
+    }
+    Ok(())
+}
+
+/// How long one frame of `timing` takes at `pclk`, blanking included.
+///
+/// Computed in 64 bits, so it holds from 80MHz down to pclks of a few kHz.
+/// At 2MHz and 500x493 a frame takes 123ms, well past what's usually
+/// hardcoded as "a frame".
+pub fn frame_period(timing: &FrameTiming, pclk: Rate) -> Duration {
+    let pixels = (timing.horizontal_total_width * timing.vertical_total_height) as u64;
+    Duration::from_micros(pixels * 1_000_000 / pclk.as_hz().max(1) as u64)
+}
+
+/// A timeout for [wait_timeout] and other per-frame waits: three
+/// [frame_period]s.
+///
+/// One period would fire on ordinary jitter, and a wait that's started just
+/// after a VSYNC legitimately takes almost two. Past three something has
+/// stopped, at 12MHz and 500x493 that's about 62ms.
+pub fn frame_timeout(timing: &FrameTiming, pclk: Rate) -> Duration {
+    Duration::from_micros(3 * frame_period(timing, pclk).as_micros())
+}
+
+/// Measures the actual refresh rate of the running DPI transfer in Hz, over
+/// `frames` frames.
+///
+/// The window starts on a VSYNC, so the result doesn't depend on where in
+/// the frame it's called, but it blocks for up to `frames + 1` frames. A few
+/// frames give a quick answer; more average out timer resolution and
+/// scheduling jitter, at 60 Hz each doubling of `frames` costs another
+/// `frames / 60` seconds.
+#[cfg(target_arch = "xtensa")]
+pub fn measure_refresh(frames: u32) -> f32 {
+    clear();
+    wait();
+
+    let start = Instant::now();
+    for _ in 0..frames {
+        wait();
+    }
+    let micros = start.elapsed().as_micros();
+
+    frames as f32 * 1_000_000.0 / micros as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boards::MIAO_480X480;
+
+    /// Sets the flag every `period` polls, like a VSYNC every few hundred
+    /// microseconds of busy polling.
+    struct SimulatedVsync {
+        period: u32,
+        polls: u32,
+        frames: u32,
+        pending: bool,
+    }
+
+    impl SimulatedVsync {
+        fn new(period: u32) -> Self {
+            Self {
+                period,
+                polls: 0,
+                frames: 0,
+                pending: false,
+            }
+        }
+    }
+
+    impl VsyncFlag for SimulatedVsync {
+        fn is_pending(&mut self) -> bool {
+            self.polls += 1;
+            if self.polls % self.period == 0 {
+                self.frames += 1;
+                self.pending = true;
+            }
+            self.pending
+        }
+
+        fn clear(&mut self) {
+            self.pending = false;
+        }
+    }
+
+    /// A clock that moves 1ms on every read.
+    fn ticking_clock() -> impl FnMut() -> Instant {
+        let mut now = Instant::EPOCH;
+        move || {
+            now += Duration::from_millis(1);
+            now
+        }
+    }
+
+    #[test]
+    fn poll_until_times_out_on_a_transfer_that_never_completes() {
+        let mut polls = 0;
+        let never = || {
+            polls += 1;
+            false
+        };
+
+        let result = poll_until_on(never, Duration::from_millis(50), ticking_clock());
+        assert_eq!(result, Err(Timeout));
+        // One poll per clock read after the start, 51 of them to pass 50ms.
+        assert_eq!(polls, 51);
+    }
+
+    #[test]
+    fn poll_until_returns_once_done() {
+        let mut polls = 0;
+        let third = || {
+            polls += 1;
+            polls == 3
+        };
+
+        assert_eq!(
+            poll_until_on(third, Duration::from_millis(50), ticking_clock()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn wait_frames_returns_on_the_nth_vsync() {
+        for (period, n) in [(1, 1), (3, 5), (7, 60)] {
+            let mut vsync = SimulatedVsync::new(period);
+            wait_frames(&mut vsync, n);
+            assert_eq!(vsync.frames, n, "period {period}");
+            assert!(!vsync.pending);
+        }
+    }
+
+    #[test]
+    fn wait_frames_counts_only_after_clear() {
+        let mut vsync = SimulatedVsync::new(3);
+        vsync.pending = true;
+        vsync.clear();
+
+        wait_frames(&mut vsync, 2);
+        assert_eq!(vsync.frames, 2);
+    }
+
+    #[test]
+    fn frame_period_holds_from_1mhz_to_80mhz() {
+        let timing = |h, v| FrameTiming {
+            horizontal_total_width: h,
+            vertical_total_height: v,
+            ..Default::default()
+        };
+        let mhz = Rate::from_mhz;
+
+        for (timing, pclk, micros) in [
+            // The MRE's 500x493.
+            (timing(500, 493), mhz(1), 246_500),
+            (timing(500, 493), mhz(80), 3_081),
+            // The largest totals the timing registers take.
+            (timing(4096, 1024), mhz(1), 4_194_304),
+            (timing(4096, 1024), mhz(80), 52_428),
+        ] {
+            assert_eq!(frame_period(&timing, pclk).as_micros(), micros, "{pclk:?}");
+            assert_eq!(
+                frame_timeout(&timing, pclk).as_micros(),
+                3 * micros,
+                "{pclk:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn debug_slow_mode_stretches_the_frame() {
+        let panel = MIAO_480X480.panel.debug_slow_mode();
+        let pclk = Rate::from_mhz(panel.pclk_mhz);
+
+        assert_eq!(pclk, Rate::from_mhz(2));
+        assert_eq!(frame_period(&panel.timing, pclk).as_micros(), 123_250);
+    }
+}
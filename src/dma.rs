@@ -1,12 +1,38 @@
 // Copied from https://github.com/Dominaezzz/esp-hal/commit/7ff621e68892c86821b45ec1a5408dd47f2e610c
 // Reference: https://github.com/esp-rs/esp-hal/discussions/2866
 //
-use core::{cmp::min, ops::Range, ptr::null_mut};
+use core::{cmp::min, fmt::Write, ops::Range, ptr::null_mut};
 
-use esp_hal::dma::{
-    BurstConfig, DmaBufError, DmaDescriptor, DmaTxBuffer, Owner, Preparation, TransferDirection,
+use crate::{
+    bmp::{Bmp565, BmpError},
+    color::{
+        LogicalPixels, LogicalPixelsMut, PixelEndian, WireBytes, WireBytesMut, WireFormat,
+        convert_rgb888_to_565, from_wire, identity_pixel, swap_rb, to_wire, wire_format,
+    },
+    framebuffer::FrameBuffer,
+    geometry::FrameGeometry,
+    hal::{
+        Blocking, DriverMode,
+        dma::{
+            BurstConfig, DmaAlignmentError, DmaBufError, DmaDescriptor, DmaError, DmaTransferRx,
+            DmaTxBuffer, Mem2Mem, Owner, Preparation, TransferDirection,
+        },
+        gpio::{Level, Output, OutputPin},
+        lcd_cam::lcd::dpi::{Dpi, DpiTransfer},
+        time::{Duration, Instant},
+    },
+    looped::LoopedFrame,
+    stats::{PushStats, ThroughputBenchmark},
 };
 
+/// `defmt::trace!` when the `defmt` feature is enabled, nothing otherwise.
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "defmt")]
+        defmt::trace!($($arg)*);
+    };
+}
+
 /// The lower bound of the system's DRAM (Data RAM) address space.
 const SOC_DRAM_LOW: usize = 0x3FC8_8000;
 /// The upper bound of the system's DRAM (Data RAM) address space.
@@ -14,11 +40,102 @@ const SOC_DRAM_HIGH: usize = 0x3FD0_0000;
 
 const DRAM: Range<usize> = SOC_DRAM_LOW..SOC_DRAM_HIGH;
 
+/// The lower bound of the external memory (PSRAM) data address space.
+const SOC_EXTRAM_DATA_LOW: usize = 0x3C00_0000;
+/// The upper bound of the external memory (PSRAM) data address space.
+const SOC_EXTRAM_DATA_HIGH: usize = 0x3E00_0000;
+
+const EXTRAM: Range<usize> = SOC_EXTRAM_DATA_LOW..SOC_EXTRAM_DATA_HIGH;
+
+/// Most bytes one descriptor covers, what
+/// `BurstConfig::default().max_compatible_chunk_size()` returns, as a
+/// constant for const contexts.
+///
+/// The HAL computes it as `4096 - alignment`, and on the ESP32-S3, which can
+/// DMA from PSRAM, the alignment includes the default 16-byte external
+/// memory burst, so it's 4080 rather than the 4092 of chips without PSRAM
+/// DMA.
+pub const MAX_CHUNK_SIZE: usize = 4096 - 16;
+
+/// Descriptors a [DmaTxStreamBuf] needs so that a `buffer_len` byte ring is
+/// usable in full, with pushes of `push_len` bytes each. Every push takes at
+/// least one descriptor, and one per [MAX_CHUNK_SIZE] bytes. See
+/// [static_assert_dma_sizing](crate::static_assert_dma_sizing) to check a pair
+/// of statics at build time.
+pub const fn required_descriptors(buffer_len: usize, push_len: usize) -> usize {
+    let chunk = if push_len < MAX_CHUNK_SIZE {
+        push_len
+    } else {
+        MAX_CHUNK_SIZE
+    };
+    let needed = buffer_len.div_ceil(if chunk == 0 { 1 } else { chunk });
+    if needed < 2 { 2 } else { needed }
+}
+
+/// Fails the build if `descriptors` are too few for a `buffer_len` byte
+/// ring, see [required_descriptors]. `push_len` is the size of a typical
+/// push, a full chunk if left out.
+///
+/// ```ignore
+/// static DESCRIPTORS: ConstStaticCell<[DmaDescriptor; 100]> = ...;
+/// static BUFFER: ConstStaticCell<[u8; 100_000]> = ...;
+/// static_assert_dma_sizing!(100_000, 100);
+/// // One 960 byte line per push needs 105.
+/// static_assert_dma_sizing!(100_000, 100, 960);
+/// ```
+#[macro_export]
+macro_rules! static_assert_dma_sizing {
+    ($buffer_len:expr, $descriptors:expr) => {
+        $crate::static_assert_dma_sizing!($buffer_len, $descriptors, $crate::dma::MAX_CHUNK_SIZE);
+    };
+    ($buffer_len:expr, $descriptors:expr, $push_len:expr) => {
+        const _: () = assert!(
+            $descriptors >= $crate::dma::required_descriptors($buffer_len, $push_len),
+            "too few DMA descriptors for the buffer, see dma::required_descriptors"
+        );
+    };
+}
+
 #[allow(unused)]
 pub(crate) fn is_slice_in_dram<T>(slice: &[T]) -> bool {
     slice_in_range(slice, DRAM)
 }
 
+/// The MMU table, one entry per 64KB page of [EXTRAM] in order. The same
+/// window holds flash (rodata) and PSRAM, only the entries tell them apart.
+const DR_REG_MMU_TABLE: usize = 0x600C_5000;
+const MMU_PAGE_SIZE: usize = 0x1_0000;
+const MMU_INVALID: u32 = 1 << 14;
+const MMU_ACCESS_SPIRAM: u32 = 1 << 15;
+
+pub(crate) fn is_slice_in_psram<T>(slice: &[T]) -> bool {
+    if !slice_in_range(slice, EXTRAM) {
+        return false;
+    }
+
+    let range = slice.as_ptr_range();
+    let first = (range.start as usize - SOC_EXTRAM_DATA_LOW) / MMU_PAGE_SIZE;
+    let last = (range.end as usize - SOC_EXTRAM_DATA_LOW).saturating_sub(1) / MMU_PAGE_SIZE;
+    (first..=last.max(first)).all(|page| {
+        let entry = unsafe { (DR_REG_MMU_TABLE as *const u32).add(page).read_volatile() };
+        entry & (MMU_INVALID | MMU_ACCESS_SPIRAM) == MMU_ACCESS_SPIRAM
+    })
+}
+
+/// Whether the GDMA can read `slice`: all of it in internal DRAM
+/// (`0x3FC8_8000..0x3FD0_0000`) or in PSRAM.
+///
+/// PSRAM and flash are mapped into the same data window,
+/// `0x3C00_0000..0x3E00_0000`, so the MMU entry of every 64KB page the
+/// slice touches has to point at PSRAM. Anything else, flash-mapped
+/// `static` data or the instruction bus alias of internal RAM, reads as
+/// garbage without any error from the DMA.
+/// [DmaTxStreamBuf] itself needs DRAM, PSRAM goes through
+/// [PsramDoubleBuffer](crate::psram::PsramDoubleBuffer).
+pub fn is_dma_capable<T>(slice: &[T]) -> bool {
+    is_slice_in_dram(slice) || is_slice_in_psram(slice)
+}
+
 fn slice_in_range<T>(slice: &[T], range: Range<usize>) -> bool {
     let slice = slice.as_ptr_range();
     let start = slice.start as usize;
@@ -29,6 +146,21 @@ fn slice_in_range<T>(slice: &[T], range: Range<usize>) -> bool {
     range.contains(&start) && end <= range.end
 }
 
+/// Buffer start alignment that DMA transfers perform best with, one cache
+/// line. Wrap static buffers in a `#[repr(align(32))]` struct to get it.
+pub const RECOMMENDED_ALIGNMENT: usize = 32;
+
+fn swap_rb_in_place(bytes: &mut [u8], endian: PixelEndian) {
+    for pixel in bytes.chunks_exact_mut(2) {
+        let bytes = [pixel[0], pixel[1]];
+        let swapped = match endian {
+            PixelEndian::Little => swap_rb(u16::from_le_bytes(bytes)).to_le_bytes(),
+            PixelEndian::Big => swap_rb(u16::from_be_bytes(bytes)).to_be_bytes(),
+        };
+        pixel.copy_from_slice(&swapped);
+    }
+}
+
 /// DMA Streaming Transmit Buffer
 pub struct DmaTxStreamBuf {
     descriptors: &'static mut [DmaDescriptor],
@@ -37,10 +169,52 @@ pub struct DmaTxStreamBuf {
     num_used_descriptors: usize,
     len_of_used_buffer: usize,
     buffer_write_offset: usize,
+
+    // Tail of a chain absorbed by `absorb_descriptor_chain` that the next
+    // descriptor has to be linked after, or null.
+    external_tail: *mut DmaDescriptor,
+
+    // Set when the buf was recovered from a stopped transfer with a complete
+    // frame still in the ring.
+    resume: Option<ResumePoint>,
+
+    benchmark: Option<ThroughputBenchmark>,
+
+    rb_swap: Option<PixelEndian>,
+    on_underrun: UnderrunPolicy,
+    debug_pin: Option<Output<'static>>,
+    hang_timeout: Duration,
+
+    // Set by `enable_loop` and `hold_resumed_frame`: the descriptor that
+    // links back to the start of the looped frame.
+    loop_tail: Option<usize>,
+}
+
+/// Where a recovered [DmaTxStreamBuf] picks the stream back up.
+struct ResumePoint {
+    first_descriptor: usize,
+    descriptor_idx: usize,
+    buffer_idx: usize,
+    free_descriptors: usize,
+    free_buffer_space: usize,
 }
 
 impl DmaTxStreamBuf {
     /// Creates a new [DmaTxStreamBuf].
+    ///
+    /// Both `descriptors` and `buffer` have to be in internal DRAM, otherwise
+    /// this fails with [DmaBufError::UnsupportedMemoryRegion] rather than
+    /// leaving the transfer to stream garbage. See [is_dma_capable].
+    ///
+    /// Each descriptor covers at most [MAX_CHUNK_SIZE] bytes, and once
+    /// streaming every push takes at least one of its own. So the ring holds
+    /// `min(buffer.len(), descriptors.len() * min(push size, MAX_CHUNK_SIZE))`
+    /// bytes: with 960 byte line pushes, 100 descriptors only ever use 96000
+    /// bytes of the buffer. Too few descriptors make the ring shallower than
+    /// it looks and underruns come sooner, see [UnderrunPolicy]. A warning is
+    /// logged if even full chunks can't cover `buffer`; check statics at
+    /// build time with
+    /// [static_assert_dma_sizing](crate::static_assert_dma_sizing) instead.
     pub fn new(
         descriptors: &'static mut [DmaDescriptor],
         buffer: &'static mut [u8],
@@ -56,17 +230,154 @@ impl DmaTxStreamBuf {
             return Err(DmaBufError::InsufficientDescriptors);
         }
 
+        let max_chunk_size = BurstConfig::default().max_compatible_chunk_size();
+        debug_assert_eq!(max_chunk_size, MAX_CHUNK_SIZE);
+        if descriptors.len() * max_chunk_size < buffer.len() {
+            warn!(
+                "{} descriptors cover only {} of {} buffer bytes",
+                descriptors.len(),
+                descriptors.len() * max_chunk_size,
+                buffer.len()
+            );
+        }
+
+        if buffer.as_ptr() as usize % RECOMMENDED_ALIGNMENT != 0 {
+            warn!(
+                "DMA buffer at {:p} is not {}-byte aligned, transfers will be slower",
+                buffer.as_ptr(),
+                RECOMMENDED_ALIGNMENT
+            );
+        }
+
+        Ok(Self::new_unchecked(descriptors, buffer))
+    }
+
+    /// [Self::new] without checking where `descriptors` and `buffer` are,
+    /// for host tests.
+    pub(crate) fn new_unchecked(
+        descriptors: &'static mut [DmaDescriptor],
+        buffer: &'static mut [u8],
+    ) -> Self {
         // The buffer starts out with one empty.
         descriptors.fill(DmaDescriptor::EMPTY);
         descriptors[0].set_owner(Owner::Dma);
 
-        Ok(Self {
+        Self {
             descriptors,
             buffer,
             num_used_descriptors: 0,
             len_of_used_buffer: 0,
             buffer_write_offset: 0,
-        })
+            external_tail: null_mut(),
+            resume: None,
+            benchmark: None,
+            rb_swap: None,
+            on_underrun: UnderrunPolicy::Stop,
+            debug_pin: None,
+            hang_timeout: DEFAULT_HANG_TIMEOUT,
+            loop_tail: None,
+        }
+    }
+
+    /// Creates a [DmaTxStreamBuf] for streaming frames of `geometry`.
+    ///
+    /// Fails with [DmaBufError::BufferTooSmall] if `buffer` can't hold a
+    /// single line, and warns if it doesn't hold a whole number of them, as
+    /// lines then straddle the end of the ring.
+    pub fn with_geometry(
+        descriptors: &'static mut [DmaDescriptor],
+        buffer: &'static mut [u8],
+        geometry: &FrameGeometry,
+    ) -> Result<Self, DmaBufError> {
+        let line = geometry.bytes_per_line();
+        if buffer.len() < line {
+            return Err(DmaBufError::BufferTooSmall);
+        }
+        if buffer.len() % line != 0 {
+            warn!(
+                "DMA buffer of {} bytes is not a multiple of the {}-byte line",
+                buffer.len(),
+                line
+            );
+        }
+
+        Self::new(descriptors, buffer)
+    }
+
+    /// Whether the buffer starts on a [RECOMMENDED_ALIGNMENT] boundary.
+    pub fn check_alignment(&self) -> bool {
+        self.buffer.as_ptr() as usize % RECOMMENDED_ALIGNMENT == 0
+    }
+
+    /// Swaps the red and blue channels of every pushed pixel, for panels
+    /// wired with R and B crossed. Pixels are taken in the byte order of the
+    /// current [WireFormat], see [WireFormat::endianness]; use
+    /// [Self::set_rb_swap] for data in another order.
+    ///
+    /// Carries over into the transfer's [DmaTxStreamBufView]. Pushes have to
+    /// be whole pixels, a pixel split across two pushes isn't swapped.
+    /// Only CPU pushes are swapped; [Self::reserve_scanlines] and
+    /// [DmaTxStreamBufView::push_dma] write the ring as is.
+    pub fn enable_rb_swap(&mut self, enable: bool) {
+        self.set_rb_swap(enable.then(|| wire_format().endianness()));
+    }
+
+    /// Swaps red and blue of pushed pixels stored with `endian` byte order,
+    /// or stops swapping with `None`. See [Self::enable_rb_swap].
+    pub fn set_rb_swap(&mut self, swap: Option<PixelEndian>) {
+        self.rb_swap = swap;
+    }
+
+    /// Pulses `pin` high for every descriptor the transfer's view fills,
+    /// from before the copy until it's linked.
+    ///
+    /// On a logic analyzer the pulse spacing is the CPU side fill rate and
+    /// the pulse width the cost of one chunk, to compare with how fast the
+    /// DMA drains the ring. Pushes before `Dpi::send` only copy and don't
+    /// pulse, they get descriptors all at once when the transfer starts. A
+    /// pulse costs two GPIO writes.
+    pub fn with_debug_pin(mut self, pin: impl OutputPin + 'static) -> Self {
+        self.debug_pin = Some(Output::new(pin, Level::Low, Default::default()));
+        self
+    }
+
+    /// Sets how long the DMA may make no progress on queued data before
+    /// [DmaTxStreamBufView::watchdog_tick] reports a hang, 50ms by default.
+    /// Carries over into the transfer.
+    pub fn set_hang_timeout(&mut self, timeout: Duration) {
+        self.hang_timeout = timeout;
+    }
+
+    /// Sets what happens when the transfer's view is pushed to too slowly
+    /// and the DMA reaches the last linked descriptor. Carries over into the
+    /// transfer.
+    pub fn set_underrun_policy(&mut self, policy: UnderrunPolicy) {
+        self.on_underrun = policy;
+    }
+
+    /// Whether the next transfer replays a frame left over from the previous
+    /// one, instead of starting from freshly pushed data.
+    pub fn is_resumed(&self) -> bool {
+        self.resume.is_some()
+    }
+
+    /// Starts counting pushed bytes, replacing any running benchmark.
+    ///
+    /// The count carries over into the transfer's [DmaTxStreamBufView], so a
+    /// benchmark started here measures the pre-fill and streaming together.
+    pub fn start_throughput_benchmark(&mut self) -> ThroughputBenchmark {
+        let bench = ThroughputBenchmark::new();
+        self.benchmark = Some(bench);
+        bench
+    }
+
+    /// Stops the benchmark started by [Self::start_throughput_benchmark] and
+    /// returns the push rate in MB/s.
+    pub fn end_throughput_benchmark(&mut self, bench: ThroughputBenchmark) -> f32 {
+        self.benchmark
+            .take()
+            .unwrap_or(bench)
+            .megabytes_per_second()
     }
 
     /// Consume the buf, returning the descriptors and buffer.
@@ -74,12 +385,92 @@ impl DmaTxStreamBuf {
         (self.descriptors, self.buffer)
     }
 
-    /// TODO
-    pub fn push(&mut self, data: &[u8]) -> usize {
-        if data.is_empty() {
-            return 0;
+    /// Turns everything pushed so far into a [LoopedFrame] whose last
+    /// descriptor links back to the first, so the DMA re-streams it forever
+    /// with no CPU involvement and no push loop to keep fed.
+    ///
+    /// The pushed data has to be exactly one full frame, since the DMA wraps
+    /// straight from its end to its start. Send the result with `repeat`
+    /// set and stop the ring with `DpiTransfer::stop`. The buffer stays
+    /// borrowed by the frame for good; [LoopedFrame::split] only returns the
+    /// descriptors.
+    pub fn into_circular(self) -> Result<LoopedFrame, DmaBufError> {
+        let len = self.len_of_used_buffer + self.buffer_write_offset;
+        let buffer: &'static [u8] = self.buffer;
+        LoopedFrame::from_parts(self.descriptors, &buffer[..len], false)
+    }
+
+    /// Links the last descriptor back to the first, so the DMA replays
+    /// everything pushed so far indefinitely without CPU involvement, e.g.
+    /// for a static splash screen.
+    ///
+    /// Pending data is committed first, with EOF at its end, so the pushed
+    /// data should be exactly one frame. `push_wire` must not be called in loop
+    /// mode, neither here nor on the transfer's view, as the ring is never
+    /// handed back. esp-hal's `repeat` flag on `Dpi::send` only keeps the LCD
+    /// running past EOF and can't reach the descriptors, so set this before
+    /// sending instead. See [Self::into_circular] for a version that gives up
+    /// the stream buf entirely.
+    ///
+    /// On a buf recovered with a frame to replay this is
+    /// [Self::hold_resumed_frame].
+    pub fn enable_loop(&mut self) {
+        if self.resume.is_some() {
+            self.hold_resumed_frame();
+            return;
+        }
+
+        self.commit(true);
+        if self.num_used_descriptors == 0 {
+            return;
         }
 
+        let tail = self.num_used_descriptors - 1;
+        let head: *mut _ = &mut self.descriptors[0];
+        let last = &mut self.descriptors[tail];
+        last.next = head;
+        last.set_suc_eof(true);
+        self.loop_tail = Some(tail);
+    }
+
+    /// Like [Self::enable_loop] for a buf recovered with a frame to replay,
+    /// see [Self::is_resumed]: the replayed frame's last descriptor links
+    /// back to its first, so the next transfer shows it forever. Returns
+    /// `false` and changes nothing if there's no such frame.
+    ///
+    /// The loop survives recovering the buf from that transfer, end it with
+    /// [Self::disable_loop] to replay the frame once more and then stream.
+    pub fn hold_resumed_frame(&mut self) -> bool {
+        let Some(resume) = &self.resume else {
+            return false;
+        };
+
+        let len = self.descriptors.len();
+        let first = resume.first_descriptor;
+        let last = (resume.descriptor_idx + len - 1) % len;
+
+        let head: *mut _ = &mut self.descriptors[first];
+        self.descriptors[last].next = head;
+        self.loop_tail = Some(last);
+        true
+    }
+
+    /// Undoes [Self::enable_loop] and [Self::hold_resumed_frame]: the looped
+    /// frame's last descriptor gets EOF and no successor, so a running DMA
+    /// stops after the current frame.
+    pub fn disable_loop(&mut self) {
+        let Some(tail) = self.loop_tail.take() else {
+            return;
+        };
+
+        let last = &mut self.descriptors[tail];
+        last.next = null_mut();
+        last.set_suc_eof(true);
+    }
+
+    /// Number of bytes that can still be pushed before the next commit runs
+    /// out of buffer space or descriptors.
+    pub fn available_bytes(&self) -> usize {
         let max_chunk_size = BurstConfig::default().max_compatible_chunk_size();
 
         let num_free_descriptors = self.descriptors.len() - self.num_used_descriptors;
@@ -88,7 +479,24 @@ impl DmaTxStreamBuf {
             max_chunk_size * num_free_descriptors,
             self.buffer.len() - self.len_of_used_buffer,
         );
-        let available_space = push_limit.saturating_sub(self.buffer_write_offset);
+        push_limit.saturating_sub(self.buffer_write_offset)
+    }
+
+    /// Use [Self::push_wire], which spells out that `data` is in wire order.
+    #[deprecated(note = "use `push_wire`")]
+    pub fn push(&mut self, data: &[u8]) -> usize {
+        self.push_wire(WireBytes(data))
+    }
+
+    /// Copies as much of `data` as fits into the buffer. Returns the bytes
+    /// copied.
+    pub fn push_wire(&mut self, WireBytes(data): WireBytes<'_>) -> usize {
+        debug_assert!(self.loop_tail.is_none(), "push in loop mode");
+        if data.is_empty() {
+            return 0;
+        }
+
+        let available_space = self.available_bytes();
 
         let available_buffer = &mut self.buffer[self.len_of_used_buffer..]
             [self.buffer_write_offset..][..available_space];
@@ -96,11 +504,316 @@ impl DmaTxStreamBuf {
         let bytes_to_push = min(data.len(), available_buffer.len());
 
         available_buffer[..bytes_to_push].copy_from_slice(&data[..bytes_to_push]);
+        if let Some(endian) = self.rb_swap {
+            swap_rb_in_place(&mut available_buffer[..bytes_to_push], endian);
+        }
         self.buffer_write_offset += bytes_to_push;
+        if let Some(bench) = &mut self.benchmark {
+            bench.record(bytes_to_push);
+        }
+
+        if bytes_to_push == 0 {
+            trace!("push stalled: {}", self);
+        }
 
         bytes_to_push
     }
 
+    /// Pushes `regions` back to back, as if they were one slice, without
+    /// concatenating them first. Returns the bytes pushed, which falls short
+    /// of the total once the buffer is full.
+    pub fn push_gather(&mut self, regions: &[&[u8]]) -> usize {
+        let mut pushed = 0;
+        for region in regions {
+            let n = self.push_wire(WireBytes(region));
+            pushed += n;
+            if n < region.len() {
+                break;
+            }
+        }
+        pushed
+    }
+
+    /// Pushes the pixels of a 16-bit BMP file, top row first, converted from
+    /// the file's little-endian pixels to the current [wire_format].
+    ///
+    /// Returns the number of pixels pushed, which is short of the whole image
+    /// if the buffer fills up.
+    pub fn push_bmp_pixels(&mut self, bmp_data: &[u8]) -> Result<usize, BmpError> {
+        let bmp = Bmp565::parse(bmp_data)?;
+        let wire = wire_format();
+
+        let mut pixels = [0; 128];
+        let mut bytes = [0; 256];
+        let chunk_len = bytes.len();
+        let mut pushed = 0;
+        for chunk in bmp.rows().flat_map(|row| row.chunks(chunk_len)) {
+            let n = from_wire(
+                WireBytes(chunk),
+                LogicalPixelsMut(&mut pixels),
+                WireFormat::DEFAULT,
+            );
+            to_wire(LogicalPixels(&pixels[..n]), WireBytesMut(&mut bytes), wire);
+
+            let written = self.push_wire(WireBytes(&bytes[..n * 2]));
+            pushed += written / 2;
+            if written < n * 2 {
+                break;
+            }
+        }
+
+        Ok(pushed)
+    }
+
+    /// Pushes the 256 pixel byte identity pattern, see [identity_pixel],
+    /// checks that the ring holds exactly the bytes the current
+    /// [wire_format] predicts for it, red and blue swapped if
+    /// [Self::set_rb_swap] is on, and takes the pattern back out.
+    ///
+    /// That covers everything on the push side, [to_wire], the red/blue
+    /// swap and the copy into the ring. What the LCD_CAM does to the bytes
+    /// on their way to the pins can't be read back on the ESP32-S3; check
+    /// that once per board with a logic analyzer on the data pins while the
+    /// pattern streams.
+    pub fn check_wire_format(&mut self) -> Result<(), WireCheckError> {
+        const PIXELS: usize = 256;
+
+        if self.available_bytes() < PIXELS * 2 {
+            return Err(WireCheckError::BufferTooSmall);
+        }
+
+        let wire = wire_format();
+        let pattern: [u16; PIXELS] = core::array::from_fn(|i| identity_pixel(i as u8));
+        let mut bytes = [0; PIXELS * 2];
+        to_wire(LogicalPixels(&pattern), WireBytesMut(&mut bytes), wire);
+
+        let mut expected = bytes;
+        if let Some(endian) = self.rb_swap {
+            swap_rb_in_place(&mut expected, endian);
+        }
+
+        let start = self.buffer_write_offset;
+        self.push_wire(WireBytes(&bytes));
+        let ring = &self.buffer[self.len_of_used_buffer..][start..][..PIXELS * 2];
+        let mismatch = ring
+            .chunks_exact(2)
+            .zip(expected.chunks_exact(2))
+            .enumerate()
+            .find(|(_, (found, expected))| found != expected)
+            .map(|(pixel, (found, expected))| WireCheckError::Mismatch {
+                pixel,
+                expected: [expected[0], expected[1]],
+                found: [found[0], found[1]],
+            });
+        self.buffer_write_offset = start;
+
+        mismatch.map_or(Ok(()), Err)
+    }
+
+    /// One character per descriptor showing who owns it, see [OwnershipMap].
+    pub fn ownership_map(&self) -> OwnershipMap<'_> {
+        let max_chunk_size = BurstConfig::default().max_compatible_chunk_size();
+        OwnershipMap {
+            descriptors: self.descriptors,
+            pending: self.num_used_descriptors
+                ..self.num_used_descriptors + self.buffer_write_offset.div_ceil(max_chunk_size),
+        }
+    }
+
+    /// Writes the data of descriptor `index` to `f` as an `xxd`-style hex
+    /// dump, 16 bytes per row, under a header with its owner and byte range
+    /// in the ring buffer:
+    ///
+    /// ```text
+    /// DESC[0] (DMA) [0x0000..0x0FFC]:
+    ///   0x0000: 00 f8 00 f8 00 f8 00 f8 00 f8 00 f8 00 f8 00 f8  ................
+    /// ```
+    ///
+    /// Descriptors of an absorbed chain show their absolute address instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of range.
+    pub fn hexdump_descriptor(&self, index: usize, f: &mut impl Write) -> core::fmt::Result {
+        let desc = &self.descriptors[index];
+        let owner = match desc.owner() {
+            Owner::Dma => "DMA",
+            Owner::Cpu => "CPU",
+        };
+
+        let data: &[u8] = if desc.buffer.is_null() {
+            &[]
+        } else {
+            unsafe { core::slice::from_raw_parts(desc.buffer, desc.len()) }
+        };
+        let ring = self.buffer.as_ptr_range();
+        let ptr = desc.buffer as *const u8;
+        let start = if ring.contains(&ptr) {
+            ptr as usize - ring.start as usize
+        } else {
+            ptr as usize
+        };
+        writeln!(
+            f,
+            "DESC[{}] ({}) [0x{:04X}..0x{:04X}]:",
+            index,
+            owner,
+            start,
+            start + data.len()
+        )?;
+
+        for (row, bytes) in data.chunks(16).enumerate() {
+            write!(f, "  0x{:04X}:", row * 16)?;
+            for i in 0..16 {
+                match bytes.get(i) {
+                    Some(byte) => write!(f, " {byte:02x}")?,
+                    None => f.write_str("   ")?,
+                }
+            }
+            f.write_str("  ")?;
+            for &byte in bytes {
+                let c = if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                };
+                f.write_char(c)?;
+            }
+            f.write_char('\n')?;
+        }
+
+        Ok(())
+    }
+
+    /// [Self::hexdump_descriptor] for every descriptor in the ring.
+    pub fn hexdump_all(&self, f: &mut impl Write) -> core::fmt::Result {
+        for index in 0..self.descriptors.len() {
+            self.hexdump_descriptor(index, f)?;
+        }
+        Ok(())
+    }
+
+    /// Reserves space for `n` scanlines of `bytes_per_scanline` bytes each.
+    ///
+    /// Returns `None` if [Self::available_bytes] can't fit all of them. The
+    /// reserved space is not handed to the DMA until
+    /// [ScanlineReservation::commit] is called; dropping the reservation
+    /// releases it untouched.
+    pub fn reserve_scanlines(
+        &mut self,
+        n: usize,
+        bytes_per_scanline: usize,
+    ) -> Option<ScanlineReservation<'_>> {
+        let len = n.checked_mul(bytes_per_scanline)?;
+        if self.available_bytes() < len {
+            return None;
+        }
+
+        Some(ScanlineReservation {
+            buf: self,
+            rows: n,
+            bytes_per_scanline,
+        })
+    }
+
+    /// Appends an externally built descriptor chain to the stream without
+    /// copying its data.
+    ///
+    /// The ring's current tail is linked to `head`, and whatever is pushed
+    /// next is linked after the `n_descriptors`-th descriptor of the chain.
+    /// Returns [DmaAlignmentError::Address] if any descriptor's buffer isn't
+    /// 4-byte aligned, in which case nothing is linked.
+    ///
+    /// # Safety
+    ///
+    /// `head` must point to a chain of at least `n_descriptors` descriptors
+    /// linked through `next`, owned by the DMA, with buffers in DMA-capable
+    /// memory. The descriptors and their buffers must stay valid and
+    /// untouched until the transfer using this buf has finished.
+    pub unsafe fn absorb_descriptor_chain(
+        &mut self,
+        head: *mut DmaDescriptor,
+        n_descriptors: usize,
+    ) -> Result<(), DmaError> {
+        if n_descriptors == 0 {
+            return Ok(());
+        }
+
+        let mut tail = head;
+        for i in 0..n_descriptors {
+            let descriptor = unsafe { &*tail };
+            if descriptor.buffer as usize % 4 != 0 {
+                return Err(DmaError::InvalidAlignment(DmaAlignmentError::Address));
+            }
+            if i + 1 < n_descriptors {
+                tail = descriptor.next;
+            }
+        }
+
+        // Everything pushed so far goes before the chain.
+        self.commit(false);
+
+        if !self.external_tail.is_null() {
+            unsafe { (*self.external_tail).next = head };
+        } else {
+            // Keep the leading empty descriptor as the one to link from.
+            self.num_used_descriptors = self.num_used_descriptors.max(1);
+            self.descriptors[self.num_used_descriptors - 1].next = head;
+        }
+
+        unsafe { (*tail).next = null_mut() };
+        self.external_tail = tail;
+
+        Ok(())
+    }
+
+    /// Grows the ring by `new_descs` and `new_buf`, e.g. when the resolution
+    /// goes up after the buf was created.
+    ///
+    /// The ring is one slice of descriptors and one of bytes, so both have to
+    /// continue right where the current ones end, as when one `static` is
+    /// split with `split_at_mut` and the second half is handed over later;
+    /// anything else fails with [DmaBufError::UnsupportedMemoryRegion]. The
+    /// new descriptors are reset before the ring's length covers them, and
+    /// only pushes after this call use them.
+    ///
+    /// Only a buf the DMA isn't working through can grow. One recovered from
+    /// a stopped transfer still holds its ring positions, and a looped one is
+    /// closed at its current end, so both fail with
+    /// [GrowError::CannotGrowWhileActive]; a view can't grow at all.
+    pub fn append_descriptors(
+        &mut self,
+        new_descs: &'static mut [DmaDescriptor],
+        new_buf: &'static mut [u8],
+    ) -> Result<(), GrowError> {
+        if self.resume.is_some() || self.loop_tail.is_some() {
+            return Err(GrowError::CannotGrowWhileActive);
+        }
+
+        let adjacent = new_descs.as_ptr() == self.descriptors.as_ptr_range().end
+            && new_buf.as_ptr() == self.buffer.as_ptr_range().end;
+        if !adjacent || !is_slice_in_dram(new_descs) || !is_slice_in_dram(new_buf) {
+            return Err(GrowError::Buf(DmaBufError::UnsupportedMemoryRegion));
+        }
+
+        new_descs.fill(DmaDescriptor::EMPTY);
+
+        let descriptors = core::mem::take(&mut self.descriptors);
+        let buffer = core::mem::take(&mut self.buffer);
+        // SAFETY: Both pairs of slices are `'static`, exclusive and directly
+        // adjacent, so each pair is one valid slice.
+        unsafe {
+            self.descriptors = core::slice::from_raw_parts_mut(
+                descriptors.as_mut_ptr(),
+                descriptors.len() + new_descs.len(),
+            );
+            self.buffer =
+                core::slice::from_raw_parts_mut(buffer.as_mut_ptr(), buffer.len() + new_buf.len());
+        }
+
+        Ok(())
+    }
+
     fn commit(&mut self, with_eof: bool) {
         if self.buffer_write_offset == 0 {
             return;
@@ -119,6 +832,11 @@ impl DmaTxStreamBuf {
 
             let desc = free_descriptors.next().unwrap();
 
+            trace!(
+                "descriptor {} -> DMA, {} bytes",
+                self.num_used_descriptors + used_descriptors,
+                chunk.len()
+            );
             desc.set_owner(Owner::Dma);
             desc.set_length(chunk.len());
             desc.set_size(chunk.len());
@@ -131,12 +849,16 @@ impl DmaTxStreamBuf {
 
         let num_unused_descriptors = free_descriptors.count();
 
+        // After an absorbed chain, the chain's tail links to the new
+        // descriptors instead of the ring's previous one.
+        let link_previous = self.external_tail.is_null() as usize;
+
         let descriptors_to_link = self
             .descriptors
             .iter_mut()
             .rev()
             .skip(num_unused_descriptors)
-            .take(used_descriptors + 1);
+            .take(used_descriptors + link_previous);
 
         let mut next = null_mut();
         for desc in descriptors_to_link {
@@ -144,6 +866,13 @@ impl DmaTxStreamBuf {
             next = desc;
         }
 
+        if !self.external_tail.is_null() {
+            unsafe {
+                (*self.external_tail).next = &mut self.descriptors[self.num_used_descriptors]
+            };
+            self.external_tail = null_mut();
+        }
+
         self.num_used_descriptors += used_descriptors;
         self.len_of_used_buffer += self.buffer_write_offset;
         self.buffer_write_offset = 0;
@@ -152,23 +881,163 @@ impl DmaTxStreamBuf {
     }
 }
 
+#[cfg(feature = "defmt")]
+impl defmt::Format for DmaTxStreamBuf {
+    fn format(&self, fmt: defmt::Formatter<'_>) {
+        defmt::write!(
+            fmt,
+            "DmaTxStreamBuf {{ descriptors: {}/{}, buffer: {}/{}, pending: {}, resumed: {} }}",
+            self.num_used_descriptors,
+            self.descriptors.len(),
+            self.len_of_used_buffer,
+            self.buffer.len(),
+            self.buffer_write_offset,
+            self.resume.is_some(),
+        )
+    }
+}
+
+/// Descriptor ownership at a glance, from [DmaTxStreamBuf::ownership_map] or
+/// [DmaTxStreamBufView::ownership_map].
+///
+/// Displays as one character per descriptor, in ring order: `D` for owned by
+/// the DMA, `C` for pushed data the CPU hasn't handed over yet and `F` for
+/// free. While streaming, a healthy ring shows the boundary between the `D`
+/// and `F` regions rotating; a ring that stays all `D` is starving the
+/// renderer, one that stays mostly `F` is about to underrun.
+pub struct OwnershipMap<'a> {
+    descriptors: &'a [DmaDescriptor],
+    pending: Range<usize>,
+}
+
+impl OwnershipMap<'_> {
+    fn state(&self, i: usize) -> char {
+        if self.pending.contains(&i) {
+            'C'
+        } else if self.descriptors[i].owner() == Owner::Dma {
+            'D'
+        } else {
+            'F'
+        }
+    }
+
+    fn count(&self, state: char) -> usize {
+        (0..self.descriptors.len())
+            .filter(|&i| self.state(i) == state)
+            .count()
+    }
+
+    /// Descriptors owned by the DMA.
+    pub fn dma_count(&self) -> usize {
+        self.count('D')
+    }
+
+    /// Descriptors holding pushed data not yet handed to the DMA.
+    pub fn cpu_count(&self) -> usize {
+        self.count('C')
+    }
+
+    /// Free descriptors.
+    pub fn free_count(&self) -> usize {
+        self.count('F')
+    }
+}
+
+impl core::fmt::Display for OwnershipMap<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for i in 0..self.descriptors.len() {
+            f.write_char(self.state(i))?;
+        }
+        Ok(())
+    }
+}
+
+/// Space for a fixed number of scanlines reserved in a [DmaTxStreamBuf].
+pub struct ScanlineReservation<'a> {
+    buf: &'a mut DmaTxStreamBuf,
+    rows: usize,
+    bytes_per_scanline: usize,
+}
+
+impl ScanlineReservation<'_> {
+    /// Mutable access to the reserved bytes of scanline `row`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `row` is not less than the number of reserved scanlines.
+    pub fn scanline_mut(&mut self, row: usize) -> &mut [u8] {
+        assert!(row < self.rows, "scanline {row} is not reserved");
+
+        let start = self.buf.len_of_used_buffer
+            + self.buf.buffer_write_offset
+            + row * self.bytes_per_scanline;
+        &mut self.buf.buffer[start..][..self.bytes_per_scanline]
+    }
+
+    /// Hands all reserved scanlines to the DMA at once.
+    pub fn commit(self) {
+        self.buf.buffer_write_offset += self.rows * self.bytes_per_scanline;
+        self.buf.commit(false);
+    }
+}
+
 unsafe impl DmaTxBuffer for DmaTxStreamBuf {
     type View = DmaTxStreamBufView;
 
     fn prepare(&mut self) -> Preparation {
-        self.commit(false);
+        let start = match self.resume.as_ref().map(|resume| resume.first_descriptor) {
+            Some(first_descriptor) => &mut self.descriptors[first_descriptor] as *mut _,
+            None => {
+                self.commit(false);
+                if self.on_underrun == UnderrunPolicy::RepeatLast
+                    && self.loop_tail.is_none()
+                    && self.external_tail.is_null()
+                    && self.num_used_descriptors > 0
+                {
+                    let tail = &mut self.descriptors[self.num_used_descriptors - 1];
+                    tail.next = tail;
+                }
+                self.descriptors.as_mut_ptr()
+            }
+        };
 
         Preparation {
-            start: self.descriptors.as_mut_ptr(),
+            start,
             direction: TransferDirection::Out,
             accesses_psram: false,
-            check_owner: None,
+            // In loop mode the DMA comes back around to descriptors it has
+            // already handed back.
+            check_owner: if self.loop_tail.is_some() {
+                Some(false)
+            } else {
+                None
+            },
             burst_transfer: BurstConfig::default(),
             auto_write_back: true,
         }
     }
 
     fn into_view(self) -> Self::View {
+        if let Some(resume) = self.resume {
+            return DmaTxStreamBufView {
+                descriptors: self.descriptors,
+                buffer: self.buffer,
+                descriptor_idx: resume.descriptor_idx,
+                buffer_idx: resume.buffer_idx,
+                free_descriptors: resume.free_descriptors,
+                free_buffer_space: resume.free_buffer_space,
+                external_tail: null_mut(),
+                stats: PushStats::new(),
+                benchmark: self.benchmark,
+                rb_swap: self.rb_swap,
+                on_underrun: self.on_underrun,
+                underruns: 0,
+                debug_pin: self.debug_pin,
+                watchdog: Watchdog::new(self.hang_timeout),
+                loop_tail: self.loop_tail,
+            };
+        }
+
         DmaTxStreamBufView {
             buffer_idx: self.len_of_used_buffer % self.buffer.len(),
             free_descriptors: self.descriptors.len() - self.num_used_descriptors,
@@ -176,10 +1045,69 @@ unsafe impl DmaTxBuffer for DmaTxStreamBuf {
             descriptors: self.descriptors,
             buffer: self.buffer,
             descriptor_idx: self.num_used_descriptors,
+            external_tail: self.external_tail,
+            stats: PushStats::new(),
+            benchmark: self.benchmark,
+            rb_swap: self.rb_swap,
+            on_underrun: self.on_underrun,
+            underruns: 0,
+            debug_pin: self.debug_pin,
+            watchdog: Watchdog::new(self.hang_timeout),
+            loop_tail: self.loop_tail,
         }
     }
 
+    /// Recovers the buf from a stopped transfer.
+    ///
+    /// If the ring still holds the most recent complete frame (everything
+    /// after the last but one descriptor pushed with `set_eof` up to the
+    /// last), the payload is left untouched and only the descriptor ownership
+    /// and links of that frame are reset, so the next transfer starts by
+    /// replaying it. This lets a static image survive any number of
+    /// reconfigurations without being pushed again.
+    ///
+    /// It requires the ring to be large enough for a whole frame, so not the
+    /// MRE's: its 100_000 byte ring in internal RAM is a fifth of a 480x480
+    /// RGB565 frame, which has to be pushed again after each restart.
+    ///
+    /// Otherwise, or after the watchdog saw the DMA hang, the buf is reset
+    /// to empty, as if newly created.
     fn from_view(view: Self::View) -> Self {
+        let resume = if view.watchdog.hung {
+            None
+        } else {
+            view.resume_point()
+        };
+        if let Some(resume) = resume {
+            let len = view.descriptors.len();
+            let count = len - resume.free_descriptors;
+            for i in 0..count {
+                let descriptor = &mut view.descriptors[(resume.first_descriptor + i) % len];
+                descriptor.set_owner(Owner::Dma);
+            }
+            // A held frame keeps looping until `disable_loop`.
+            if view.loop_tail.is_none() {
+                view.descriptors[(resume.descriptor_idx + len - 1) % len].next = null_mut();
+            }
+
+            return Self {
+                // Nothing can be pushed until the replayed frame is in flight.
+                num_used_descriptors: len,
+                len_of_used_buffer: view.buffer.len(),
+                descriptors: view.descriptors,
+                buffer: view.buffer,
+                buffer_write_offset: 0,
+                external_tail: null_mut(),
+                resume: Some(resume),
+                benchmark: view.benchmark,
+                rb_swap: view.rb_swap,
+                on_underrun: view.on_underrun,
+                debug_pin: view.debug_pin,
+                hang_timeout: view.watchdog.timeout,
+                loop_tail: view.loop_tail,
+            };
+        }
+
         let descriptors = view.descriptors;
         let buffer = view.buffer;
 
@@ -193,6 +1121,14 @@ unsafe impl DmaTxBuffer for DmaTxStreamBuf {
             num_used_descriptors: 0,
             len_of_used_buffer: 0,
             buffer_write_offset: 0,
+            external_tail: null_mut(),
+            resume: None,
+            benchmark: view.benchmark,
+            rb_swap: view.rb_swap,
+            on_underrun: view.on_underrun,
+            debug_pin: view.debug_pin,
+            hang_timeout: view.watchdog.timeout,
+            loop_tail: None,
         }
     }
 }
@@ -209,16 +1145,170 @@ pub struct DmaTxStreamBufView {
 
     free_descriptors: usize,
     free_buffer_space: usize,
+
+    // Tail of an absorbed descriptor chain the next push links after, or null.
+    external_tail: *mut DmaDescriptor,
+
+    stats: PushStats,
+    benchmark: Option<ThroughputBenchmark>,
+    rb_swap: Option<PixelEndian>,
+    on_underrun: UnderrunPolicy,
+    underruns: u32,
+    debug_pin: Option<Output<'static>>,
+    watchdog: Watchdog,
+    // The buf's loop, carried over so recovering the buf keeps it.
+    loop_tail: Option<usize>,
+}
+
+/// How long the DMA may sit on queued data before
+/// [DmaTxStreamBufView::watchdog_tick] calls it hung.
+const DEFAULT_HANG_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// State of [DmaTxStreamBufView::watchdog_tick].
+struct Watchdog {
+    timeout: Duration,
+    // Descriptors reclaimed from the DMA so far, its progress.
+    reclaimed: u32,
+    // `reclaimed` at the last tick that saw progress, and when.
+    last_progress: Option<(u32, Instant)>,
+    hung: bool,
+}
+
+impl Watchdog {
+    const fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            reclaimed: 0,
+            last_progress: None,
+            hung: false,
+        }
+    }
+}
+
+/// The DMA reached the end of the queued data and stopped.
+///
+/// Descriptors linked after this point are never picked up, the transfer has
+/// to be stopped and started again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Underrun;
+
+/// What the DMA does when it runs out of linked descriptors mid-stream, see
+/// [DmaTxStreamBuf::set_underrun_policy].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum UnderrunPolicy {
+    /// The last descriptor links nowhere, so the DMA stops there and the
+    /// panel freezes; [DmaTxStreamBufView::try_push_wire] reports
+    /// [Underrun] from then on.
+    #[default]
+    Stop,
+    /// The last descriptor links to itself, so the DMA sends its chunk over
+    /// and over until the next push links a new one after it. The stream
+    /// never stops, but every repeat adds pixels the LCD's timing doesn't
+    /// account for, so the picture stays shifted until the transfer is
+    /// restarted.
+    RepeatLast,
+}
+
+/// Errors of [DmaTxStreamBuf::check_wire_format].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireCheckError {
+    /// Less than 512 bytes free for the pattern.
+    BufferTooSmall,
+    /// The ring holds `found` where the [WireFormat] says `expected`.
+    Mismatch {
+        pixel: usize,
+        expected: [u8; 2],
+        found: [u8; 2],
+    },
+}
+
+/// [DmaTxStreamBufView::drain] gave up with data still queued, e.g. because
+/// PCLK stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DrainTimeout {
+    /// Bytes the DMA hadn't sent yet.
+    pub queued: usize,
+}
+
+/// [recover_from_hang] couldn't start the DMA again.
+#[derive(Debug)]
+pub struct HangError(pub DmaError);
+
+/// Restarts a transfer [DmaTxStreamBufView::watchdog_tick] found hung:
+/// stops the DMA channel and the LCD, resets the ring to empty and starts
+/// the DMA again from its first descriptor. A transfer that isn't hung is
+/// handed back untouched.
+///
+/// Everything queued is dropped, also a complete frame that would otherwise
+/// be replayed, since the data or descriptors may be what hung the DMA.
+/// Push the next frame from its start afterwards.
+pub fn recover_from_hang<Dm: DriverMode>(
+    transfer: DpiTransfer<'_, DmaTxStreamBuf, Dm>,
+) -> Result<DpiTransfer<'_, DmaTxStreamBuf, Dm>, (HangError, Dpi<'_, Dm>, DmaTxStreamBuf)> {
+    if !transfer.has_hung() {
+        return Ok(transfer);
+    }
+
+    let (dpi, buf) = transfer.stop();
+    dpi.send(true, buf)
+        .map_err(|(err, dpi, buf)| (HangError(err), dpi, buf))
+}
+
+/// Errors of [DmaTxStreamBuf::append_descriptors].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrowError {
+    /// The buf holds a ring the DMA has been or is working through.
+    CannotGrowWhileActive,
+    Buf(DmaBufError),
+}
+
+/// A memory-to-memory copy into the ring started by
+/// [DmaTxStreamBufView::push_dma].
+pub struct DmaPush<'a, 'd> {
+    transfer: DmaTransferRx<'a, Mem2Mem<'d, Blocking>>,
+    view: &'a mut DmaTxStreamBufView,
+    len: usize,
+    set_eof: bool,
+    setup_micros: u64,
+}
+
+impl DmaPush<'_, '_> {
+    /// Whether the copy has finished.
+    pub fn is_done(&mut self) -> bool {
+        self.transfer.is_done()
+    }
+
+    /// Waits for the copy to finish and hands the copied bytes to the LCD DMA.
+    ///
+    /// Returns the number of bytes pushed.
+    pub fn finish(self) -> Result<usize, DmaError> {
+        let started = Instant::now();
+        self.transfer.wait()?;
+        self.view.link_chunk(self.len, self.set_eof);
+
+        let micros = self.setup_micros + started.elapsed().as_micros();
+        self.view.stats.record_dma(self.len, micros);
+        Ok(self.len)
+    }
 }
 
 impl DmaTxStreamBufView {
-    /// TODO
+    /// Use [Self::push_wire], which spells out that `data` is in wire order.
+    #[deprecated(note = "use `push_wire`")]
     pub fn push(&mut self, data: &[u8], set_eof: bool) -> usize {
+        self.push_wire(WireBytes(data), set_eof)
+    }
+
+    /// Links as much of `data` into the ring as there is room for, reclaiming
+    /// descriptors the DMA is done with. Returns the bytes pushed.
+    pub fn push_wire(&mut self, WireBytes(data): WireBytes<'_>, set_eof: bool) -> usize {
         if data.is_empty() {
             // TODO: Handle EOF
             return 0;
         }
 
+        let started = Instant::now();
+
         let max_chunk_size = BurstConfig::default().max_compatible_chunk_size();
 
         let mut remaining_to_push = data;
@@ -241,40 +1331,207 @@ impl DmaTxStreamBufView {
 
             let (chunk, remaining) = remaining_to_push.split_at(chunk_size);
 
-            let buffer_len = self.buffer.len();
+            if let Some(pin) = &mut self.debug_pin {
+                pin.set_high();
+            }
+
             let dest = &mut self.buffer[self.buffer_idx..][..chunk_size];
             dest.copy_from_slice(chunk);
+            if let Some(endian) = self.rb_swap {
+                swap_rb_in_place(dest, endian);
+            }
+            self.link_chunk(chunk_size, set_eof && remaining.is_empty());
+
+            if let Some(pin) = &mut self.debug_pin {
+                pin.set_low();
+            }
+
+            remaining_to_push = remaining;
+        }
+
+        let pushed = data.len() - remaining_to_push.len();
+        self.stats.record_cpu(pushed, started.elapsed().as_micros());
+        if let Some(bench) = &mut self.benchmark {
+            bench.record(pushed);
+        }
+
+        if pushed == 0 {
+            trace!(
+                "push stalled: {} free descriptors, {} free bytes",
+                self.free_descriptors, self.free_buffer_space
+            );
+        }
+
+        pushed
+    }
+
+    /// Pushes packed RGB888 pixels (red, green, blue bytes), e.g. straight
+    /// from an image decoder, converted with [convert_rgb888_to_565] to the
+    /// current [wire_format]. `set_eof` goes with the last pixel.
+    ///
+    /// Returns the number of pixels pushed, which is short of all of them if
+    /// the ring fills up. Trailing bytes short of a pixel are ignored.
+    pub fn push_rgb888(&mut self, src: &[u8], set_eof: bool) -> usize {
+        let wire = wire_format();
+
+        let mut pixels = [0; 128];
+        let mut bytes = [0; 256];
+        let mut pushed = 0;
+        let mut chunks = src.chunks(pixels.len() * 3).peekable();
+        while let Some(chunk) = chunks.next() {
+            let n = chunk.len() / 3;
+            convert_rgb888_to_565(chunk, &mut pixels);
+            to_wire(LogicalPixels(&pixels[..n]), WireBytesMut(&mut bytes), wire);
+
+            let last = chunks.peek().is_none();
+            let written = self.push_wire(WireBytes(&bytes[..n * 2]), set_eof && last);
+            pushed += written / 2;
+            if written < n * 2 {
+                break;
+            }
+        }
+
+        pushed
+    }
+
+    /// Copies `data` into the ring with a memory-to-memory DMA channel instead
+    /// of the CPU.
+    ///
+    /// The copy runs in the background until [DmaPush::finish] is called, so
+    /// the CPU can render the next scanline in the meantime. `data` has to
+    /// outlive the copy, hence `'static`, and be DMA-capable, see
+    /// [is_dma_capable]. `'static` alone doesn't make it so: a `static` or
+    /// `const` array is placed in flash, which fails with
+    /// [DmaError::UnsupportedMemoryRegion]. Data that is already in wire
+    /// format is what benefits from this.
+    ///
+    /// Only the part of `data` that fits in one contiguous free region of the
+    /// ring is copied, [DmaPush::finish] returns how much that was.
+    pub fn push_dma<'a, 'd>(
+        &'a mut self,
+        m2m: &'a mut Mem2Mem<'d, Blocking>,
+        data: &'static [u8],
+        set_eof: bool,
+    ) -> Result<DmaPush<'a, 'd>, DmaError> {
+        if !is_dma_capable(data) {
+            return Err(DmaError::UnsupportedMemoryRegion);
+        }
+
+        let started = Instant::now();
+
+        let max_chunk_size = BurstConfig::default().max_compatible_chunk_size();
+        if self.free_descriptors == 0 || self.free_buffer_space < data.len() {
+            self.reclaim_from_dma();
+        }
+
+        let len = data
+            .len()
+            .min(self.buffer.len() - self.buffer_idx)
+            .min(self.free_buffer_space)
+            .min(self.free_descriptors * max_chunk_size);
+        if len == 0 {
+            return Err(DmaError::Overflow);
+        }
+
+        // SAFETY: The region is free, and `self` stays mutably borrowed by the
+        // returned `DmaPush` until the copy has finished.
+        let mut dest = unsafe {
+            core::slice::from_raw_parts_mut(self.buffer.as_mut_ptr().add(self.buffer_idx), len)
+        };
+        let transfer = m2m.start_transfer(&mut dest, &&data[..len])?;
+
+        Ok(DmaPush {
+            transfer,
+            view: self,
+            len,
+            set_eof: set_eof && len == data.len(),
+            setup_micros: started.elapsed().as_micros(),
+        })
+    }
+
+    /// Changes the red/blue swap carried over from the [DmaTxStreamBuf], see
+    /// [DmaTxStreamBuf::set_rb_swap].
+    pub fn set_rb_swap(&mut self, swap: Option<PixelEndian>) {
+        self.rb_swap = swap;
+    }
+
+    /// The benchmark carried over from the [DmaTxStreamBuf], if one is
+    /// running.
+    pub fn throughput_benchmark(&self) -> Option<&ThroughputBenchmark> {
+        self.benchmark.as_ref()
+    }
+
+    /// One character per descriptor showing who owns it, see [OwnershipMap].
+    ///
+    /// Pushes hand their descriptors to the DMA right away, so there are no
+    /// `C` entries here.
+    pub fn ownership_map(&self) -> OwnershipMap<'_> {
+        OwnershipMap {
+            descriptors: self.descriptors,
+            pending: 0..0,
+        }
+    }
+
+    /// Push statistics gathered so far.
+    pub fn stats(&self) -> &PushStats {
+        &self.stats
+    }
+
+    /// Hands the next `len` bytes at `buffer_idx` to the DMA, splitting them
+    /// over as many descriptors as needed.
+    fn link_chunk(&mut self, len: usize, set_eof: bool) {
+        let max_chunk_size = BurstConfig::default().max_compatible_chunk_size();
+
+        let mut remaining = len;
+        while remaining > 0 {
+            let chunk_size = min(max_chunk_size, remaining);
+            remaining -= chunk_size;
+
+            let buffer_len = self.buffer.len();
+            let dest = self.buffer[self.buffer_idx..].as_mut_ptr();
             self.free_buffer_space -= chunk_size;
             self.buffer_idx += chunk_size;
             if self.buffer_idx == buffer_len {
+                trace!("ring buffer wrapped");
                 self.buffer_idx = 0;
             }
 
             let descriptor = &mut self.descriptors[self.descriptor_idx];
+            let this: *mut _ = descriptor;
 
-            descriptor.next = null_mut();
-            descriptor.buffer = dest.as_mut_ptr();
-            descriptor.set_length(chunk.len());
-            descriptor.set_size(chunk.len());
-            descriptor.set_suc_eof(set_eof && remaining.is_empty());
+            descriptor.next = match self.on_underrun {
+                UnderrunPolicy::Stop => null_mut(),
+                UnderrunPolicy::RepeatLast => this,
+            };
+            descriptor.buffer = dest;
+            descriptor.set_length(chunk_size);
+            descriptor.set_size(chunk_size);
+            descriptor.set_suc_eof(set_eof && remaining == 0);
+            trace!(
+                "descriptor {} -> DMA, {} bytes",
+                self.descriptor_idx, chunk_size
+            );
             descriptor.set_owner(Owner::Dma);
 
             let descriptor: *mut _ = descriptor;
-            self.descriptors[self
-                .descriptor_idx
-                .checked_sub(1)
-                .unwrap_or(self.descriptors.len() - 1)]
-            .next = descriptor;
+            if self.external_tail.is_null() {
+                self.descriptors[self
+                    .descriptor_idx
+                    .checked_sub(1)
+                    .unwrap_or(self.descriptors.len() - 1)]
+                .next = descriptor;
+            } else {
+                unsafe { (*self.external_tail).next = descriptor };
+                self.external_tail = null_mut();
+            }
 
             self.free_descriptors -= 1;
             self.descriptor_idx += 1;
             if self.descriptor_idx == self.descriptors.len() {
+                trace!("descriptor ring wrapped");
                 self.descriptor_idx = 0;
             }
-            remaining_to_push = remaining;
         }
-
-        data.len() - remaining_to_push.len()
     }
 
     /// TODO
@@ -282,6 +1539,187 @@ impl DmaTxStreamBufView {
         todo!()
     }
 
+    /// Use [Self::try_push_wire], which spells out that `data` is in wire
+    /// order.
+    #[deprecated(note = "use `try_push_wire`")]
+    pub fn try_push(&mut self, data: &[u8], set_eof: bool) -> Result<usize, Underrun> {
+        self.try_push_wire(WireBytes(data), set_eof)
+    }
+
+    /// Pushes all of `buf` as one frame, starting at row `y_offset` and
+    /// wrapping around, see [FrameBuffer::scrolled]. Blocks until it's all
+    /// in the ring, or fails with [Underrun] once the DMA has stopped, see
+    /// [Self::try_push_wire].
+    ///
+    /// Incrementing `y_offset` every frame scrolls a static buffer smoothly
+    /// without redrawing it and without the panel's scroll command. The wrap
+    /// costs at most one descriptor more than an unscrolled frame.
+    pub fn stream_scrolled<const W: usize, const H: usize>(
+        &mut self,
+        buf: &FrameBuffer<W, H>,
+        y_offset: usize,
+    ) -> Result<(), Underrun> {
+        let [first, second] = buf.scrolled(y_offset);
+        for (run, last) in [(first, second.is_empty()), (second, true)] {
+            let mut remaining = run;
+            while !remaining.is_empty() {
+                let pushed = self.try_push_wire(WireBytes(remaining), last)?;
+                remaining = &remaining[pushed..];
+            }
+        }
+        Ok(())
+    }
+
+    /// Like [Self::push_wire], but reports when the DMA has already stopped.
+    ///
+    /// - `Ok(0)` for non-empty `data`: the ring is full, try again later.
+    /// - `Err(Underrun)`: the DMA ran out of data and stopped, pushing more
+    ///   won't restart it.
+    ///
+    /// With [UnderrunPolicy::RepeatLast] the DMA never stops, so this pushes
+    /// regardless. Either way every underrun seen here is counted in
+    /// [Self::underruns].
+    ///
+    /// The check happens before pushing. If the DMA runs dry in the moment
+    /// between the check and the new data being linked, the stop can't be
+    /// seen from the descriptors at all; [DpiTransfer::is_done] is
+    /// authoritative.
+    ///
+    /// [DpiTransfer::is_done]: esp_hal::lcd_cam::lcd::dpi::DpiTransfer::is_done
+    pub fn try_push_wire(&mut self, data: WireBytes<'_>, set_eof: bool) -> Result<usize, Underrun> {
+        if self.has_underrun() {
+            self.underruns = self.underruns.saturating_add(1);
+            if self.on_underrun == UnderrunPolicy::Stop {
+                return Err(Underrun);
+            }
+        }
+
+        Ok(self.push_wire(data, set_eof))
+    }
+
+    /// Underruns [Self::try_push_wire] has seen.
+    pub fn underruns(&self) -> u32 {
+        self.underruns
+    }
+
+    /// Whether the DMA has finished the last linked descriptor, which means
+    /// it found no next one and stopped, or with
+    /// [UnderrunPolicy::RepeatLast] that it's repeating it.
+    pub fn has_underrun(&self) -> bool {
+        let tail = if self.external_tail.is_null() {
+            let idx = self
+                .descriptor_idx
+                .checked_sub(1)
+                .unwrap_or(self.descriptors.len() - 1);
+            &self.descriptors[idx]
+        } else {
+            unsafe { &*self.external_tail }
+        };
+
+        tail.owner() == Owner::Cpu
+    }
+
+    /// Busy-polls until the DMA has consumed every descriptor pushed so far,
+    /// or until `timeout` passes.
+    ///
+    /// For a clean shutdown: push the last frame, drain, then stop the
+    /// transfer or drop the `Dpi`, instead of cutting the panel off
+    /// mid-frame. The last bytes may still be in the LCD's FIFO when this
+    /// returns, well under a line. `None` waits forever, which hangs if the
+    /// DMA stalls; size the timeout with
+    /// [vsync::frame_timeout](crate::display::vsync::frame_timeout) and the
+    /// number of queued frames.
+    ///
+    /// Called on the running transfer, which derefs to the view; the
+    /// [DmaTxStreamBuf] itself is never running.
+    pub fn drain(&mut self, timeout: Option<Duration>) -> Result<(), DrainTimeout> {
+        let start = Instant::now();
+        while !self.has_underrun() {
+            if timeout.is_some_and(|timeout| start.elapsed() > timeout) {
+                return Err(DrainTimeout {
+                    queued: self.queued_bytes(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the DMA is still working through the ring. Call it
+    /// regularly, e.g. once per frame; returns `false` once it's hung.
+    ///
+    /// Progress is counted in descriptors the DMA hands back. With data
+    /// queued and none handed back for longer than the hang timeout, see
+    /// [DmaTxStreamBuf::set_hang_timeout], the DMA counts as hung, e.g. on a
+    /// descriptor pointing at unmapped memory or a stalled bus. An empty
+    /// ring or an [Underrun] is not a hang. Recover with
+    /// [recover_from_hang].
+    pub fn watchdog_tick(&mut self) -> bool {
+        if self.watchdog.hung {
+            return false;
+        }
+
+        self.reclaim_from_dma();
+        let now = Instant::now();
+        let reclaimed = self.watchdog.reclaimed;
+        let idle = self.free_descriptors == self.descriptors.len() || self.has_underrun();
+
+        match self.watchdog.last_progress {
+            Some((last, since)) if !idle && last == reclaimed => {
+                if now - since > self.watchdog.timeout {
+                    warn!("DMA made no progress for {}ms", (now - since).as_millis());
+                    self.watchdog.hung = true;
+                    return false;
+                }
+            }
+            _ => self.watchdog.last_progress = Some((reclaimed, now)),
+        }
+        true
+    }
+
+    /// Whether [Self::watchdog_tick] found the DMA hung.
+    pub fn has_hung(&self) -> bool {
+        self.watchdog.hung
+    }
+
+    /// Bytes pushed but not yet sent out by the DMA, i.e. how far ahead of
+    /// the panel the renderer is.
+    pub fn queued_bytes(&mut self) -> usize {
+        self.reclaim_from_dma();
+        self.buffer.len() - self.free_buffer_space
+    }
+
+    /// Finds the most recent complete frame still held by the ring.
+    fn resume_point(&self) -> Option<ResumePoint> {
+        let len = self.descriptors.len();
+        let used = len - self.free_descriptors;
+        // Newest first.
+        let idx = |k: usize| (self.descriptor_idx + len - 1 - k) % len;
+
+        let end = (0..used).find(|&k| self.descriptors[idx(k)].flags.suc_eof())?;
+        // The descriptor before the oldest used one counts too: it was filled
+        // the lap before, so its EOF still ends the frame before. That's all
+        // that's left of it after a resume.
+        let prev_end =
+            (end + 1..=used.min(len - 1)).find(|&k| self.descriptors[idx(k)].flags.suc_eof())?;
+
+        let frame = end..prev_end;
+        let frame_len: usize = frame.clone().map(|k| self.descriptors[idx(k)].size()).sum();
+        let last = &self.descriptors[idx(end)];
+        let buffer_end = unsafe {
+            last.buffer
+                .add(last.size())
+                .offset_from(self.buffer.as_ptr())
+        };
+
+        Some(ResumePoint {
+            first_descriptor: idx(prev_end - 1),
+            descriptor_idx: (idx(end) + 1) % len,
+            buffer_idx: buffer_end as usize % self.buffer.len(),
+            free_descriptors: len - frame.len(),
+            free_buffer_space: self.buffer.len() - frame_len,
+        })
+    }
+
     fn reclaim_from_dma(&mut self) {
         let (last, first) = self.descriptors.split_at(self.descriptor_idx);
         let descriptors_to_reclaim = first.iter().chain(last.iter()).skip(self.free_descriptors);
@@ -313,6 +1751,203 @@ impl DmaTxStreamBufView {
                 buffer_checkpoint = new_checkpoint;
             }
             self.free_descriptors += 1;
+            self.watchdog.reclaimed = self.watchdog.reclaimed.wrapping_add(1);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINE: usize = 480;
+    const LINES: usize = 4;
+
+    fn ring(descriptors: usize, bytes: usize) -> DmaTxStreamBuf {
+        let descriptors = Box::leak(vec![DmaDescriptor::EMPTY; descriptors].into_boxed_slice());
+        let buffer = Box::leak(vec![0; bytes].into_boxed_slice());
+        DmaTxStreamBuf::new_unchecked(descriptors, buffer)
+    }
+
+    fn push_frame(view: &mut DmaTxStreamBufView) {
+        for line in 0..LINES {
+            let data = [line as u8 + 1; LINE];
+            assert_eq!(view.push_wire(WireBytes(&data), line == LINES - 1), LINE);
+        }
+    }
+
+    /// Plays the DMA for one frame from `start`: sums the payload and hands
+    /// each descriptor back, up to the one with EOF.
+    fn scan_out_frame(start: *mut DmaDescriptor) -> usize {
+        let mut sum = 0;
+        let mut descriptor = start;
+        loop {
+            let d = unsafe { &mut *descriptor };
+            assert!(d.owner() == Owner::Dma, "descriptor not handed to the DMA");
+            let payload = unsafe { core::slice::from_raw_parts(d.buffer, d.len()) };
+            sum += payload.iter().map(|&b| b as usize).sum::<usize>();
+            d.set_owner(Owner::Cpu);
+            if d.flags.suc_eof() {
+                return sum;
+            }
+            descriptor = d.next;
+            assert!(!descriptor.is_null(), "frame ends without EOF");
+        }
+    }
+
+    #[test]
+    fn restarts_five_times_and_checksums_the_frame() {
+        let expected: usize = (1..=LINES).map(|line| line * LINE).sum();
+
+        let mut buf = ring(2 * LINES, 2 * LINES * LINE);
+        let start = buf.prepare().start;
+        let mut view = buf.into_view();
+        push_frame(&mut view);
+        push_frame(&mut view);
+        assert_eq!(scan_out_frame(start), expected);
+
+        for restart in 0..5 {
+            let mut buf = DmaTxStreamBuf::from_view(view);
+            assert!(buf.is_resumed(), "restart {restart}: frame not kept");
+
+            let start = buf.prepare().start;
+            view = buf.into_view();
+            assert_eq!(scan_out_frame(start), expected, "restart {restart}");
+        }
+    }
+
+    #[test]
+    fn check_wire_format_expects_the_rb_swap() {
+        for swap in [None, Some(PixelEndian::Little), Some(PixelEndian::Big)] {
+            let mut buf = ring(4, 4 * 1024);
+            buf.set_rb_swap(swap);
+            let available = buf.available_bytes();
+            assert_eq!(buf.check_wire_format(), Ok(()), "swap {swap:?}");
+            assert_eq!(buf.available_bytes(), available, "swap {swap:?}");
+        }
+    }
+
+    /// A view on `ring(2, …)` under `policy`, with one line prefilled and
+    /// one pushed as a frame and scanned out, so the DMA is past the tail.
+    fn played_out_ring(policy: UnderrunPolicy) -> DmaTxStreamBufView {
+        let mut buf = ring(2, 4 * LINE);
+        buf.set_underrun_policy(policy);
+        assert_eq!(buf.push_wire(WireBytes(&[1; LINE])), LINE);
+        let start = buf.prepare().start;
+        let mut view = buf.into_view();
+        assert_eq!(view.try_push_wire(WireBytes(&[2; LINE]), true), Ok(LINE));
+        assert_eq!(view.try_push_wire(WireBytes(&[3; LINE]), false), Ok(0));
+        assert_eq!(scan_out_frame(start), 3 * LINE);
+        view
+    }
+
+    #[test]
+    fn stop_policy_ends_the_chain_and_reports_underrun() {
+        let mut view = played_out_ring(UnderrunPolicy::Stop);
+        assert!(view.descriptors[1].next.is_null());
+
+        assert_eq!(
+            view.try_push_wire(WireBytes(&[3; LINE]), false),
+            Err(Underrun)
+        );
+        assert_eq!(view.underruns(), 1);
+    }
+
+    #[test]
+    fn repeat_last_policy_loops_the_tail_until_the_next_push() {
+        let mut view = played_out_ring(UnderrunPolicy::RepeatLast);
+        let [first, tail] = [0, 1].map(|i| &raw mut view.descriptors[i]);
+        assert_eq!(view.descriptors[1].next, tail);
+
+        assert_eq!(view.try_push_wire(WireBytes(&[3; LINE]), false), Ok(LINE));
+        assert_eq!(view.underruns(), 1);
+        assert_eq!(view.descriptors[1].next, first);
+        assert_eq!(view.descriptors[0].next, first);
+    }
+
+    #[test]
+    fn into_circular_links_the_last_descriptor_back_to_the_first() {
+        let max_chunk_size = BurstConfig::default().max_compatible_chunk_size();
+        // Three descriptors' worth, the last one partly filled.
+        let len = 2 * max_chunk_size + 100;
+        let mut buf = ring(4, 3 * max_chunk_size);
+        assert_eq!(buf.push_wire(WireBytes(&vec![0x5A; len])), len);
+
+        let mut frame = buf.into_circular().unwrap();
+        let start = frame.prepare().start;
+
+        let mut descriptor = start;
+        for i in 0..3 {
+            assert!(!descriptor.is_null(), "chain ends after {i} descriptors");
+            let d = unsafe { &*descriptor };
+            assert_eq!(d.flags.suc_eof(), i == 2, "EOF on descriptor {i}");
+            descriptor = d.next;
+        }
+        assert_eq!(descriptor, start, "no loop after 3 descriptors");
+        assert_eq!(start, &raw mut frame.split()[0]);
+    }
+
+    /// Plays the DMA one descriptor at a time, from where it left off.
+    struct Consumer(*mut DmaDescriptor);
+
+    impl Consumer {
+        /// Hands up to `n` descriptors back, stopping at the end of the chain.
+        fn consume(&mut self, n: usize) {
+            for _ in 0..n {
+                let d = unsafe { &mut *self.0 };
+                assert!(d.owner() == Owner::Dma, "descriptor not handed to the DMA");
+                d.set_owner(Owner::Cpu);
+                if d.next.is_null() {
+                    return;
+                }
+                self.0 = d.next;
+            }
+        }
+    }
+
+    #[test]
+    fn try_push_tells_a_full_ring_from_a_stopped_dma() {
+        let mut buf = ring(2, 4 * LINE);
+        assert_eq!(buf.push_wire(WireBytes(&[1; LINE])), LINE);
+        let mut dma = Consumer(buf.prepare().start);
+        let mut view = buf.into_view();
+
+        assert_eq!(view.try_push_wire(WireBytes(&[2; LINE]), false), Ok(LINE));
+        // Both descriptors are still the DMA's.
+        assert_eq!(view.try_push_wire(WireBytes(&[3; LINE]), false), Ok(0));
+
+        dma.consume(1);
+        assert_eq!(view.try_push_wire(WireBytes(&[3; LINE]), false), Ok(LINE));
+        assert_eq!(view.underruns(), 0);
+
+        // The DMA catches up and stops at the tail.
+        dma.consume(2);
+        assert_eq!(
+            view.try_push_wire(WireBytes(&[4; LINE]), false),
+            Err(Underrun)
+        );
+        assert_eq!(view.underruns(), 1);
+    }
+
+    #[test]
+    fn memory_outside_dram_and_psram_is_not_dma_capable() {
+        // Host addresses are far from the ESP32-S3's DRAM and PSRAM windows.
+        let descriptors = Box::leak(vec![DmaDescriptor::EMPTY; 2].into_boxed_slice());
+        let buffer = Box::leak(vec![0; 64].into_boxed_slice());
+        assert!(!is_dma_capable(buffer));
+        assert!(matches!(
+            DmaTxStreamBuf::new(descriptors, buffer),
+            Err(DmaBufError::UnsupportedMemoryRegion)
+        ));
+    }
+
+    #[test]
+    fn slice_in_range_needs_all_of_the_slice() {
+        let data = [0u8; 16];
+        let start = data.as_ptr() as usize;
+
+        assert!(slice_in_range(&data, start..start + 16));
+        assert!(!slice_in_range(&data, start..start + 15));
+        assert!(!slice_in_range(&data, start + 1..start + 32));
+    }
+}
@@ -0,0 +1,121 @@
+//! Copying changed rows of a [FrameBuffer] into a buffer the DMA scans out,
+//! e.g. the back buffer of a
+//! [PsramDoubleBuffer](crate::psram::PsramDoubleBuffer).
+//!
+//! Each copy has a fixed cost on top of the bytes moved, mostly the cache
+//! writeback, so UIs that dirty many scattered single rows spend more time on
+//! overhead than on pixels. [flush] merges dirty rows separated by fewer than
+//! [FlushConfig::coalesce_gap] clean ones into a single copy, re-copying the
+//! clean rows in between, and caps the bytes per call so a large change is
+//! spread over several frames instead of stalling one.
+
+use core::ops::Range;
+
+use crate::{
+    dma::is_slice_in_psram, framebuffer::FrameBuffer, hal::time::Instant, psram::cache_writeback,
+    stats::FlushStats,
+};
+
+/// Rows of an `H` row frame changed since the last [flush].
+pub struct DirtyRows<const H: usize> {
+    rows: [bool; H],
+}
+
+impl<const H: usize> DirtyRows<H> {
+    pub const fn new() -> Self {
+        Self { rows: [false; H] }
+    }
+
+    pub fn mark(&mut self, y: usize) {
+        if let Some(row) = self.rows.get_mut(y) {
+            *row = true;
+        }
+    }
+
+    /// Marks `range`, clipped to the frame.
+    pub fn mark_range(&mut self, range: Range<usize>) {
+        let end = range.end.min(H);
+        if range.start < end {
+            self.rows[range.start..end].fill(true);
+        }
+    }
+
+    pub fn is_clean(&self) -> bool {
+        !self.rows.contains(&true)
+    }
+
+    /// The next run of dirty rows at or after `from`, extended over gaps of
+    /// fewer than `gap` clean rows.
+    fn next_range(&self, from: usize, gap: usize) -> Option<Range<usize>> {
+        let start = from + self.rows[from..].iter().position(|&d| d)?;
+        let mut end = start + 1;
+        let mut y = end;
+        while y < H && (self.rows[y] || y - end + 1 < gap) {
+            if self.rows[y] {
+                end = y + 1;
+            }
+            y += 1;
+        }
+        Some(start..end)
+    }
+}
+
+impl<const H: usize> Default for DirtyRows<H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Tuning of [flush].
+#[derive(Debug, Clone, Copy)]
+pub struct FlushConfig {
+    /// Dirty rows separated by fewer clean rows than this are copied
+    /// together. 0 copies every run of dirty rows on its own.
+    pub coalesce_gap: usize,
+    /// Most bytes copied per call; rows that don't fit stay dirty for the
+    /// next one. At least one row is always copied.
+    pub max_bytes: usize,
+}
+
+/// Copies the dirty rows of `src` into `dst`, which holds a frame in the
+/// same layout, and marks them clean.
+///
+/// Call once per frame until [DirtyRows::is_clean]. `dst` is written back
+/// from the cache after every copy if it's in PSRAM.
+pub fn flush<const W: usize, const H: usize>(
+    src: &FrameBuffer<W, H>,
+    dst: &mut [u8],
+    dirty: &mut DirtyRows<H>,
+    config: &FlushConfig,
+) -> FlushStats {
+    let started = Instant::now();
+    let line = FrameBuffer::<W, H>::GEOMETRY.bytes_per_line();
+    let max_rows = (config.max_bytes / line).max(1);
+    let psram = is_slice_in_psram(dst);
+
+    let mut stats = FlushStats::default();
+    let mut y = 0;
+    let mut rows_left = max_rows;
+    while rows_left > 0 {
+        let Some(range) = dirty.next_range(y, config.coalesce_gap) else {
+            break;
+        };
+        let range = range.start..range.end.min(range.start + rows_left);
+
+        let bytes = range.start * line..range.end * line;
+        let dst = &mut dst[bytes.clone()];
+        dst.copy_from_slice(&src.as_bytes()[bytes]);
+        if psram {
+            cache_writeback(dst);
+        }
+        dirty.rows[range.clone()].fill(false);
+
+        stats.ranges += 1;
+        stats.bytes += dst.len();
+        rows_left -= range.len();
+        y = range.end;
+    }
+
+    stats.micros = started.elapsed().as_micros();
+    stats
+}
@@ -0,0 +1,207 @@
+//! Latest-wins hand-off of complete frames from a producer to the streamer.
+//!
+//! Frames can arrive at any rate, e.g. from a network stream, while the panel
+//! refreshes at a fixed one. The producer renders into a free slot and
+//! submits it; once per refresh the streamer presents the newest submitted
+//! slot. Older submitted slots that were never shown are dropped and freed,
+//! so the panel never lags behind and the producer never waits on the
+//! display.
+//!
+//! The queue only tracks slot indices; the frame buffers themselves live
+//! with the caller, one per slot. `N` slots cost `N` full frames, 460800
+//! bytes each at 480x480 RGB565. `N = 3` is enough for the producer to never
+//! run out: one slot shown, one ready and one being written. With `N = 2`
+//! [FrameQueue::acquire] fails while a submitted frame is waiting to be
+//! shown.
+//!
+//! Producer and streamer both need `&mut`, so share the queue through a
+//! `critical_section::Mutex<RefCell<_>>` if they run in different contexts.
+//!
+//! ```ignore
+//! // Producer
+//! if let Some(slot) = queue.acquire() {
+//!     decode_into(&mut frames[slot]);
+//!     queue.submit(slot);
+//! }
+//!
+//! // Streamer, once per refresh
+//! queue.present();
+//! if let Some(slot) = queue.current() {
+//!     push_frame(&frames[slot]);
+//! }
+//! ```
+
+/// State of one slot of a [FrameQueue].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Slot {
+    Free,
+    Writing,
+    /// Submitted with this sequence number, newer frames have higher ones.
+    Ready(u32),
+    Shown,
+}
+
+/// Slot bookkeeping for `N` frame buffers, see the [module](self) docs.
+pub struct FrameQueue<const N: usize> {
+    slots: [Slot; N],
+    next_seq: u32,
+    dropped: u32,
+}
+
+impl<const N: usize> FrameQueue<N> {
+    pub const fn new() -> Self {
+        Self {
+            slots: [Slot::Free; N],
+            next_seq: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Claims a free slot for the producer to render into, if there is one.
+    pub fn acquire(&mut self) -> Option<usize> {
+        let slot = self.slots.iter().position(|&s| s == Slot::Free)?;
+        self.slots[slot] = Slot::Writing;
+        Some(slot)
+    }
+
+    /// Marks `slot`, claimed with [Self::acquire], as a complete frame.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `slot` wasn't acquired.
+    pub fn submit(&mut self, slot: usize) {
+        assert_eq!(self.slots[slot], Slot::Writing, "slot {slot} not acquired");
+        self.slots[slot] = Slot::Ready(self.next_seq);
+        self.next_seq = self.next_seq.wrapping_add(1);
+    }
+
+    /// Hands back a slot claimed with [Self::acquire] without submitting it.
+    pub fn release(&mut self, slot: usize) {
+        if self.slots[slot] == Slot::Writing {
+            self.slots[slot] = Slot::Free;
+        }
+    }
+
+    /// Switches to the newest submitted frame, for the streamer to call once
+    /// per refresh. Returns the new slot, or `None` to keep showing
+    /// [Self::current].
+    ///
+    /// The previously shown slot and every older submitted one are freed;
+    /// the latter count as [Self::dropped].
+    pub fn present(&mut self) -> Option<usize> {
+        // Sequence numbers are compared relative to the next one, so
+        // wrapping doesn't reorder them.
+        let age = |seq: u32| self.next_seq.wrapping_sub(seq);
+        let newest = (0..N)
+            .filter_map(|i| match self.slots[i] {
+                Slot::Ready(seq) => Some((i, age(seq))),
+                _ => None,
+            })
+            .min_by_key(|&(_, age)| age)
+            .map(|(i, _)| i)?;
+
+        for (i, slot) in self.slots.iter_mut().enumerate() {
+            match *slot {
+                Slot::Shown => *slot = Slot::Free,
+                Slot::Ready(_) if i != newest => {
+                    *slot = Slot::Free;
+                    self.dropped += 1;
+                }
+                _ => {}
+            }
+        }
+        self.slots[newest] = Slot::Shown;
+
+        Some(newest)
+    }
+
+    /// The slot being shown, if any frame was presented yet.
+    pub fn current(&self) -> Option<usize> {
+        self.slots.iter().position(|&s| s == Slot::Shown)
+    }
+
+    /// Submitted frames replaced by a newer one before they were shown.
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+}
+
+impl<const N: usize> Default for FrameQueue<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn present_shows_the_newest_and_drops_the_rest() {
+        let mut queue = FrameQueue::<3>::new();
+        assert_eq!(queue.present(), None);
+        assert_eq!(queue.current(), None);
+
+        let a = queue.acquire().unwrap();
+        queue.submit(a);
+        let b = queue.acquire().unwrap();
+        queue.submit(b);
+
+        assert_eq!(queue.present(), Some(b));
+        assert_eq!(queue.current(), Some(b));
+        assert_eq!(queue.dropped(), 1);
+
+        // Nothing new: keep showing b.
+        assert_eq!(queue.present(), None);
+        assert_eq!(queue.current(), Some(b));
+    }
+
+    #[test]
+    fn present_frees_the_previous_frame() {
+        let mut queue = FrameQueue::<2>::new();
+        let a = queue.acquire().unwrap();
+        queue.submit(a);
+        queue.present();
+
+        let b = queue.acquire().unwrap();
+        queue.submit(b);
+        // One slot shown, one waiting: the producer has to wait.
+        assert_eq!(queue.acquire(), None);
+
+        assert_eq!(queue.present(), Some(b));
+        assert_eq!(queue.acquire(), Some(a));
+        assert_eq!(queue.dropped(), 0);
+    }
+
+    #[test]
+    fn release_and_writing_slots_are_never_presented() {
+        let mut queue = FrameQueue::<3>::new();
+        let a = queue.acquire().unwrap();
+        let b = queue.acquire().unwrap();
+        queue.release(a);
+
+        assert_eq!(queue.present(), None);
+        assert_eq!(queue.acquire(), Some(a));
+        queue.submit(b);
+        assert_eq!(queue.present(), Some(b));
+    }
+
+    #[test]
+    fn newest_wins_across_sequence_wrap() {
+        let mut queue = FrameQueue::<3>::new();
+        queue.next_seq = u32::MAX;
+
+        let old = queue.acquire().unwrap();
+        queue.submit(old);
+        let new = queue.acquire().unwrap();
+        queue.submit(new);
+
+        assert_eq!(queue.present(), Some(new));
+    }
+
+    #[test]
+    #[should_panic(expected = "not acquired")]
+    fn submit_without_acquire_panics() {
+        FrameQueue::<2>::new().submit(0);
+    }
+}
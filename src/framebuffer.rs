@@ -0,0 +1,228 @@
+use crate::{
+    color::{PixelFormat, WireFormat, wire_format},
+    geometry::FrameGeometry,
+};
+
+/// Rotation of logical coordinates relative to the panel, clockwise.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    /// Logical (x, y) is physical (x, y).
+    #[default]
+    Deg0,
+    /// Logical (x, y) is physical (W - 1 - y, x); the logical frame is H
+    /// wide and W high.
+    Deg90,
+    /// Logical (x, y) is physical (W - 1 - x, H - 1 - y).
+    Deg180,
+    /// Logical (x, y) is physical (y, H - 1 - x); the logical frame is H
+    /// wide and W high.
+    Deg270,
+}
+
+/// Full-frame buffer of RGB565 pixels in the byte layout expected by the DMA.
+///
+/// Every pixel is stored as two bytes in the [WireFormat] current when it was
+/// written, see [set_wire_format](crate::color::set_wire_format). Rows are
+/// always stored in panel order; [FrameBuffer::set_pixel] maps logical
+/// coordinates through the [Orientation].
+pub struct FrameBuffer<const W: usize, const H: usize> {
+    pixels: [[[u8; 2]; W]; H],
+    orientation: Orientation,
+}
+
+impl<const W: usize, const H: usize> FrameBuffer<W, H> {
+    /// Physical size and pixel format of the buffer.
+    pub const GEOMETRY: FrameGeometry = FrameGeometry::new(W, H, PixelFormat::Rgb565);
+
+    /// Creates a black frame buffer.
+    ///
+    /// A 480x480 buffer is 460_800 bytes, far more than the stack holds, so
+    /// build it where it's going to live, e.g. in a `ConstStaticCell`, and
+    /// [Self::fill] it there:
+    ///
+    /// ```ignore
+    /// static FB: ConstStaticCell<FrameBuffer<480, 480>> = ConstStaticCell::new(FrameBuffer::new());
+    /// let fb = FB.take();
+    /// fb.fill(RED);
+    /// ```
+    pub const fn new() -> Self {
+        Self {
+            pixels: [[[0; 2]; W]; H],
+            orientation: Orientation::Deg0,
+        }
+    }
+
+    /// Sets every pixel to `color`, in place.
+    pub fn fill(&mut self, color: u16) {
+        let bytes = wire_format().encode(color);
+        self.pixels.as_flattened_mut().fill(bytes);
+    }
+
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    /// Sets how [Self::set_pixel] maps coordinates. Pixels already drawn
+    /// stay where they are.
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+    }
+
+    /// Width and height in logical coordinates.
+    pub fn logical_size(&self) -> (usize, usize) {
+        match self.orientation {
+            Orientation::Deg0 | Orientation::Deg180 => (W, H),
+            Orientation::Deg90 | Orientation::Deg270 => (H, W),
+        }
+    }
+
+    /// The physical position of logical (`x`, `y`).
+    pub(crate) fn physical(&self, x: usize, y: usize) -> (usize, usize) {
+        match self.orientation {
+            Orientation::Deg0 => (x, y),
+            Orientation::Deg90 => (W - 1 - y, x),
+            Orientation::Deg180 => (W - 1 - x, H - 1 - y),
+            Orientation::Deg270 => (y, H - 1 - x),
+        }
+    }
+
+    /// The whole frame as bytes, ready to be pushed.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.pixels.as_flattened().as_flattened()
+    }
+
+    /// Sets the pixel at logical (`x`, `y`) to `color`.
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: u16) {
+        let (x, y) = self.physical(x, y);
+        self.pixels[y][x] = wire_format().encode(color);
+    }
+
+    /// The bytes of physical row `y`.
+    pub fn row(&self, y: usize) -> &[u8] {
+        self.pixels[y].as_flattened()
+    }
+
+    /// The frame starting at physical row `y_offset` and wrapping around to
+    /// row 0, as the two contiguous runs to push in order.
+    ///
+    /// Row `y_offset % H` comes first, so content moves up one row per
+    /// increment and what scrolls off the top comes back at the bottom. At
+    /// offset 0 and for an empty frame the second run is empty.
+    pub fn scrolled(&self, y_offset: usize) -> [&[u8]; 2] {
+        let (top, bottom) = self.pixels.split_at(y_offset.checked_rem(H).unwrap_or(0));
+        [
+            bottom.as_flattened().as_flattened(),
+            top.as_flattened().as_flattened(),
+        ]
+    }
+}
+
+impl<const W: usize, const H: usize> Default for FrameBuffer<W, H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builds a `W` x `H` frame of `color` laid out as `wire` says.
+///
+/// This runs in const context, where the [WireFormat] set at runtime isn't
+/// known, so pass the one built from the DPI `Format` with
+/// [WireFormat::from_format].
+///
+/// Stable const generics can't express `[u8; W * H * 2]` as a return type, so
+/// the byte length is passed as `N` and must equal the RGB565
+/// [FrameGeometry::bytes_per_frame]. A mismatch fails to compile:
+///
+/// ```ignore
+/// static FRAME: [u8; 480 * 480 * 2] =
+///     solid_frame::<480, 480, { 480 * 480 * 2 }>(RED, WireFormat::DEFAULT);
+/// ```
+pub const fn solid_frame<const W: usize, const H: usize, const N: usize>(
+    color: u16,
+    wire: WireFormat,
+) -> [u8; N] {
+    const {
+        let geometry = FrameGeometry::new(W, H, PixelFormat::Rgb565);
+        assert!(N == geometry.bytes_per_frame(), "N must be W * H * 2");
+    };
+
+    let bytes = wire.encode(color);
+    let mut frame = [0; N];
+
+    let mut i = 0;
+    while i < N {
+        frame[i] = bytes[i % 2];
+        i += 1;
+    }
+
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: u16 = 0b11111_000000_00000;
+
+    #[test]
+    fn solid_frame_repeats_the_wire_bytes() {
+        let wire = WireFormat::DEFAULT;
+        let frame = solid_frame::<4, 3, { 4 * 3 * 2 }>(RED, wire);
+        assert!(frame.chunks_exact(2).all(|pixel| pixel == wire.encode(RED)));
+    }
+
+    #[test]
+    fn fill_sets_every_pixel() {
+        let mut fb = FrameBuffer::<4, 3>::new();
+        assert!(fb.as_bytes().iter().all(|&b| b == 0));
+
+        fb.fill(RED);
+        let bytes = wire_format().encode(RED);
+        assert_eq!(fb.as_bytes().len(), 4 * 3 * 2);
+        assert!(fb.as_bytes().chunks_exact(2).all(|pixel| pixel == bytes));
+    }
+
+    #[test]
+    fn scrolled_handles_an_empty_frame() {
+        let fb = FrameBuffer::<4, 0>::new();
+        assert_eq!(fb.scrolled(3), [&[][..], &[][..]]);
+    }
+
+    /// The physical position of the only non-black pixel.
+    fn lit_pixel<const W: usize, const H: usize>(fb: &FrameBuffer<W, H>) -> (usize, usize) {
+        let mut lit = (0..H)
+            .flat_map(|y| (0..W).map(move |x| (x, y)))
+            .filter(|&(x, y)| fb.row(y)[x * 2..][..2] != [0, 0]);
+        let pixel = lit.next().expect("no pixel set");
+        assert_eq!(lit.next(), None, "more than one pixel set");
+        pixel
+    }
+
+    #[test]
+    fn set_pixel_maps_logical_corners_for_each_orientation() {
+        const W: usize = 4;
+        const H: usize = 3;
+        // Physical positions of logical (0, 0) and of the logical bottom
+        // right corner.
+        let cases = [
+            (Orientation::Deg0, (W, H), (0, 0), (W - 1, H - 1)),
+            (Orientation::Deg90, (H, W), (W - 1, 0), (0, H - 1)),
+            (Orientation::Deg180, (W, H), (W - 1, H - 1), (0, 0)),
+            (Orientation::Deg270, (H, W), (0, H - 1), (W - 1, 0)),
+        ];
+
+        for (orientation, size, origin, corner) in cases {
+            let mut fb = FrameBuffer::<W, H>::new();
+            fb.set_orientation(orientation);
+            assert_eq!(fb.logical_size(), size, "{orientation:?}");
+
+            fb.set_pixel(0, 0, RED);
+            assert_eq!(lit_pixel(&fb), origin, "{orientation:?} origin");
+
+            let mut fb = FrameBuffer::<W, H>::new();
+            fb.set_orientation(orientation);
+            fb.set_pixel(size.0 - 1, size.1 - 1, RED);
+            assert_eq!(lit_pixel(&fb), corner, "{orientation:?} corner");
+        }
+    }
+}
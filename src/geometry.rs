@@ -0,0 +1,37 @@
+//! Frame dimensions and the byte sizes derived from them.
+
+use crate::color::PixelFormat;
+
+/// Size and pixel format of a frame, the one place bytes-per-line and
+/// bytes-per-frame are worked out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameGeometry {
+    pub width: usize,
+    pub height: usize,
+    pub pixel_format: PixelFormat,
+}
+
+impl FrameGeometry {
+    pub const fn new(width: usize, height: usize, pixel_format: PixelFormat) -> Self {
+        Self {
+            width,
+            height,
+            pixel_format,
+        }
+    }
+
+    /// Bytes in one line of pixels.
+    pub const fn bytes_per_line(&self) -> usize {
+        self.width * self.pixel_format.bytes_per_pixel()
+    }
+
+    /// Bytes in a whole frame.
+    pub const fn bytes_per_frame(&self) -> usize {
+        self.bytes_per_line() * self.height
+    }
+
+    /// Whole lines that fit in `chunk_size` bytes.
+    pub const fn lines_per_chunk(&self, chunk_size: usize) -> usize {
+        chunk_size / self.bytes_per_line()
+    }
+}
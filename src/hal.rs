@@ -0,0 +1,15 @@
+//! esp-hal, as the rest of the crate imports it.
+//!
+//! On the ESP32-S3 this is esp-hal itself. esp-hal doesn't build for any
+//! other target, so on the host [host] stands in for the parts the library
+//! uses: plain data types behave like esp-hal's, and peripheral drivers are
+//! types without values, so code that drives hardware still type checks but
+//! can't be called from a test.
+
+#[cfg(target_arch = "xtensa")]
+pub use esp_hal::*;
+
+#[cfg(not(target_arch = "xtensa"))]
+mod host;
+#[cfg(not(target_arch = "xtensa"))]
+pub use host::*;
@@ -0,0 +1,975 @@
+//! Host stand-ins for esp-hal 1.0.0-beta.0, see [crate::hal].
+//!
+//! Everything here mirrors the signature of the esp-hal item of the same
+//! path. Keep it to what the library uses.
+
+use core::marker::PhantomData;
+
+/// A value of a peripheral driver, which can't exist on the host.
+#[derive(Debug)]
+pub(crate) enum Never {}
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// See `esp_hal::DriverMode`.
+pub trait DriverMode: private::Sealed {}
+
+/// See `esp_hal::Blocking`.
+#[derive(Debug)]
+pub struct Blocking;
+
+impl private::Sealed for Blocking {}
+impl DriverMode for Blocking {}
+
+pub mod time {
+    //! See `esp_hal::time`, counting from the start of the test process.
+
+    use core::{
+        fmt::{self, Debug, Display, Formatter},
+        ops::{Add, AddAssign, Div, Mul, Sub, SubAssign},
+    };
+
+    /// See `esp_hal::time::Rate`.
+    #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+    pub struct Rate(u32);
+
+    impl Rate {
+        pub const fn from_hz(val: u32) -> Self {
+            Self(val)
+        }
+
+        pub const fn from_khz(val: u32) -> Self {
+            Self(val * 1_000)
+        }
+
+        pub const fn from_mhz(val: u32) -> Self {
+            Self(val * 1_000_000)
+        }
+
+        pub const fn as_hz(&self) -> u32 {
+            self.0
+        }
+
+        pub const fn as_khz(&self) -> u32 {
+            self.0 / 1_000
+        }
+
+        pub const fn as_mhz(&self) -> u32 {
+            self.0 / 1_000_000
+        }
+
+        pub const fn as_duration(&self) -> Duration {
+            Duration::from_micros(1_000_000 / self.0 as u64)
+        }
+    }
+
+    impl Display for Rate {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            write!(f, "{} Hz", self.0)
+        }
+    }
+
+    impl Div for Rate {
+        type Output = u32;
+
+        fn div(self, rhs: Self) -> u32 {
+            self.0 / rhs.0
+        }
+    }
+
+    impl Mul<u32> for Rate {
+        type Output = Rate;
+
+        fn mul(self, rhs: u32) -> Rate {
+            Rate(self.0 * rhs)
+        }
+    }
+
+    impl Div<u32> for Rate {
+        type Output = Rate;
+
+        fn div(self, rhs: u32) -> Rate {
+            Rate(self.0 / rhs)
+        }
+    }
+
+    /// See `esp_hal::time::Instant`.
+    #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    pub struct Instant(u64);
+
+    impl Instant {
+        pub const EPOCH: Instant = Instant(0);
+
+        pub fn now() -> Self {
+            extern crate std;
+            use std::{sync::OnceLock, time};
+
+            static START: OnceLock<time::Instant> = OnceLock::new();
+            Self(START.get_or_init(time::Instant::now).elapsed().as_micros() as u64)
+        }
+
+        pub fn duration_since_epoch(&self) -> Duration {
+            Duration(self.0)
+        }
+
+        pub fn elapsed(&self) -> Duration {
+            Self::now() - *self
+        }
+    }
+
+    impl Debug for Instant {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            write!(f, "Instant({} µs since epoch)", self.0)
+        }
+    }
+
+    impl Display for Instant {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            write!(f, "{} µs since epoch", self.0)
+        }
+    }
+
+    impl Add<Duration> for Instant {
+        type Output = Self;
+
+        fn add(self, rhs: Duration) -> Self {
+            Self(self.0 + rhs.0)
+        }
+    }
+
+    impl AddAssign<Duration> for Instant {
+        fn add_assign(&mut self, rhs: Duration) {
+            self.0 += rhs.0;
+        }
+    }
+
+    impl Sub for Instant {
+        type Output = Duration;
+
+        fn sub(self, rhs: Self) -> Duration {
+            Duration(self.0 - rhs.0)
+        }
+    }
+
+    impl Sub<Duration> for Instant {
+        type Output = Self;
+
+        fn sub(self, rhs: Duration) -> Self {
+            Self(self.0 - rhs.0)
+        }
+    }
+
+    impl SubAssign<Duration> for Instant {
+        fn sub_assign(&mut self, rhs: Duration) {
+            self.0 -= rhs.0;
+        }
+    }
+
+    /// See `esp_hal::time::Duration`, in microseconds.
+    #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    pub struct Duration(u64);
+
+    impl Duration {
+        pub const MAX: Self = Self(u64::MAX);
+        pub const ZERO: Self = Self(0);
+
+        pub const fn from_micros(val: u64) -> Self {
+            Self(val)
+        }
+
+        pub const fn from_millis(val: u64) -> Self {
+            Self(val * 1_000)
+        }
+
+        pub const fn from_secs(val: u64) -> Self {
+            Self(val * 1_000_000)
+        }
+
+        pub const fn from_minutes(val: u64) -> Self {
+            Self(val * 60_000_000)
+        }
+
+        pub const fn from_hours(val: u64) -> Self {
+            Self(val * 3_600_000_000)
+        }
+
+        pub const fn as_micros(&self) -> u64 {
+            self.0
+        }
+
+        pub const fn as_millis(&self) -> u64 {
+            self.0 / 1_000
+        }
+
+        pub const fn as_secs(&self) -> u64 {
+            self.0 / 1_000_000
+        }
+
+        pub const fn as_minutes(&self) -> u64 {
+            self.0 / 60_000_000
+        }
+
+        pub const fn as_hours(&self) -> u64 {
+            self.0 / 3_600_000_000
+        }
+
+        pub const fn checked_add(self, rhs: Self) -> Option<Self> {
+            match self.0.checked_add(rhs.0) {
+                Some(val) => Some(Self(val)),
+                None => None,
+            }
+        }
+
+        pub const fn checked_sub(self, rhs: Self) -> Option<Self> {
+            match self.0.checked_sub(rhs.0) {
+                Some(val) => Some(Self(val)),
+                None => None,
+            }
+        }
+
+        pub const fn saturating_add(self, rhs: Self) -> Self {
+            Self(self.0.saturating_add(rhs.0))
+        }
+
+        pub const fn saturating_sub(self, rhs: Self) -> Self {
+            Self(self.0.saturating_sub(rhs.0))
+        }
+    }
+
+    impl Debug for Duration {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            write!(f, "Duration({} µs)", self.0)
+        }
+    }
+
+    impl Display for Duration {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            write!(f, "{} µs", self.0)
+        }
+    }
+
+    impl Add for Duration {
+        type Output = Self;
+
+        fn add(self, rhs: Self) -> Self {
+            Self(self.0 + rhs.0)
+        }
+    }
+
+    impl AddAssign for Duration {
+        fn add_assign(&mut self, rhs: Self) {
+            self.0 += rhs.0;
+        }
+    }
+
+    impl Sub for Duration {
+        type Output = Self;
+
+        fn sub(self, rhs: Self) -> Self {
+            Self(self.0 - rhs.0)
+        }
+    }
+
+    impl SubAssign for Duration {
+        fn sub_assign(&mut self, rhs: Self) {
+            self.0 -= rhs.0;
+        }
+    }
+
+    impl Mul<u32> for Duration {
+        type Output = Self;
+
+        fn mul(self, rhs: u32) -> Self {
+            Self(self.0 * rhs as u64)
+        }
+    }
+
+    impl Div<u32> for Duration {
+        type Output = Self;
+
+        fn div(self, rhs: u32) -> Self {
+            Self(self.0 / rhs as u64)
+        }
+    }
+
+    impl Div for Duration {
+        type Output = u64;
+
+        fn div(self, rhs: Self) -> u64 {
+            self.0 / rhs.0
+        }
+    }
+}
+
+pub mod gpio {
+    //! See `esp_hal::gpio`.
+
+    use super::*;
+
+    /// See `esp_hal::gpio::Level`.
+    #[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+    pub enum Level {
+        Low,
+        High,
+    }
+
+    impl core::ops::Not for Level {
+        type Output = Self;
+
+        fn not(self) -> Self {
+            match self {
+                Self::Low => Self::High,
+                Self::High => Self::Low,
+            }
+        }
+    }
+
+    impl From<bool> for Level {
+        fn from(val: bool) -> Self {
+            if val { Self::High } else { Self::Low }
+        }
+    }
+
+    impl From<Level> for bool {
+        fn from(level: Level) -> bool {
+            level == Level::High
+        }
+    }
+
+    /// See `esp_hal::gpio::Pull`.
+    #[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+    pub enum Pull {
+        None,
+        Up,
+        Down,
+    }
+
+    /// See `esp_hal::gpio::OutputConfig`.
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+    pub struct OutputConfig;
+
+    /// See `esp_hal::gpio::OutputPin`. The host has no pins, so nothing
+    /// implements it.
+    pub trait OutputPin {}
+
+    /// See `esp_hal::gpio::Output`.
+    #[derive(Debug)]
+    pub struct Output<'d>(Never, PhantomData<&'d ()>);
+
+    impl<'d> Output<'d> {
+        pub fn new(_pin: impl OutputPin + 'd, _initial: Level, _config: OutputConfig) -> Self {
+            unreachable!("no OutputPin on the host")
+        }
+
+        pub fn set_high(&mut self) {
+            match self.0 {}
+        }
+
+        pub fn set_low(&mut self) {
+            match self.0 {}
+        }
+
+        pub fn set_level(&mut self, _level: Level) {
+            match self.0 {}
+        }
+
+        pub fn toggle(&mut self) {
+            match self.0 {}
+        }
+    }
+
+    /// See `esp_hal::gpio::Flex`.
+    #[derive(Debug)]
+    pub struct Flex<'d>(Never, PhantomData<&'d ()>);
+
+    impl Flex<'_> {
+        pub fn set_as_input(&mut self, _pull: Pull) {
+            match self.0 {}
+        }
+
+        pub fn set_as_output(&mut self) {
+            match self.0 {}
+        }
+
+        pub fn set_high(&mut self) {
+            match self.0 {}
+        }
+
+        pub fn set_low(&mut self) {
+            match self.0 {}
+        }
+
+        pub fn is_high(&self) -> bool {
+            match self.0 {}
+        }
+    }
+}
+
+pub mod delay {
+    //! See `esp_hal::delay`, sleeping the test thread.
+
+    extern crate std;
+
+    /// See `esp_hal::delay::Delay`.
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct Delay;
+
+    impl Delay {
+        pub const fn new() -> Self {
+            Self
+        }
+
+        pub fn delay_millis(&self, ms: u32) {
+            std::thread::sleep(std::time::Duration::from_millis(ms.into()));
+        }
+
+        pub fn delay_micros(&self, us: u32) {
+            std::thread::sleep(std::time::Duration::from_micros(us.into()));
+        }
+
+        pub fn delay_nanos(&self, ns: u32) {
+            std::thread::sleep(std::time::Duration::from_nanos(ns.into()));
+        }
+    }
+
+    impl embedded_hal::delay::DelayNs for Delay {
+        fn delay_ns(&mut self, ns: u32) {
+            self.delay_nanos(ns);
+        }
+    }
+}
+
+pub mod spi {
+    //! See `esp_hal::spi`.
+
+    /// See `esp_hal::spi::DataMode`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum DataMode {
+        Single,
+    }
+
+    /// See `esp_hal::spi::Error`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum Error {
+        Unsupported,
+    }
+
+    pub mod master {
+        //! See `esp_hal::spi::master`.
+
+        use super::{super::*, DataMode, Error};
+
+        /// See `esp_hal::spi::master::Command`.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum Command {
+            None,
+            _9Bit(u16, DataMode),
+        }
+
+        /// See `esp_hal::spi::master::Address`.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum Address {
+            None,
+        }
+
+        /// See `esp_hal::spi::master::Spi`.
+        #[derive(Debug)]
+        pub struct Spi<'d, Dm: DriverMode>(Never, PhantomData<(&'d (), Dm)>);
+
+        impl<Dm: DriverMode> Spi<'_, Dm> {
+            pub fn half_duplex_write(
+                &mut self,
+                _data_mode: DataMode,
+                _cmd: Command,
+                _address: Address,
+                _dummy: u8,
+                _buffer: &[u8],
+            ) -> Result<(), Error> {
+                match self.0 {}
+            }
+
+            pub fn half_duplex_read(
+                &mut self,
+                _data_mode: DataMode,
+                _cmd: Command,
+                _address: Address,
+                _dummy: u8,
+                _buffer: &mut [u8],
+            ) -> Result<(), Error> {
+                match self.0 {}
+            }
+        }
+    }
+}
+
+pub mod dma {
+    //! See `esp_hal::dma`. Descriptors are plain memory, so rings of them
+    //! can be built and walked on the host.
+
+    use core::fmt::{self, Debug, Formatter};
+
+    use super::*;
+
+    /// See `esp_hal::dma::DmaBufError`.
+    #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+    pub enum DmaBufError {
+        BufferTooSmall,
+        InsufficientDescriptors,
+        UnsupportedMemoryRegion,
+        InvalidAlignment(DmaAlignmentError),
+        InvalidChunkSize,
+    }
+
+    /// See `esp_hal::dma::DmaAlignmentError`.
+    #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+    pub enum DmaAlignmentError {
+        Address,
+        Size,
+    }
+
+    impl From<DmaAlignmentError> for DmaBufError {
+        fn from(err: DmaAlignmentError) -> Self {
+            DmaBufError::InvalidAlignment(err)
+        }
+    }
+
+    /// See `esp_hal::dma::DmaError`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum DmaError {
+        InvalidAlignment(DmaAlignmentError),
+        OutOfDescriptors,
+        DescriptorError,
+        Overflow,
+        BufferTooSmall,
+        UnsupportedMemoryRegion,
+        InvalidChunkSize,
+        Late,
+    }
+
+    impl From<DmaBufError> for DmaError {
+        fn from(error: DmaBufError) -> Self {
+            match error {
+                DmaBufError::InsufficientDescriptors => DmaError::OutOfDescriptors,
+                DmaBufError::UnsupportedMemoryRegion => DmaError::UnsupportedMemoryRegion,
+                DmaBufError::InvalidAlignment(err) => DmaError::InvalidAlignment(err),
+                DmaBufError::InvalidChunkSize => DmaError::InvalidChunkSize,
+                DmaBufError::BufferTooSmall => DmaError::BufferTooSmall,
+            }
+        }
+    }
+
+    /// See `esp_hal::dma::ExternalBurstConfig`.
+    #[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+    pub enum ExternalBurstConfig {
+        #[default]
+        Size16 = 16,
+        Size32 = 32,
+        Size64 = 64,
+    }
+
+    /// See `esp_hal::dma::InternalBurstConfig`.
+    #[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+    pub enum InternalBurstConfig {
+        #[default]
+        Disabled,
+        Enabled,
+    }
+
+    /// See `esp_hal::dma::BurstConfig`.
+    #[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+    pub struct BurstConfig {
+        pub external_memory: ExternalBurstConfig,
+        pub internal_memory: InternalBurstConfig,
+    }
+
+    impl BurstConfig {
+        pub const DEFAULT: Self = Self {
+            external_memory: ExternalBurstConfig::Size16,
+            internal_memory: InternalBurstConfig::Disabled,
+        };
+
+        /// As on the ESP32-S3: external memory alignment always counts, and
+        /// internal bursts need words.
+        pub const fn min_compatible_alignment(self) -> usize {
+            let internal = match self.internal_memory {
+                InternalBurstConfig::Disabled => 1,
+                InternalBurstConfig::Enabled => 4,
+            };
+            let external = self.external_memory as usize;
+            if internal > external {
+                internal
+            } else {
+                external
+            }
+        }
+
+        pub const fn max_compatible_chunk_size(self) -> usize {
+            4096 - self.min_compatible_alignment()
+        }
+    }
+
+    impl From<InternalBurstConfig> for BurstConfig {
+        fn from(internal_memory: InternalBurstConfig) -> Self {
+            Self {
+                internal_memory,
+                ..Self::DEFAULT
+            }
+        }
+    }
+
+    impl From<ExternalBurstConfig> for BurstConfig {
+        fn from(external_memory: ExternalBurstConfig) -> Self {
+            Self {
+                external_memory,
+                ..Self::DEFAULT
+            }
+        }
+    }
+
+    /// See `esp_hal::dma::TransferDirection`.
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum TransferDirection {
+        In,
+        Out,
+    }
+
+    /// See `esp_hal::dma::Preparation`.
+    #[derive(PartialEq, Eq, Debug)]
+    pub struct Preparation {
+        pub start: *mut DmaDescriptor,
+        pub direction: TransferDirection,
+        pub accesses_psram: bool,
+        pub burst_transfer: BurstConfig,
+        pub check_owner: Option<bool>,
+        pub auto_write_back: bool,
+    }
+
+    /// See `esp_hal::dma::DmaTxBuffer`.
+    ///
+    /// # Safety
+    ///
+    /// As for esp-hal's.
+    pub unsafe trait DmaTxBuffer {
+        type View;
+
+        fn prepare(&mut self) -> Preparation;
+
+        fn into_view(self) -> Self::View;
+
+        fn from_view(view: Self::View) -> Self;
+    }
+
+    /// See `esp_hal::dma::Owner`.
+    #[derive(PartialEq, PartialOrd)]
+    pub enum Owner {
+        Cpu = 0,
+        Dma = 1,
+    }
+
+    impl From<u32> for Owner {
+        fn from(value: u32) -> Self {
+            match value {
+                0 => Owner::Cpu,
+                _ => Owner::Dma,
+            }
+        }
+    }
+
+    /// See `esp_hal::dma::DmaDescriptorFlags`, with the same bit layout.
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct DmaDescriptorFlags(pub u32);
+
+    impl DmaDescriptorFlags {
+        const LENGTH_SHIFT: u32 = 12;
+        const OWNER: u32 = 1 << 31;
+        const SIZE: u32 = 0xFFF;
+        const SUC_EOF: u32 = 1 << 30;
+
+        pub fn size(&self) -> u16 {
+            (self.0 & Self::SIZE) as u16
+        }
+
+        pub fn set_size(&mut self, size: u16) {
+            self.0 = self.0 & !Self::SIZE | size as u32 & Self::SIZE;
+        }
+
+        pub fn length(&self) -> u16 {
+            ((self.0 >> Self::LENGTH_SHIFT) & Self::SIZE) as u16
+        }
+
+        pub fn set_length(&mut self, length: u16) {
+            self.0 = (self.0 & !(Self::SIZE << Self::LENGTH_SHIFT))
+                | ((length as u32 & Self::SIZE) << Self::LENGTH_SHIFT);
+        }
+
+        pub fn suc_eof(&self) -> bool {
+            self.0 & Self::SUC_EOF != 0
+        }
+
+        pub fn set_suc_eof(&mut self, suc_eof: bool) {
+            self.set_bit(Self::SUC_EOF, suc_eof);
+        }
+
+        pub fn owner(&self) -> bool {
+            self.0 & Self::OWNER != 0
+        }
+
+        pub fn set_owner(&mut self, owner: bool) {
+            self.set_bit(Self::OWNER, owner);
+        }
+
+        fn set_bit(&mut self, bit: u32, set: bool) {
+            if set {
+                self.0 |= bit;
+            } else {
+                self.0 &= !bit;
+            }
+        }
+    }
+
+    impl Debug for DmaDescriptorFlags {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            f.debug_struct("DmaDescriptorFlags")
+                .field("size", &self.size())
+                .field("length", &self.length())
+                .field("suc_eof", &self.suc_eof())
+                .field("owner", &(if self.owner() { "DMA" } else { "CPU" }))
+                .finish()
+        }
+    }
+
+    /// See `esp_hal::dma::DmaDescriptor`.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    #[repr(C)]
+    pub struct DmaDescriptor {
+        pub flags: DmaDescriptorFlags,
+        pub buffer: *mut u8,
+        pub next: *mut DmaDescriptor,
+    }
+
+    unsafe impl Send for DmaDescriptor {}
+
+    impl DmaDescriptor {
+        pub const EMPTY: Self = Self {
+            flags: DmaDescriptorFlags(0),
+            buffer: core::ptr::null_mut(),
+            next: core::ptr::null_mut(),
+        };
+
+        pub fn reset_for_tx(&mut self, set_eof: bool) {
+            self.set_owner(Owner::Dma);
+            self.set_suc_eof(set_eof);
+        }
+
+        pub fn set_size(&mut self, len: usize) {
+            self.flags.set_size(len as u16)
+        }
+
+        pub fn set_length(&mut self, len: usize) {
+            self.flags.set_length(len as u16)
+        }
+
+        pub fn size(&self) -> usize {
+            self.flags.size() as usize
+        }
+
+        #[allow(clippy::len_without_is_empty)]
+        pub fn len(&self) -> usize {
+            self.flags.length() as usize
+        }
+
+        pub fn set_suc_eof(&mut self, suc_eof: bool) {
+            self.flags.set_suc_eof(suc_eof)
+        }
+
+        pub fn set_owner(&mut self, owner: Owner) {
+            self.flags.set_owner(owner == Owner::Dma)
+        }
+
+        pub fn owner(&self) -> Owner {
+            if self.flags.owner() {
+                Owner::Dma
+            } else {
+                Owner::Cpu
+            }
+        }
+    }
+
+    /// See `esp_hal::dma::Mem2Mem`.
+    #[derive(Debug)]
+    pub struct Mem2Mem<'d, Dm: DriverMode>(Never, PhantomData<(&'d (), Dm)>);
+
+    impl<Dm: DriverMode> Mem2Mem<'_, Dm> {
+        pub fn start_transfer<'t, TXBUF, RXBUF>(
+            &mut self,
+            _rx_buffer: &'t mut RXBUF,
+            _tx_buffer: &'t TXBUF,
+        ) -> Result<DmaTransferRx<'_, Self>, DmaError> {
+            match self.0 {}
+        }
+    }
+
+    /// See `esp_hal::dma::DmaTransferRx`.
+    #[derive(Debug)]
+    pub struct DmaTransferRx<'a, I>(Never, PhantomData<&'a mut I>);
+
+    impl<I> DmaTransferRx<'_, I> {
+        pub fn wait(self) -> Result<(), DmaError> {
+            match self.0 {}
+        }
+
+        pub fn is_done(&mut self) -> bool {
+            match self.0 {}
+        }
+    }
+}
+
+pub mod lcd_cam {
+    //! See `esp_hal::lcd_cam`.
+
+    /// See `esp_hal::lcd_cam::BitOrder`.
+    #[derive(Debug, Clone, Copy, PartialEq, Default)]
+    pub enum BitOrder {
+        #[default]
+        Native   = 0,
+        Inverted = 1,
+    }
+
+    /// See `esp_hal::lcd_cam::ByteOrder`.
+    #[derive(Debug, Clone, Copy, PartialEq, Default)]
+    pub enum ByteOrder {
+        #[default]
+        Native   = 0,
+        Inverted = 1,
+    }
+
+    pub mod lcd {
+        //! See `esp_hal::lcd_cam::lcd`.
+
+        /// See `esp_hal::lcd_cam::lcd::ClockMode`.
+        #[derive(Debug, Clone, Copy, PartialEq, Default)]
+        pub struct ClockMode {
+            pub polarity: Polarity,
+            pub phase: Phase,
+        }
+
+        /// See `esp_hal::lcd_cam::lcd::Polarity`.
+        #[derive(Debug, Clone, Copy, PartialEq, Default)]
+        pub enum Polarity {
+            #[default]
+            IdleLow,
+            IdleHigh,
+        }
+
+        /// See `esp_hal::lcd_cam::lcd::Phase`.
+        #[derive(Debug, Clone, Copy, PartialEq, Default)]
+        pub enum Phase {
+            #[default]
+            ShiftLow,
+            ShiftHigh,
+        }
+
+        pub mod dpi {
+            //! See `esp_hal::lcd_cam::lcd::dpi`.
+
+            use core::{
+                marker::PhantomData,
+                ops::{Deref, DerefMut},
+            };
+
+            use super::super::{BitOrder, ByteOrder};
+            use crate::hal::{
+                DriverMode, Never,
+                dma::{DmaError, DmaTxBuffer},
+            };
+
+            /// See `esp_hal::lcd_cam::lcd::dpi::Dpi`.
+            #[derive(Debug)]
+            pub struct Dpi<'d, Dm: DriverMode>(Never, PhantomData<(&'d (), Dm)>);
+
+            impl<'d, Dm: DriverMode> Dpi<'d, Dm> {
+                #[allow(clippy::type_complexity)]
+                pub fn send<TX: DmaTxBuffer>(
+                    self,
+                    _next_frame_en: bool,
+                    _buf: TX,
+                ) -> Result<DpiTransfer<'d, TX, Dm>, (DmaError, Self, TX)> {
+                    match self.0 {}
+                }
+            }
+
+            /// See `esp_hal::lcd_cam::lcd::dpi::DpiTransfer`.
+            pub struct DpiTransfer<'d, BUF: DmaTxBuffer, Dm: DriverMode>(
+                Never,
+                PhantomData<(&'d (), BUF, Dm)>,
+            );
+
+            impl<'d, BUF: DmaTxBuffer, Dm: DriverMode> DpiTransfer<'d, BUF, Dm> {
+                pub fn is_done(&self) -> bool {
+                    match self.0 {}
+                }
+
+                pub fn stop(self) -> (Dpi<'d, Dm>, BUF) {
+                    match self.0 {}
+                }
+
+                pub fn wait(self) -> (Result<(), DmaError>, Dpi<'d, Dm>, BUF) {
+                    match self.0 {}
+                }
+            }
+
+            impl<BUF: DmaTxBuffer, Dm: DriverMode> Deref for DpiTransfer<'_, BUF, Dm> {
+                type Target = BUF::View;
+
+                fn deref(&self) -> &BUF::View {
+                    match self.0 {}
+                }
+            }
+
+            impl<BUF: DmaTxBuffer, Dm: DriverMode> DerefMut for DpiTransfer<'_, BUF, Dm> {
+                fn deref_mut(&mut self) -> &mut BUF::View {
+                    match self.0 {}
+                }
+            }
+
+            /// See `esp_hal::lcd_cam::lcd::dpi::Format`.
+            #[derive(Debug, Clone, Copy, PartialEq, Default)]
+            pub struct Format {
+                pub bit_order: BitOrder,
+                pub byte_order: ByteOrder,
+                pub enable_2byte_mode: bool,
+            }
+
+            /// See `esp_hal::lcd_cam::lcd::dpi::FrameTiming`.
+            #[derive(Debug, Clone, Copy, PartialEq, Default)]
+            pub struct FrameTiming {
+                pub horizontal_total_width: usize,
+                pub horizontal_blank_front_porch: usize,
+                pub horizontal_active_width: usize,
+                pub vertical_total_height: usize,
+                pub vertical_blank_front_porch: usize,
+                pub vertical_active_height: usize,
+                pub vsync_width: usize,
+                pub hsync_width: usize,
+                pub hsync_position: usize,
+            }
+        }
+    }
+}
+
+pub mod xtensa_lx {
+    //! See `esp_hal::xtensa_lx`.
+
+    pub mod timer {
+        //! See `esp_hal::xtensa_lx::timer`.
+
+        use crate::hal::time::Instant;
+
+        /// The cycle counter of a 240MHz core, derived from the host clock.
+        pub fn get_cycle_count() -> u32 {
+            (Instant::now().duration_since_epoch().as_micros() * 240) as u32
+        }
+    }
+}
@@ -0,0 +1,43 @@
+//! Checking the heap before allocating, so a heap that's too small is
+//! reported instead of aborting inside the allocator.
+
+use core::fmt;
+
+/// An allocation that doesn't fit in the free heap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocError {
+    /// What wanted the memory.
+    pub component: &'static str,
+    pub wanted: usize,
+    pub free: usize,
+}
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} needs {} bytes of heap but only {} are free, increase heap_allocator! to at least \
+             {} bytes",
+            self.component,
+            self.wanted,
+            self.free,
+            esp_alloc::HEAP.used() + self.wanted
+        )
+    }
+}
+
+/// Fails if `bytes` for `component` don't fit in the free heap.
+///
+/// Fragmentation can still make an allocation that passes this fail, so
+/// leave some slack when sizing the heap.
+pub fn check(component: &'static str, bytes: usize) -> Result<(), AllocError> {
+    let free = esp_alloc::HEAP.free();
+    if bytes > free {
+        return Err(AllocError {
+            component,
+            wanted: bytes,
+            free,
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,43 @@
+//! The helpers this reproduction grew around the LCD_CAM DPI driver, as a
+//! library so other firmware can use them. `main.rs` is the reproduction
+//! itself.
+
+#![cfg_attr(not(test), no_std)]
+#![allow(clippy::unusual_byte_groupings)]
+// Like `Dpi::send`, failed restarts hand the `Dpi` and buffers back.
+#![allow(clippy::result_large_err)]
+
+extern crate alloc;
+
+#[macro_use]
+pub mod logging;
+
+#[cfg(all(feature = "bench", target_arch = "xtensa"))]
+pub mod bench;
+pub mod bmp;
+pub mod boards;
+pub mod color;
+#[cfg(target_arch = "xtensa")]
+pub mod diag;
+pub mod display;
+pub mod dma;
+pub mod flush;
+pub mod frame_queue;
+pub mod framebuffer;
+pub mod geometry;
+pub mod hal;
+#[cfg(target_arch = "xtensa")]
+pub mod heap;
+pub mod looped;
+pub mod overlay;
+pub mod palette;
+pub mod persist;
+#[cfg(feature = "preview")]
+pub mod preview;
+pub mod psram;
+pub mod redraw;
+pub mod render;
+#[cfg(target_arch = "xtensa")]
+pub mod soak;
+pub mod stats;
+pub mod stream;
@@ -0,0 +1,71 @@
+//! Logging that compiles to nothing with the `no-log` feature.
+//!
+//! The crate logs through these macros instead of `log`'s, so a `no-log`
+//! build drops the formatting code along with the calls. Arguments are still
+//! type checked either way.
+
+use crate::hal::time::{Duration, Instant};
+
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if cfg!(not(feature = "no-log")) {
+            log::info!($($arg)*);
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)*) => {
+        if cfg!(not(feature = "no-log")) {
+            log::warn!($($arg)*);
+        }
+    };
+}
+
+/// Lets a log through at most once per interval, for logs on paths that run
+/// every frame or line.
+pub struct RateLimit {
+    interval: Duration,
+    last: Option<Instant>,
+}
+
+impl RateLimit {
+    pub const fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last: None,
+        }
+    }
+
+    /// Whether to log now. Only a `true` starts a new interval.
+    pub fn ready(&mut self) -> bool {
+        let now = Instant::now();
+        if self.last.is_some_and(|last| now - last < self.interval) {
+            return false;
+        }
+        self.last = Some(now);
+        true
+    }
+}
+
+#[cfg(target_arch = "xtensa")]
+unsafe extern "C" {
+    // Defined by `size.x`.
+    static _text_size: u8;
+}
+
+/// Size of the `.text` section in bytes, for comparing builds.
+#[cfg(target_arch = "xtensa")]
+pub fn text_size() -> usize {
+    // The symbol's address is the value, there is nothing behind it.
+    unsafe { &raw const _text_size as usize }
+}
+
+/// Logs [text_size]. A `no-log` build stays silent like everywhere else,
+/// read its `.text` from the ELF instead.
+#[cfg(target_arch = "xtensa")]
+pub fn report_text_size() {
+    info!(".text: {} bytes", text_size());
+}
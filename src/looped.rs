@@ -0,0 +1,468 @@
+//! Looping a static frame, e.g. a splash screen stored in flash.
+
+use crate::{
+    color::{self, BRIGHTNESS_LEVELS, DimMode, WireBytes, wire_format},
+    display::{
+        clock::is_clock_gated,
+        vsync::{self, VsyncFlag},
+    },
+    dma::{DmaTxStreamBuf, is_slice_in_dram, is_slice_in_psram},
+    hal::{
+        DriverMode,
+        dma::{
+            BurstConfig, DmaAlignmentError, DmaBufError, DmaDescriptor, DmaError, DmaTxBuffer,
+            Owner, Preparation, TransferDirection,
+        },
+        lcd_cam::lcd::dpi::{Dpi, DpiTransfer},
+    },
+    psram::{EXTERNAL_ALIGNMENT, cache_writeback},
+};
+
+/// A single frame the DMA sends over and over without CPU involvement.
+pub struct LoopedFrame {
+    descriptors: &'static mut [DmaDescriptor],
+    frame: &'static [u8],
+    accesses_psram: bool,
+}
+
+impl LoopedFrame {
+    /// Creates a [LoopedFrame] from a frame anywhere in memory.
+    ///
+    /// Frames in internal RAM or PSRAM are sent in place, PSRAM ones have to
+    /// be aligned to 16 bytes and are written back from the cache first.
+    /// Anything else, in particular `static` data the linker put in flash,
+    /// which the GDMA can't read, is copied once into `scratch`. That needs
+    /// `scratch` to be in internal RAM or PSRAM and at least as long as the
+    /// frame.
+    pub fn new(
+        descriptors: &'static mut [DmaDescriptor],
+        frame: &'static [u8],
+        scratch: Option<&'static mut [u8]>,
+    ) -> Result<Self, DmaBufError> {
+        if !is_slice_in_dram(descriptors) {
+            return Err(DmaBufError::UnsupportedMemoryRegion);
+        }
+
+        let (frame, accesses_psram) = if is_slice_in_dram(frame) {
+            (frame, false)
+        } else if is_slice_in_psram(frame) {
+            if frame.as_ptr() as usize % EXTERNAL_ALIGNMENT != 0 {
+                return Err(DmaBufError::InvalidAlignment(DmaAlignmentError::Address));
+            }
+            if frame.len() % EXTERNAL_ALIGNMENT != 0 {
+                return Err(DmaBufError::InvalidAlignment(DmaAlignmentError::Size));
+            }
+            cache_writeback(frame);
+            (frame, true)
+        } else {
+            let Some(scratch) = scratch else {
+                return Err(DmaBufError::UnsupportedMemoryRegion);
+            };
+            let accesses_psram = is_slice_in_psram(scratch);
+            if !accesses_psram && !is_slice_in_dram(scratch) {
+                return Err(DmaBufError::UnsupportedMemoryRegion);
+            }
+
+            let scratch = scratch
+                .get_mut(..frame.len())
+                .ok_or(DmaBufError::BufferTooSmall)?;
+            scratch.copy_from_slice(frame);
+            if accesses_psram {
+                cache_writeback(scratch);
+            }
+
+            warn!(
+                "Frame at {:p} is not DMA-capable, copied {} bytes to {:p}",
+                frame.as_ptr(),
+                frame.len(),
+                scratch.as_ptr()
+            );
+
+            (&*scratch, accesses_psram)
+        };
+
+        Self::from_parts(descriptors, frame, accesses_psram)
+    }
+
+    /// Wraps a frame already known to be DMA-capable.
+    pub(crate) fn from_parts(
+        descriptors: &'static mut [DmaDescriptor],
+        frame: &'static [u8],
+        accesses_psram: bool,
+    ) -> Result<Self, DmaBufError> {
+        let max_chunk_size = BurstConfig::default().max_compatible_chunk_size();
+        if descriptors.len() < frame.len().div_ceil(max_chunk_size) {
+            return Err(DmaBufError::InsufficientDescriptors);
+        }
+
+        Ok(Self {
+            descriptors,
+            frame,
+            accesses_psram,
+        })
+    }
+
+    /// Consume the buf, returning the descriptors.
+    pub fn split(self) -> &'static mut [DmaDescriptor] {
+        self.descriptors
+    }
+}
+
+unsafe impl DmaTxBuffer for LoopedFrame {
+    type View = LoopedFrame;
+
+    fn prepare(&mut self) -> Preparation {
+        let max_chunk_size = BurstConfig::default().max_compatible_chunk_size();
+        let chain = &mut self.descriptors[..self.frame.len().div_ceil(max_chunk_size)];
+
+        // Link the chain into a loop, with EOF at the end of the frame.
+        let head: *mut _ = &mut chain[0];
+        let mut next = head;
+        for (chunk, desc) in self.frame.chunks(max_chunk_size).zip(chain).rev() {
+            desc.buffer = chunk.as_ptr() as *mut u8;
+            desc.set_length(chunk.len());
+            desc.set_size(chunk.len());
+            desc.set_suc_eof(next == head);
+            desc.set_owner(Owner::Dma);
+            desc.next = next;
+            next = desc;
+        }
+
+        debug_assert!(
+            {
+                let len = self.frame.len().div_ceil(max_chunk_size);
+                let mut desc = head;
+                for _ in 0..len {
+                    desc = unsafe { (*desc).next };
+                }
+                desc == head
+            },
+            "descriptor chain doesn't loop back to its head"
+        );
+
+        Preparation {
+            start: head,
+            direction: TransferDirection::Out,
+            accesses_psram: self.accesses_psram,
+            // The DMA hands descriptors back, and then comes around to them
+            // again.
+            check_owner: Some(false),
+            burst_transfer: BurstConfig::default(),
+            auto_write_back: false,
+        }
+    }
+
+    fn into_view(self) -> Self::View {
+        self
+    }
+
+    fn from_view(view: Self::View) -> Self {
+        view
+    }
+}
+
+/// A frame of a single color from a short pattern the DMA reads over and
+/// over, so a full-screen color costs [SolidFrame::PATTERN_LEN] bytes and
+/// two descriptors instead of a frame buffer, and no CPU per frame.
+///
+/// The two descriptors link to each other and both point at the pattern.
+/// Every byte pair is the same pixel, so the loop doesn't have to line up
+/// with frames; the LCD's timing cuts lines and frames out of the stream.
+/// There is no EOF, the stream runs until `DpiTransfer::stop`.
+pub struct SolidFrame {
+    descriptors: &'static mut [DmaDescriptor; 2],
+    pattern: &'static mut [u8; SolidFrame::PATTERN_LEN],
+}
+
+impl SolidFrame {
+    /// Bytes of the repeated pattern. Long enough that the DMA spends its
+    /// time on data rather than fetching descriptors.
+    pub const PATTERN_LEN: usize = 256;
+
+    /// Fills `pattern` with `color` in the current
+    /// [WireFormat](crate::color::WireFormat). Both have to be in internal
+    /// DRAM.
+    pub fn new(
+        descriptors: &'static mut [DmaDescriptor; 2],
+        pattern: &'static mut [u8; Self::PATTERN_LEN],
+        color: u16,
+    ) -> Result<Self, DmaBufError> {
+        if !is_slice_in_dram(descriptors) || !is_slice_in_dram(pattern) {
+            return Err(DmaBufError::UnsupportedMemoryRegion);
+        }
+
+        let mut frame = Self {
+            descriptors,
+            pattern,
+        };
+        frame.set_color(color);
+        Ok(frame)
+    }
+
+    /// Changes the color, also while the transfer runs, e.g. once per frame
+    /// for a fade with [dim565](crate::color::dim565). The frame being sent
+    /// when this is called may come out in both colors.
+    pub fn set_color(&mut self, color: u16) {
+        self.fill(wire_format().encode(color));
+    }
+
+    /// Fades from `color` to black, one [BRIGHTNESS_LEVELS] step per VSYNC
+    /// on `flag`, and blocks until it's black. Call it on a running
+    /// transfer, which derefs to the frame.
+    pub fn fade_to_black(&mut self, color: u16, mode: DimMode<'_>, flag: &mut impl VsyncFlag) {
+        let bytes = wire_format().encode(color);
+        for level in (0..BRIGHTNESS_LEVELS as u8).rev() {
+            // Dimmed on the side, so the DMA never reads a half-dimmed pattern.
+            let mut dimmed = [0; 2];
+            color::fade_to_black(&bytes, &mut dimmed, level, mode);
+            self.fill(dimmed);
+            vsync::wait_frames(flag, 1);
+        }
+    }
+
+    fn fill(&mut self, pixel: [u8; 2]) {
+        for chunk in self.pattern.chunks_exact_mut(2) {
+            chunk.copy_from_slice(&pixel);
+        }
+    }
+
+    /// Consume the frame, returning the descriptors and pattern.
+    pub fn split(
+        self,
+    ) -> (
+        &'static mut [DmaDescriptor; 2],
+        &'static mut [u8; Self::PATTERN_LEN],
+    ) {
+        (self.descriptors, self.pattern)
+    }
+}
+
+unsafe impl DmaTxBuffer for SolidFrame {
+    type View = SolidFrame;
+
+    fn prepare(&mut self) -> Preparation {
+        let [first, second] = &mut *self.descriptors;
+        let (first_ptr, second_ptr): (*mut _, *mut _) = (&mut *first, &mut *second);
+
+        for (desc, next) in [(first, second_ptr), (second, first_ptr)] {
+            desc.buffer = self.pattern.as_mut_ptr();
+            desc.set_length(Self::PATTERN_LEN);
+            desc.set_size(Self::PATTERN_LEN);
+            desc.set_suc_eof(false);
+            desc.set_owner(Owner::Dma);
+            desc.next = next;
+        }
+
+        Preparation {
+            start: first_ptr,
+            direction: TransferDirection::Out,
+            accesses_psram: false,
+            // Same as a looped frame, the DMA comes back around to both.
+            check_owner: Some(false),
+            burst_transfer: BurstConfig::default(),
+            auto_write_back: false,
+        }
+    }
+
+    fn into_view(self) -> Self::View {
+        self
+    }
+
+    fn from_view(view: Self::View) -> Self {
+        view
+    }
+}
+
+/// Sends `frame` in a loop on `dpi`, copying it to `scratch` first if it's
+/// in flash. Frames in internal RAM or PSRAM don't need `scratch`. See
+/// [LoopedFrame::new].
+pub fn send_looped_from_flash<'d, Dm: DriverMode>(
+    dpi: Dpi<'d, Dm>,
+    descriptors: &'static mut [DmaDescriptor],
+    frame: &'static [u8],
+    scratch: Option<&'static mut [u8]>,
+) -> Result<DpiTransfer<'d, LoopedFrame, Dm>, (DmaError, Dpi<'d, Dm>)> {
+    let looped = match LoopedFrame::new(descriptors, frame, scratch) {
+        Ok(looped) => looped,
+        Err(err) => return Err((err.into(), dpi)),
+    };
+
+    debug_assert!(!is_clock_gated(), "LCD_CAM clock gated before send");
+    dpi.send(true, looped)
+        .map_err(|(err, dpi, _looped)| (err, dpi))
+}
+
+/// Fills `buf` with `frame`, closes it into a loop with
+/// [DmaTxStreamBuf::enable_loop] and only then starts `dpi`.
+///
+/// The started transfer never needs the CPU, so nothing the caller does
+/// afterwards, including the `delay_millis(10)` after `send` that hangs the
+/// push loop in `main`, can underrun it. The price is that the frame is
+/// static: the buf has to be empty and hold all of `frame`, which has to be
+/// exactly one frame, and nothing may be pushed while it loops.
+pub fn start_held<'d, Dm: DriverMode>(
+    dpi: Dpi<'d, Dm>,
+    mut buf: DmaTxStreamBuf,
+    frame: &[u8],
+) -> Result<DpiTransfer<'d, DmaTxStreamBuf, Dm>, (DmaError, Dpi<'d, Dm>, DmaTxStreamBuf)> {
+    if let Err(err) = fill_and_loop(&mut buf, frame) {
+        return Err((err.into(), dpi, buf));
+    }
+
+    debug_assert!(!is_clock_gated(), "LCD_CAM clock gated before send");
+    dpi.send(true, buf)
+}
+
+/// The buf side of [start_held]: pushes all of `frame` and closes it into a
+/// loop.
+fn fill_and_loop(buf: &mut DmaTxStreamBuf, frame: &[u8]) -> Result<(), DmaBufError> {
+    if buf.available_bytes() < frame.len() {
+        return Err(DmaBufError::BufferTooSmall);
+    }
+
+    buf.push_wire(WireBytes(frame));
+    buf.enable_loop();
+    Ok(())
+}
+
+/// Sends `looped` on `dpi` for exactly `n` refreshes, then stops the transfer
+/// and hands both back.
+///
+/// Blocks for the whole `n` frames. Frames are counted on the LCD VSYNC
+/// interrupt flag, which is polled; nothing else should be listening for or
+/// clearing it in the meantime.
+#[cfg(target_arch = "xtensa")]
+pub fn show_for_frames<'d, Dm: DriverMode>(
+    dpi: Dpi<'d, Dm>,
+    looped: LoopedFrame,
+    n: u32,
+) -> Result<(Dpi<'d, Dm>, LoopedFrame), (DmaError, Dpi<'d, Dm>, LoopedFrame)> {
+    debug_assert!(!is_clock_gated(), "LCD_CAM clock gated before send");
+    vsync::clear();
+    let transfer = dpi.send(true, looped)?;
+
+    vsync::wait_frames(&mut vsync::LcdVsync, n);
+
+    Ok(transfer.stop())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+
+    const FRAME_BYTES: usize = 480 * 480 * 2;
+
+    /// A [SolidFrame] on leaked host memory, skipping the DRAM check.
+    fn solid(color: u16) -> SolidFrame {
+        let mut frame = SolidFrame {
+            descriptors: Box::leak(Box::new([DmaDescriptor::EMPTY; 2])),
+            pattern: Box::leak(Box::new([0; SolidFrame::PATTERN_LEN])),
+        };
+        frame.set_color(color);
+        frame
+    }
+
+    /// Reads `len` bytes the way the DMA would, following the descriptors
+    /// from `start`.
+    fn capture(start: *mut DmaDescriptor, len: usize) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(len);
+        let mut descriptor = start;
+        while bytes.len() < len {
+            let d = unsafe { &*descriptor };
+            assert!(d.owner() == Owner::Dma, "descriptor not handed to the DMA");
+            assert!(!d.flags.suc_eof(), "solid frame stream ends");
+            bytes.extend_from_slice(unsafe { core::slice::from_raw_parts(d.buffer, d.len()) });
+            descriptor = d.next;
+        }
+        bytes.truncate(len);
+        bytes
+    }
+
+    /// Counts the frames waited for.
+    struct CountingVsync(u32);
+
+    impl VsyncFlag for CountingVsync {
+        fn is_pending(&mut self) -> bool {
+            true
+        }
+
+        fn clear(&mut self) {
+            self.0 += 1;
+        }
+    }
+
+    #[test]
+    fn captured_frame_is_byte_exact() {
+        let color = 0b10101_110011_01110;
+        let mut frame = solid(color);
+        let start = frame.prepare().start;
+
+        let pixel = wire_format().encode(color);
+        let expected: Vec<u8> = pixel.iter().copied().cycle().take(FRAME_BYTES).collect();
+        assert!(capture(start, FRAME_BYTES) == expected);
+    }
+
+    #[test]
+    fn set_color_changes_the_running_stream() {
+        let mut frame = solid(0xFFFF);
+        let start = frame.prepare().start;
+        frame.set_color(0x07E0);
+
+        let pixel = wire_format().encode(0x07E0);
+        let captured = capture(start, 2 * SolidFrame::PATTERN_LEN);
+        assert!(captured.chunks_exact(2).all(|p| p == pixel));
+    }
+
+    #[test]
+    fn fade_steps_once_per_frame_down_to_black() {
+        let mut frame = solid(0xFFFF);
+        let start = frame.prepare().start;
+        let mut vsync = CountingVsync(0);
+
+        frame.fade_to_black(0xFFFF, DimMode::Fast, &mut vsync);
+
+        assert_eq!(vsync.0, BRIGHTNESS_LEVELS as u32);
+        let black = wire_format().encode(0);
+        let captured = capture(start, 2 * SolidFrame::PATTERN_LEN);
+        assert!(captured.chunks_exact(2).all(|p| p == black));
+    }
+
+    #[test]
+    fn held_start_survives_a_delay_before_the_first_push() {
+        // Three descriptors.
+        const LEN: usize = 10_000;
+        let descriptors = Box::leak(std::vec![DmaDescriptor::EMPTY; 4].into_boxed_slice());
+        let buffer = Box::leak(std::vec![0; LEN].into_boxed_slice());
+        let mut buf = DmaTxStreamBuf::new_unchecked(descriptors, buffer);
+        let frame: Vec<u8> = (0..LEN).map(|i| i as u8).collect();
+
+        assert!(matches!(
+            fill_and_loop(&mut buf, &[0; LEN + 1]),
+            Err(DmaBufError::BufferTooSmall)
+        ));
+        fill_and_loop(&mut buf, &frame).unwrap();
+        // What `send` does before enabling the LCD.
+        let start = buf.prepare().start;
+
+        // However long the caller delays, nothing is pushed, and the DMA keeps
+        // replaying the frame instead of running off the end of the ring.
+        let mut descriptor = start;
+        for repeat in 0..5 {
+            let mut bytes = Vec::new();
+            loop {
+                assert!(!descriptor.is_null(), "stream ends in repeat {repeat}");
+                let d = unsafe { &*descriptor };
+                assert!(d.owner() == Owner::Dma, "descriptor not handed to the DMA");
+                bytes.extend_from_slice(unsafe { core::slice::from_raw_parts(d.buffer, d.len()) });
+                descriptor = d.next;
+                if d.flags.suc_eof() {
+                    break;
+                }
+            }
+            assert!(bytes == frame, "repeat {repeat}");
+            assert_eq!(descriptor, start, "repeat {repeat}");
+        }
+    }
+}
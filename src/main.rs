@@ -1,33 +1,34 @@
-#![no_std]
-#![no_main]
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
 #![allow(clippy::unusual_byte_groupings)]
 
 extern crate alloc;
 
+#[cfg(not(test))]
 use esp_backtrace as _;
+use esp_dma_lcd_mre::{
+    boards::{self, Board},
+    color::{self, WireBytes, WireFormat},
+    display::st7701::{ManualSpi, St7701},
+    dma::DmaTxStreamBuf,
+    info, logging, persist, static_assert_dma_sizing,
+};
+#[cfg(not(test))]
+use esp_hal::xtensa_lx_rt::entry;
 use esp_hal::{
     clock::CpuClock,
     delay::Delay,
     dma::DmaDescriptor,
-    gpio::{Flex, Level, Output},
+    gpio::{AnyPin, Flex, Level, Output},
     lcd_cam::{
         lcd::{dpi::*, *},
         *,
     },
     time::Rate,
-    xtensa_lx_rt::entry,
 };
-use log::info;
+use esp_storage::FlashStorage;
 use static_cell::ConstStaticCell;
 
-mod display;
-mod dma;
-
-use crate::{
-    display::st7701::{ManualSpi, St7701},
-    dma::DmaTxStreamBuf,
-};
-
 const MAX_RED: u16 = (1 << 5) - 1;
 
 const RED: u16 = rgb(MAX_RED, 0, 0);
@@ -36,39 +37,66 @@ const fn rgb(r: u16, g: u16, b: u16) -> u16 {
     (r << 11) | (g << 5) | b
 }
 
-const V_RES: usize = 480;
-const H_RES: usize = 480;
+/// The board to run on, see [boards].
+const BOARD: Board = boards::MIAO_480X480;
+
+#[cfg(feature = "defmt")]
+defmt::timestamp!(
+    "{=u64:us}",
+    esp_hal::time::Instant::now()
+        .duration_since_epoch()
+        .as_micros()
+);
 
 static DESCRIPTORS: ConstStaticCell<[DmaDescriptor; 100]> =
     ConstStaticCell::new([DmaDescriptor::EMPTY; 100]);
 
 static BUFFER: ConstStaticCell<[u8; 100_000]> = ConstStaticCell::new([0; 100_000]);
 
-#[entry]
+static_assert_dma_sizing!(100_000, 100);
+
+#[cfg_attr(not(test), entry)]
 fn main() -> ! {
     esp_println::logger::init_logger_from_env();
+    logging::report_text_size();
+    #[cfg(feature = "bench")]
+    esp_dma_lcd_mre::bench::rgb888_to_565();
     esp_alloc::heap_allocator!(10 * 1024);
 
     let peripherals: esp_hal::peripherals::Peripherals =
         esp_hal::init(esp_hal::Config::default().with_cpu_clock(CpuClock::max()));
 
-    let rst = Output::new(peripherals.GPIO47, Level::High, Default::default());
-    let cs = Output::new(peripherals.GPIO21, Level::Low, Default::default());
-    let scl = Output::new(peripherals.GPIO14, Level::Low, Default::default());
-    let mut sda = Flex::new(peripherals.GPIO13);
+    let mut flash = FlashStorage::new();
+    let panel = persist::load(&mut flash, &BOARD.panel);
+
+    if let Err(err) = BOARD.validate_pins() {
+        panic!("Pin map of {}: {}", BOARD.name, err);
+    }
+
+    // The pins are taken by number from the board's pin map.
+    let pin = |n: u8| unsafe { AnyPin::steal(n) };
+
+    let rst = BOARD
+        .spi
+        .rst
+        .map(|n| Output::new(pin(n), Level::High, Default::default()));
+    let cs = Output::new(pin(BOARD.spi.cs), Level::Low, Default::default());
+    let scl = Output::new(pin(BOARD.spi.scl), Level::Low, Default::default());
+    let mut sda = Flex::new(pin(BOARD.spi.sda));
 
     sda.set_as_output();
 
-    let spi = ManualSpi { cs, sda, scl };
+    let spi = ManualSpi::new(cs, sda, scl);
 
     let mut st7701 = St7701::new(spi, rst);
     let mut delay = Delay::new();
 
-    info!("Initializing LCD");
+    info!("Initializing LCD on {}", BOARD.name);
 
     delay.delay_millis(50);
 
     st7701.init(&mut delay).unwrap();
+    st7701.set_vcom(panel.vcom).unwrap();
 
     info!("Initialized");
 
@@ -77,81 +105,46 @@ fn main() -> ! {
     let lcd_cam = LcdCam::new(peripherals.LCD_CAM);
     let channel = peripherals.DMA_CH0;
 
+    let format = Format {
+        enable_2byte_mode: true,
+        bit_order: BitOrder::Inverted,
+        ..Default::default()
+    };
+    color::set_wire_format(WireFormat::from_format(&format));
+
     let config = dpi::Config::default()
-        .with_frequency(Rate::from_mhz(12))
-        .with_clock_mode(ClockMode {
-            polarity: Polarity::IdleLow,
-            phase: Phase::ShiftHigh,
-        })
-        .with_format(Format {
-            enable_2byte_mode: true,
-            bit_order: BitOrder::Inverted,
-            ..Default::default()
-        })
-        .with_timing(FrameTiming {
-            horizontal_active_width: H_RES,
-            horizontal_total_width: 500,
-            horizontal_blank_front_porch: 10,
-
-            vertical_active_height: V_RES,
-            vertical_total_height: 493,
-            vertical_blank_front_porch: 2,
-
-            hsync_width: 10,
-            vsync_width: 10,
-
-            hsync_position: 0,
-        })
-        .with_vsync_idle_level(Level::High)
-        .with_hsync_idle_level(Level::High)
-        .with_de_idle_level(Level::Low)
+        .with_frequency(Rate::from_mhz(panel.pclk_mhz))
+        .with_clock_mode(panel.latch.clock_mode())
+        .with_format(format)
+        .with_timing(panel.timing)
+        .with_vsync_idle_level(panel.idle.vsync)
+        .with_hsync_idle_level(panel.idle.hsync)
+        .with_de_idle_level(panel.idle.de)
         .with_disable_black_region(false);
 
-    let dpi = Dpi::new(lcd_cam.lcd, channel, config)
-        .unwrap()
-        // Blue
-        .with_data0(peripherals.GPIO46)
-        .with_data1(peripherals.GPIO9)
-        .with_data2(peripherals.GPIO10)
-        .with_data3(peripherals.GPIO11)
-        .with_data4(peripherals.GPIO12)
-        // Green
-        .with_data5(peripherals.GPIO17)
-        .with_data6(peripherals.GPIO18)
-        .with_data7(peripherals.GPIO8)
-        .with_data8(peripherals.GPIO19)
-        .with_data9(peripherals.GPIO20)
-        .with_data10(peripherals.GPIO3)
-        // Red
-        .with_data11(peripherals.GPIO5)
-        .with_data12(peripherals.GPIO6)
-        .with_data13(peripherals.GPIO7)
-        .with_data14(peripherals.GPIO15)
-        .with_data15(peripherals.GPIO16)
-        // Control
-        .with_pclk(peripherals.GPIO40)
-        .with_hsync(peripherals.GPIO39)
-        .with_vsync(peripherals.GPIO38)
-        .with_de(peripherals.GPIO37);
+    let dpi = Dpi::new(lcd_cam.lcd, channel, config).unwrap();
+    let dpi = unsafe { BOARD.dpi.attach(dpi) };
 
     let mut dma_buf = DmaTxStreamBuf::new(DESCRIPTORS.take(), BUFFER.take()).unwrap();
 
+    // The bytes the hang was reported with, not `WireFormat::encode`'s.
     loop {
-        if dma_buf.push(&RED.to_be_bytes()) < 2 {
+        if dma_buf.push_wire(WireBytes(&RED.to_be_bytes())) < 2 {
             break;
         }
     }
 
-    let mut buffer = [0; 480 * 16];
+    // Eight lines, the 7680 bytes per push the hang was reported with.
+    let mut buffer = [0; BOARD.panel.geometry.bytes_per_line() * 8];
 
-    log::info!("Buffering");
+    info!("Buffering");
 
     for chunk in buffer.chunks_mut(2) {
         let color: u16 = 0b11111_000000_00000;
         chunk.copy_from_slice(&color.to_le_bytes());
     }
 
-    log::info!("Rendering");
+    info!("Rendering");
 
     let mut transfer = dpi.send(true, dma_buf).map_err(|e| e.0).unwrap();
 
@@ -159,6 +152,6 @@ fn main() -> ! {
     // esp_hal::delay::Delay::new().delay_millis(10);
 
     loop {
-        transfer.push(&buffer, false);
+        transfer.push_wire(WireBytes(&buffer), false);
     }
 }
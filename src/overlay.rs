@@ -0,0 +1,140 @@
+//! Burn-in overlay: uptime, frame, underrun and boot counters in the top
+//! left corner of the frame, readable from a photo of the panel.
+
+use core::fmt::Write;
+
+use heapless::String;
+
+use crate::{
+    flush::DirtyRows,
+    framebuffer::FrameBuffer,
+    hal::time::{Duration, Instant},
+};
+
+const GLYPH_WIDTH: usize = 3;
+const GLYPH_HEIGHT: usize = 5;
+/// Each font pixel is drawn as a `SCALE` x `SCALE` square.
+const SCALE: usize = 3;
+const ADVANCE: usize = (GLYPH_WIDTH + 1) * SCALE;
+const LINE_HEIGHT: usize = (GLYPH_HEIGHT + 1) * SCALE;
+
+const LINES: usize = 4;
+/// Fits `T 999:59:59`, six weeks of uptime.
+const COLUMNS: usize = 11;
+
+/// Width and height of the overlay in logical pixels.
+pub const SIZE: (usize, usize) = (COLUMNS * ADVANCE + SCALE, LINES * LINE_HEIGHT + SCALE);
+
+/// Rows of a 3x5 glyph, top first, leftmost pixel in bit 2. Covers what the
+/// overlay prints; anything else is blank.
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        _ => [0; GLYPH_HEIGHT],
+    }
+}
+
+/// Draws the counters of a long-running test once per second.
+///
+/// The lines are `T` uptime as hours:minutes:seconds, `F` frames, `U`
+/// underruns and `B` boots, the last from
+/// [count_boot](crate::diag::count_boot) so a reset shows up as a boot
+/// count above 1 and an uptime that doesn't match the wall clock.
+pub struct Overlay {
+    boot_count: u32,
+    started: Instant,
+    next: Instant,
+    foreground: u16,
+    background: u16,
+}
+
+impl Overlay {
+    pub fn new(boot_count: u32, foreground: u16, background: u16) -> Self {
+        let now = Instant::now();
+        Self {
+            boot_count,
+            started: now,
+            next: now,
+            foreground,
+            background,
+        }
+    }
+
+    /// Redraws the overlay into `fb` if a second has passed since the last
+    /// time, and marks the rows it touched in `dirty` for the next
+    /// [flush](crate::flush::flush). Call once per frame. Returns whether it
+    /// drew.
+    pub fn update<const W: usize, const H: usize>(
+        &mut self,
+        fb: &mut FrameBuffer<W, H>,
+        dirty: &mut DirtyRows<H>,
+        frames: u64,
+        underruns: u32,
+    ) -> bool {
+        let now = Instant::now();
+        if now < self.next {
+            return false;
+        }
+        self.next = now + Duration::from_secs(1);
+
+        let secs = (now - self.started).as_secs();
+        let mut lines: [String<COLUMNS>; LINES] = Default::default();
+        // Writes only fail once a line is full, which truncates it.
+        let _ = write!(
+            lines[0],
+            "T {}:{:02}:{:02}",
+            secs / 3600,
+            secs / 60 % 60,
+            secs % 60
+        );
+        let _ = write!(lines[1], "F {}", frames);
+        let _ = write!(lines[2], "U {}", underruns);
+        let _ = write!(lines[3], "B {}", self.boot_count);
+
+        let (width, height) = fb.logical_size();
+        let mut put = |x: usize, y: usize, color: u16| {
+            if x < width && y < height {
+                fb.set_pixel(x, y, color);
+                dirty.mark(fb.physical(x, y).1);
+            }
+        };
+
+        for y in 0..SIZE.1 {
+            for x in 0..SIZE.0 {
+                put(x, y, self.background);
+            }
+        }
+
+        for (row, line) in lines.iter().enumerate() {
+            for (column, c) in line.chars().enumerate() {
+                let (x0, y0) = (SCALE + column * ADVANCE, SCALE + row * LINE_HEIGHT);
+                for (gy, bits) in glyph(c).into_iter().enumerate() {
+                    for gx in (0..GLYPH_WIDTH).filter(|gx| (bits >> (2 - gx)) & 1 != 0) {
+                        let (x, y) = (x0 + gx * SCALE, y0 + gy * SCALE);
+                        for dy in 0..SCALE {
+                            for dx in 0..SCALE {
+                                put(x + dx, y + dy, self.foreground);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        true
+    }
+}
@@ -0,0 +1,155 @@
+//! Indexed color with palettes that only change between frames.
+
+use crate::color::wire_format;
+
+/// A palette of `N` RGB565 colors with a staged replacement.
+///
+/// [Palette::stage] doesn't touch the colors in use; the staged palette
+/// takes over at the next [Palette::begin_frame], so a frame never mixes two
+/// palettes. Call `begin_frame` when rendering line 0, which comes right
+/// after the previous frame's EOF push.
+pub struct Palette<const N: usize> {
+    active: [u16; N],
+    staged: [u16; N],
+    pending: bool,
+}
+
+impl<const N: usize> Palette<N> {
+    pub const fn new(colors: [u16; N]) -> Self {
+        Self {
+            active: colors,
+            staged: colors,
+            pending: false,
+        }
+    }
+
+    /// Queues `colors` to replace the palette at the next frame boundary.
+    /// Staging again before then replaces the queued palette.
+    pub fn stage(&mut self, colors: &[u16; N]) {
+        self.staged = *colors;
+        self.pending = true;
+    }
+
+    /// Marks a frame boundary, swapping in the staged palette if there is
+    /// one. Returns whether it did.
+    pub fn begin_frame(&mut self) -> bool {
+        if !self.pending {
+            return false;
+        }
+        core::mem::swap(&mut self.active, &mut self.staged);
+        self.pending = false;
+        true
+    }
+
+    /// The colors in use.
+    pub fn colors(&self) -> &[u16; N] {
+        &self.active
+    }
+
+    /// Writes the colors of `indices` to `dst` in the current
+    /// [WireFormat](crate::color::WireFormat).
+    /// Indices out of range are drawn as black.
+    pub fn expand_line(&self, indices: &[u8], dst: &mut [u8]) {
+        let wire = wire_format();
+        for (&index, dst) in indices.iter().zip(dst.chunks_exact_mut(2)) {
+            let color = self.active.get(index as usize).copied().unwrap_or(0);
+            dst.copy_from_slice(&wire.encode(color));
+        }
+    }
+}
+
+/// Color cycling demo: diagonal bands that scroll as the palette rotates by
+/// one entry per frame, without redrawing any pixel data.
+///
+/// Returns a render callback for [stream_frames](crate::stream::stream_frames)
+/// drawing `width` pixel lines.
+pub fn color_cycling<const N: usize>(
+    palette: &mut Palette<N>,
+    width: usize,
+) -> impl FnMut(usize, &mut [u8]) + '_ {
+    const BAND: usize = 16;
+
+    let wire = wire_format();
+
+    move |y, line| {
+        if y == 0 {
+            let mut next = *palette.colors();
+            next.rotate_left(1);
+            palette.stage(&next);
+            palette.begin_frame();
+        }
+
+        for (x, dst) in line.chunks_exact_mut(2).take(width).enumerate() {
+            let index = (x + y) / BAND % N;
+            dst.copy_from_slice(&wire.encode(palette.colors()[index]));
+        }
+    }
+}
+
+/// Streams a frame stored as one palette index per pixel, `width` indices
+/// per row, expanding each row while it's pushed.
+///
+/// Returns a render callback for [stream_frames](crate::stream::stream_frames).
+/// Indices are bytes, so at most 256 colors are usable. The frame takes
+/// half the RAM of RGB565, 230400 bytes at 480x480, for a lookup of a few
+/// cycles per pixel: an index load, a bounds check against `N` and a
+/// two-byte store, see [Palette::expand_line].
+pub fn indexed_frame<'a, const N: usize>(
+    palette: &'a Palette<N>,
+    indices: &'a [u8],
+    width: usize,
+) -> impl FnMut(usize, &mut [u8]) + 'a {
+    const { assert!(N <= 256, "palette indices are bytes") };
+
+    move |y, line| {
+        let row = indices.get(y * width..).unwrap_or(&[]);
+        palette.expand_line(&row[..width.min(row.len())], line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+
+    const COLORS: [u16; 4] = [0xF800, 0x07E0, 0x001F, 0xFFFF];
+
+    /// `colors` laid out as [Palette::expand_line] writes them.
+    fn encoded(colors: &[u16]) -> Vec<u8> {
+        let wire = wire_format();
+        colors
+            .iter()
+            .flat_map(|&color| wire.encode(color))
+            .collect()
+    }
+
+    #[test]
+    fn indexed_frame_expands_each_row_through_the_palette() {
+        let palette = Palette::new(COLORS);
+        // The out of range 9 is drawn black.
+        let indices = [0, 1, 2, 3, 9, 0];
+        let mut render = indexed_frame(&palette, &indices, 3);
+
+        let mut line = [0xAA; 6];
+        render(0, &mut line);
+        assert_eq!(line[..], encoded(&[0xF800, 0x07E0, 0x001F]));
+        render(1, &mut line);
+        assert_eq!(line[..], encoded(&[0xFFFF, 0x0000, 0xF800]));
+    }
+
+    #[test]
+    fn staged_colors_apply_from_the_next_frame() {
+        let mut palette = Palette::new(COLORS);
+        palette.stage(&[0x1234; 4]);
+
+        let mut line = [0; 2];
+        palette.expand_line(&[3], &mut line);
+        assert_eq!(line[..], encoded(&[0xFFFF]));
+
+        assert!(palette.begin_frame());
+        palette.expand_line(&[3], &mut line);
+        assert_eq!(line[..], encoded(&[0x1234]));
+        assert!(!palette.begin_frame());
+    }
+}
@@ -0,0 +1,322 @@
+//! Tuned panel parameters kept in flash across reboots.
+//!
+//! The tunable fields of a [PanelDescriptor] are stored as one fixed-layout,
+//! versioned record with a CRC in a flash sector. At boot [load] applies the
+//! record on top of the compiled-in preset if it's valid; anything corrupt,
+//! from another version, out of range or simply absent leaves the preset
+//! untouched.
+//!
+//! The functions take any embedded-storage flash, on the ESP32-S3 usually
+//! esp-storage's `FlashStorage`.
+
+use core::fmt::Debug;
+
+use embedded_storage::{ReadStorage, Storage};
+
+use crate::{
+    boards::{IdleLevels, PanelDescriptor},
+    display::clock::LatchEdge,
+};
+
+/// Flash offset of the record: the start of the default partition table's
+/// `nvs` partition, which this firmware doesn't otherwise use.
+const OFFSET: u32 = 0x9000;
+
+const MAGIC: u32 = u32::from_le_bytes(*b"PNL\0");
+const VERSION: u16 = 2;
+
+const LEN: usize = 27;
+
+/// The fastest pclk a record may ask for. More than any panel here takes, so
+/// anything above is a corrupt or mistyped value.
+const MAX_PCLK_MHZ: u16 = 40;
+
+/// The tunable part of a [PanelDescriptor], as stored in flash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Record {
+    pclk_mhz: u16,
+    latch: u8,
+    vcom: u8,
+    horizontal_total_width: u16,
+    horizontal_blank_front_porch: u16,
+    hsync_width: u16,
+    vertical_total_height: u16,
+    vertical_blank_front_porch: u16,
+    vsync_width: u16,
+    /// [IdleLevels] as bits, HSYNC, VSYNC and DE from bit 0, set for high.
+    idle: u8,
+}
+
+impl Record {
+    fn from_panel(panel: &PanelDescriptor) -> Self {
+        let timing = &panel.timing;
+        Self {
+            pclk_mhz: panel.pclk_mhz as u16,
+            latch: panel.latch as u8,
+            vcom: panel.vcom,
+            horizontal_total_width: timing.horizontal_total_width as u16,
+            horizontal_blank_front_porch: timing.horizontal_blank_front_porch as u16,
+            hsync_width: timing.hsync_width as u16,
+            vertical_total_height: timing.vertical_total_height as u16,
+            vertical_blank_front_porch: timing.vertical_blank_front_porch as u16,
+            vsync_width: timing.vsync_width as u16,
+            idle: bool::from(panel.idle.hsync) as u8
+                | ((bool::from(panel.idle.vsync) as u8) << 1)
+                | ((bool::from(panel.idle.de) as u8) << 2),
+        }
+    }
+
+    /// Whether the values fit the DPI timing registers and the active size
+    /// of `panel`, which isn't stored. A record that passes the CRC can
+    /// still hold nonsense, e.g. written by a buggy console command.
+    fn is_in_range(&self, panel: &PanelDescriptor) -> bool {
+        let timing = &panel.timing;
+        let h_blank =
+            (self.horizontal_total_width as usize).saturating_sub(timing.horizontal_active_width);
+        let v_blank =
+            (self.vertical_total_height as usize).saturating_sub(timing.vertical_active_height);
+
+        (1..=MAX_PCLK_MHZ).contains(&self.pclk_mhz)
+            && self.horizontal_total_width <= 4096
+            && self.vertical_total_height <= 1024
+            && self.horizontal_blank_front_porch <= 2048
+            && self.vertical_blank_front_porch <= 256
+            && (1..=128).contains(&self.hsync_width)
+            && (1..=128).contains(&self.vsync_width)
+            && (self.horizontal_blank_front_porch as usize) < h_blank
+            && (self.vertical_blank_front_porch as usize) < v_blank
+            && (self.hsync_width as usize) < h_blank
+            && (self.vsync_width as usize) < v_blank
+            && self.idle < 1 << 3
+    }
+
+    /// `preset` with the record applied, or `None` if it's out of range.
+    fn apply(&self, preset: &PanelDescriptor) -> Option<PanelDescriptor> {
+        if !self.is_in_range(preset) {
+            return None;
+        }
+
+        let mut panel = *preset;
+        panel.pclk_mhz = self.pclk_mhz as u32;
+        panel.latch = match self.latch {
+            0 => LatchEdge::RisingEdgeLatch,
+            _ => LatchEdge::FallingEdgeLatch,
+        };
+        panel.vcom = self.vcom;
+
+        let timing = &mut panel.timing;
+        timing.horizontal_total_width = self.horizontal_total_width as usize;
+        timing.horizontal_blank_front_porch = self.horizontal_blank_front_porch as usize;
+        timing.hsync_width = self.hsync_width as usize;
+        timing.vertical_total_height = self.vertical_total_height as usize;
+        timing.vertical_blank_front_porch = self.vertical_blank_front_porch as usize;
+        timing.vsync_width = self.vsync_width as usize;
+        panel.idle = IdleLevels {
+            hsync: (self.idle & 1 != 0).into(),
+            vsync: (self.idle & 2 != 0).into(),
+            de: (self.idle & 4 != 0).into(),
+        };
+        Some(panel)
+    }
+
+    /// Magic, version, fields in declaration order, then the CRC of
+    /// everything before it. All little-endian.
+    fn to_bytes(self) -> [u8; LEN] {
+        let mut bytes = [0; LEN];
+        bytes[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+        bytes[4..6].copy_from_slice(&VERSION.to_le_bytes());
+        bytes[6..8].copy_from_slice(&self.pclk_mhz.to_le_bytes());
+        bytes[8] = self.latch;
+        bytes[9] = self.vcom;
+        for (i, value) in [
+            self.horizontal_total_width,
+            self.horizontal_blank_front_porch,
+            self.hsync_width,
+            self.vertical_total_height,
+            self.vertical_blank_front_porch,
+            self.vsync_width,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            bytes[10 + i * 2..][..2].copy_from_slice(&value.to_le_bytes());
+        }
+        bytes[22] = self.idle;
+        let crc = crc32(&bytes[..LEN - 4]);
+        bytes[LEN - 4..].copy_from_slice(&crc.to_le_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8; LEN]) -> Option<Self> {
+        let u16_at = |at: usize| u16::from_le_bytes([bytes[at], bytes[at + 1]]);
+        let u32_at = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+
+        if u32_at(0) != MAGIC || u16_at(4) != VERSION {
+            return None;
+        }
+        if u32_at(LEN - 4) != crc32(&bytes[..LEN - 4]) {
+            return None;
+        }
+
+        Some(Self {
+            pclk_mhz: u16_at(6),
+            latch: bytes[8],
+            vcom: bytes[9],
+            horizontal_total_width: u16_at(10),
+            horizontal_blank_front_porch: u16_at(12),
+            hsync_width: u16_at(14),
+            vertical_total_height: u16_at(16),
+            vertical_blank_front_porch: u16_at(18),
+            vsync_width: u16_at(20),
+            idle: bytes[22],
+        })
+    }
+}
+
+/// CRC-32 (IEEE), bitwise; the record is too small for a table to pay off.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// `preset` with the stored tuning applied, or `preset` itself if there is
+/// no valid record.
+pub fn load<F>(flash: &mut F, preset: &PanelDescriptor) -> PanelDescriptor
+where
+    F: ReadStorage<Error: Debug>,
+{
+    let mut bytes = [0; LEN];
+    if let Err(err) = flash.read(OFFSET, &mut bytes) {
+        warn!("Reading panel tuning failed: {:?}", err);
+        return *preset;
+    }
+
+    let Some(record) = Record::from_bytes(&bytes) else {
+        info!("No valid panel tuning in flash, using preset");
+        return *preset;
+    };
+
+    match record.apply(preset) {
+        Some(panel) => {
+            info!("Loaded panel tuning from flash");
+            panel
+        }
+        None => {
+            warn!("Panel tuning in flash is out of range, using preset");
+            *preset
+        }
+    }
+}
+
+/// Stores the tunable fields of `panel`.
+pub fn save<F: Storage>(flash: &mut F, panel: &PanelDescriptor) -> Result<(), F::Error> {
+    flash.write(OFFSET, &Record::from_panel(panel).to_bytes())
+}
+
+/// Invalidates the stored record, so the next boot uses the preset.
+pub fn reset<F: Storage>(flash: &mut F) -> Result<(), F::Error> {
+    flash.write(OFFSET, &[0xFF; LEN])
+}
+
+/// Handles the `save` and `reset` console commands. Returns `None` for any
+/// other command.
+pub fn console_command<F: Storage>(
+    line: &str,
+    flash: &mut F,
+    panel: &PanelDescriptor,
+) -> Option<Result<(), F::Error>> {
+    match line.trim() {
+        "save" => Some(save(flash, panel)),
+        "reset" => Some(reset(flash)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{boards::MIAO_480X480, hal::gpio::Level};
+
+    const PRESET: PanelDescriptor = MIAO_480X480.panel;
+
+    fn record() -> Record {
+        Record::from_panel(&PRESET)
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let mut record = record();
+        record.pclk_mhz = 14;
+        record.idle = 0b101;
+        assert_eq!(Record::from_bytes(&record.to_bytes()), Some(record));
+    }
+
+    #[test]
+    fn rejects_corrupt_and_old_records() {
+        let mut bytes = record().to_bytes();
+        bytes[12] ^= 1;
+        assert_eq!(Record::from_bytes(&bytes), None);
+
+        let mut bytes = record().to_bytes();
+        bytes[4..6].copy_from_slice(&1u16.to_le_bytes());
+        assert_eq!(Record::from_bytes(&bytes), None);
+
+        assert_eq!(Record::from_bytes(&[0xFF; LEN]), None);
+    }
+
+    #[test]
+    fn preset_is_in_range() {
+        for board in crate::boards::ALL {
+            let panel = board.panel;
+            assert!(
+                Record::from_panel(&panel).is_in_range(&panel),
+                "{}",
+                board.name
+            );
+        }
+    }
+
+    #[test]
+    fn out_of_range_records_are_not_applied() {
+        let cases: [fn(&mut Record); 6] = [
+            |r| r.pclk_mhz = 0,
+            |r| r.pclk_mhz = MAX_PCLK_MHZ + 1,
+            |r| r.horizontal_blank_front_porch = r.horizontal_total_width,
+            |r| r.vertical_blank_front_porch = 20,
+            |r| r.hsync_width = 0,
+            |r| r.vsync_width = r.vertical_total_height,
+        ];
+        for (i, break_it) in cases.into_iter().enumerate() {
+            let mut record = record();
+            break_it(&mut record);
+            assert!(record.apply(&PRESET).is_none(), "case {i}");
+        }
+    }
+
+    #[test]
+    fn applies_timing_and_polarities() {
+        let mut record = record();
+        record.pclk_mhz = 10;
+        record.hsync_width = 4;
+        record.idle = 0b010;
+
+        let panel = record.apply(&PRESET).unwrap();
+        assert_eq!(panel.pclk_mhz, 10);
+        assert_eq!(panel.timing.hsync_width, 4);
+        assert_eq!(
+            panel.idle,
+            IdleLevels {
+                hsync: Level::Low,
+                vsync: Level::High,
+                de: Level::Low,
+            }
+        );
+    }
+}
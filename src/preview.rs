@@ -0,0 +1,82 @@
+//! Low-rate preview of the frame over the log, for remote devices.
+//!
+//! Enabled with the `preview` feature. See [HOST_DECODE] for the record
+//! format.
+
+use crate::{
+    color::wire_format, framebuffer::FrameBuffer, hal::time::Duration, logging::RateLimit,
+};
+
+/// Width and height of the preview in pixels.
+pub const SIZE: usize = 60;
+
+/// How to turn the log back into an image on the host.
+pub const HOST_DECODE: &str = "\
+A preview is a `PREVIEW BEGIN 60x60 rgb332` line, 60 `PV <row> <hex>` lines and a `PREVIEW END` \
+                               line. Each hex string is 60 pixels of one byte each, top row \
+                               first: bits 7..5 red, 4..2 green, 1..0 blue. Scale red and green \
+                               by 255/7 and blue by 255/3. Drop previews missing any row.";
+
+/// Emits a downsampled [FrameBuffer] over the log every few frames.
+pub struct Preview {
+    every: u32,
+    frame: u32,
+    limit: RateLimit,
+}
+
+impl Preview {
+    /// Emits at most every `every` frames and never more often than
+    /// `min_interval`.
+    pub const fn new(every: u32, min_interval: Duration) -> Self {
+        Self {
+            every,
+            frame: 0,
+            limit: RateLimit::new(min_interval),
+        }
+    }
+
+    /// Call once per frame. Emits a preview if one is due and `headroom` is
+    /// set; pass whether the stream can spare the time, e.g. whether the
+    /// queued bytes are above a safe watermark. A skipped preview is retried
+    /// on the next frame. Returns whether one was emitted.
+    pub fn frame<const W: usize, const H: usize>(
+        &mut self,
+        fb: &FrameBuffer<W, H>,
+        headroom: bool,
+    ) -> bool {
+        self.frame = self.frame.saturating_add(1);
+        if self.frame < self.every || !headroom || !self.limit.ready() {
+            return false;
+        }
+        self.frame = 0;
+
+        info!("PREVIEW BEGIN {}x{} rgb332", SIZE, SIZE);
+
+        let wire = wire_format();
+        let mut hex = [0; SIZE * 2];
+        for y in 0..SIZE {
+            let row = fb.row(y * H / SIZE);
+            for x in 0..SIZE {
+                let at = x * W / SIZE * 2;
+                let pixel = rgb332(wire.decode([row[at], row[at + 1]]));
+                hex[x * 2] = HEX[(pixel >> 4) as usize];
+                hex[x * 2 + 1] = HEX[(pixel & 0xF) as usize];
+            }
+            // Only ASCII hex digits were written.
+            let hex = core::str::from_utf8(&hex).unwrap();
+            info!("PV {} {}", y, hex);
+        }
+
+        info!("PREVIEW END");
+        true
+    }
+}
+
+const HEX: &[u8; 16] = b"0123456789abcdef";
+
+fn rgb332(color: u16) -> u8 {
+    let r = (color >> 13) as u8;
+    let g = ((color >> 8) & 0b111) as u8;
+    let b = ((color >> 3) & 0b11) as u8;
+    (r << 5) | (g << 2) | b
+}
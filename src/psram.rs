@@ -0,0 +1,370 @@
+//! Tear-free full-frame rendering from two frame buffers in PSRAM.
+//!
+//! Each buffer gets its own descriptor chain that loops back onto itself, so
+//! the DPI keeps scanning out the front buffer without any CPU involvement.
+//! A swap relinks the tail of the front chain to the head of the back chain:
+//! the DMA finishes the frame it is on and only then moves over, which makes
+//! every swap land in vertical blanking.
+//!
+//! The buffers have to come from mapped PSRAM, which needs esp-hal's `psram`
+//! feature; a full 480x480 RGB565 frame doesn't fit in internal RAM anyway.
+//!
+//! # Cache coherency
+//!
+//! The CPU writes PSRAM through the data cache while the DMA reads PSRAM
+//! directly, so pixels that are still sitting in dirty cache lines are
+//! invisible to the DMA. [PsramDoubleBuffer::swap] does the following, in
+//! this order:
+//!
+//! 1. Writes back every cache line of the back buffer to PSRAM.
+//! 2. Hands the back chain's descriptors to the DMA and closes it into a loop.
+//! 3. Issues a fence, so none of the above can be reordered past step 4.
+//! 4. Points the front chain's tail at the back chain's head.
+//!
+//! Until [PsramDoubleBuffer::is_swap_done] returns `true` the DMA may still
+//! be reading the old front buffer, so it must not be written to. Writes made
+//! to the back buffer after `swap` returns won't be written back and may
+//! never reach the panel.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let mut buf = PsramDoubleBuffer::new(DESCRIPTORS.take(), front, back).unwrap();
+//! render(buf.back_mut());
+//! buf.swap();
+//!
+//! let mut transfer = dpi.send(true, buf).map_err(|e| e.0).unwrap();
+//! loop {
+//!     // Only safe to render once the DMA has left the old front buffer.
+//!     transfer.wait_for_swap();
+//!     render(transfer.back_mut());
+//!     transfer.swap();
+//! }
+//! ```
+
+use core::{
+    ptr::null_mut,
+    sync::atomic::{Ordering, fence},
+};
+
+use crate::{
+    display::vsync::{self, Timeout},
+    dma::{is_slice_in_dram, is_slice_in_psram},
+    hal::{
+        dma::{
+            BurstConfig, DmaAlignmentError, DmaBufError, DmaDescriptor, DmaTxBuffer, Owner,
+            Preparation, TransferDirection,
+        },
+        time::Duration,
+    },
+};
+
+/// Alignment of buffer start and length required for DMA out of PSRAM with
+/// the default burst config.
+pub(crate) const EXTERNAL_ALIGNMENT: usize = 16;
+
+unsafe extern "C" {
+    fn rom_Cache_WriteBack_Addr(addr: u32, size: u32);
+    fn Cache_Suspend_DCache_Autoload() -> u32;
+    fn Cache_Resume_DCache_Autoload(value: u32);
+}
+
+/// Writes back all dirty cache lines covering `data`.
+pub(crate) fn cache_writeback(data: &[u8]) {
+    // Autoload is suspended so no lines get loaded while writing back.
+    unsafe {
+        let autoload = Cache_Suspend_DCache_Autoload();
+        rom_Cache_WriteBack_Addr(data.as_ptr() as u32, data.len() as u32);
+        Cache_Resume_DCache_Autoload(autoload);
+    }
+}
+
+/// 32-bit FNV-1a over the little-endian words of `data`, a trailing partial
+/// word ignored; frame buffers here are multiples of 16 bytes.
+fn frame_hash(data: &[u8]) -> u32 {
+    data.chunks_exact(4).fold(0x811C_9DC5, |hash, word| {
+        (hash ^ u32::from_le_bytes([word[0], word[1], word[2], word[3]])).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// Decides which presents [PsramDoubleBuffer::set_skip_unchanged] skips.
+#[derive(Debug, Default)]
+struct UnchangedFilter {
+    enabled: bool,
+    // Hash of the front buffer as presented, `None` after a plain `swap`.
+    front_hash: Option<u32>,
+}
+
+impl UnchangedFilter {
+    /// Whether `back` has to be swapped in, i.e. the filter is off or `back`
+    /// differs from the frame on screen. Takes `back` as the new front if so.
+    fn should_present(&mut self, back: &[u8]) -> bool {
+        if !self.enabled {
+            return true;
+        }
+
+        let hash = frame_hash(back);
+        if self.front_hash == Some(hash) {
+            return false;
+        }
+        self.front_hash = Some(hash);
+        true
+    }
+}
+
+/// Two PSRAM frame buffers scanned out alternately.
+pub struct PsramDoubleBuffer {
+    descriptors: &'static mut [DmaDescriptor],
+    buffers: [&'static mut [u8]; 2],
+
+    // Index of the buffer the DMA is (or will be) scanning out.
+    front: usize,
+    // Set between a swap and the DMA reaching the new front buffer.
+    swap_pending: bool,
+
+    // See `set_skip_unchanged`.
+    unchanged: UnchangedFilter,
+}
+
+impl PsramDoubleBuffer {
+    /// Creates a new [PsramDoubleBuffer].
+    ///
+    /// Both buffers must be one full frame, the same length, in PSRAM, and
+    /// aligned to 16 bytes. The descriptors are split evenly between the two
+    /// buffers, so there must be enough of them for two frames.
+    pub fn new(
+        descriptors: &'static mut [DmaDescriptor],
+        front: &'static mut [u8],
+        back: &'static mut [u8],
+    ) -> Result<Self, DmaBufError> {
+        if !is_slice_in_dram(descriptors) {
+            return Err(DmaBufError::UnsupportedMemoryRegion);
+        }
+        if !is_slice_in_psram(front) || !is_slice_in_psram(back) {
+            return Err(DmaBufError::UnsupportedMemoryRegion);
+        }
+
+        if front.len() != back.len() || front.is_empty() {
+            return Err(DmaBufError::BufferTooSmall);
+        }
+        for buffer in [&*front, &*back] {
+            if buffer.as_ptr() as usize % EXTERNAL_ALIGNMENT != 0 {
+                return Err(DmaBufError::InvalidAlignment(DmaAlignmentError::Address));
+            }
+            if buffer.len() % EXTERNAL_ALIGNMENT != 0 {
+                return Err(DmaBufError::InvalidAlignment(DmaAlignmentError::Size));
+            }
+        }
+
+        let max_chunk_size = BurstConfig::default().max_compatible_chunk_size();
+        if descriptors.len() / 2 < front.len().div_ceil(max_chunk_size) {
+            return Err(DmaBufError::InsufficientDescriptors);
+        }
+
+        descriptors.fill(DmaDescriptor::EMPTY);
+
+        let mut this = Self {
+            descriptors,
+            buffers: [front, back],
+            front: 0,
+            swap_pending: false,
+            unchanged: UnchangedFilter::default(),
+        };
+        this.build_chain(0);
+        this.build_chain(1);
+
+        Ok(this)
+    }
+
+    /// The buffer to render the next frame into.
+    pub fn back_mut(&mut self) -> &mut [u8] {
+        &mut self.buffers[1 - self.front][..]
+    }
+
+    /// Makes the back buffer the front one, starting with the next frame.
+    ///
+    /// Blocks until the previous swap has completed first. See the
+    /// [module docs](self) for the exact sequence.
+    pub fn swap(&mut self) {
+        self.swap_buffers();
+        self.unchanged.front_hash = None;
+    }
+
+    /// [Self::swap] without forgetting the front buffer's hash, for
+    /// [Self::present].
+    fn swap_buffers(&mut self) {
+        self.wait_for_swap();
+
+        let back = 1 - self.front;
+
+        cache_writeback(&self.buffers[back][..]);
+        self.build_chain(back);
+        fence(Ordering::SeqCst);
+
+        let back_head: *mut _ = &mut self.chain(back)[0];
+        let front_chain = self.chain(self.front);
+        front_chain[front_chain.len() - 1].next = back_head;
+
+        self.front = back;
+        self.swap_pending = true;
+    }
+
+    /// Makes [Self::present] skip frames identical to the one on screen.
+    ///
+    /// The front chain loops on its own, so a skipped frame keeps showing
+    /// without any work. The DPI scans out every refresh either way; what's
+    /// saved is the cache writeback of the whole back buffer, a frame's
+    /// worth of PSRAM writes, and the wait for the swap, about a frame of
+    /// CPU time. Against that, every present reads the back buffer once to
+    /// hash it, so this only pays off for content that mostly stands still.
+    ///
+    /// The hash is 32-bit FNV-1a over 32-bit words. A changed frame that
+    /// collides with the previous one, about 1 in 4 billion, is skipped and
+    /// the stale frame stays up until the next change.
+    pub fn set_skip_unchanged(&mut self, enable: bool) {
+        self.unchanged = UnchangedFilter {
+            enabled: enable,
+            front_hash: None,
+        };
+    }
+
+    /// [Self::swap], unless [Self::set_skip_unchanged] is on and the back
+    /// buffer holds the frame already shown. Returns whether it swapped.
+    pub fn present(&mut self) -> bool {
+        if !self.unchanged.should_present(self.buffers[1 - self.front]) {
+            return false;
+        }
+        self.swap_buffers();
+        true
+    }
+
+    /// Whether the DMA has moved on to the buffer passed to the last
+    /// [Self::swap], making the other one safe to render into.
+    pub fn is_swap_done(&mut self) -> bool {
+        if self.swap_pending && self.chain(self.front)[0].owner() == Owner::Cpu {
+            self.swap_pending = false;
+        }
+        !self.swap_pending
+    }
+
+    /// Blocks until [Self::is_swap_done].
+    pub fn wait_for_swap(&mut self) {
+        while !self.is_swap_done() {}
+    }
+
+    /// [Self::wait_for_swap] with a deadline, see [vsync::frame_timeout] for
+    /// a default. A swap that doesn't complete in a frame or two means the
+    /// DMA has stopped.
+    ///
+    /// [vsync::frame_timeout]: crate::display::vsync::frame_timeout
+    pub fn wait_for_swap_timeout(&mut self, timeout: Duration) -> Result<(), Timeout> {
+        vsync::poll_until(|| self.is_swap_done(), timeout)
+    }
+
+    /// Consume the buf, returning the descriptors and buffers.
+    pub fn split(self) -> (&'static mut [DmaDescriptor], [&'static mut [u8]; 2]) {
+        (self.descriptors, self.buffers)
+    }
+
+    fn chain(&mut self, index: usize) -> &mut [DmaDescriptor] {
+        let max_chunk_size = BurstConfig::default().max_compatible_chunk_size();
+        let len = self.buffers[index].len().div_ceil(max_chunk_size);
+        let half = self.descriptors.len() / 2;
+
+        &mut self.descriptors[index * half..][..len]
+    }
+
+    /// Points the chain of buffer `index` at its buffer, hands it to the DMA
+    /// and closes it into a loop.
+    fn build_chain(&mut self, index: usize) {
+        let max_chunk_size = BurstConfig::default().max_compatible_chunk_size();
+        let buffer = self.buffers[index].as_mut_ptr();
+        let buffer_len = self.buffers[index].len();
+
+        let chain = self.chain(index);
+        let head: *mut _ = &mut chain[0];
+        let mut next = head;
+        for (i, desc) in chain.iter_mut().enumerate().rev() {
+            let offset = i * max_chunk_size;
+            let len = max_chunk_size.min(buffer_len - offset);
+
+            desc.buffer = unsafe { buffer.add(offset) };
+            desc.set_length(len);
+            desc.set_size(len);
+            desc.set_suc_eof(next == head);
+            desc.set_owner(Owner::Dma);
+            desc.next = next;
+            next = desc;
+        }
+    }
+}
+
+unsafe impl DmaTxBuffer for PsramDoubleBuffer {
+    type View = PsramDoubleBuffer;
+
+    fn prepare(&mut self) -> Preparation {
+        let front = self.front;
+        self.build_chain(front);
+        self.swap_pending = false;
+
+        Preparation {
+            start: &mut self.chain(front)[0],
+            direction: TransferDirection::Out,
+            accesses_psram: true,
+            // The chains are loops, descriptors the DMA has handed back come
+            // around again.
+            check_owner: Some(false),
+            burst_transfer: BurstConfig::default(),
+            auto_write_back: true,
+        }
+    }
+
+    fn into_view(self) -> Self::View {
+        self
+    }
+
+    fn from_view(mut view: Self::View) -> Self {
+        // Break the loops so nothing points into a stale chain.
+        for index in 0..2 {
+            let chain = view.chain(index);
+            chain[chain.len() - 1].next = null_mut();
+        }
+        view.swap_pending = false;
+        view
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unchanged_frames_are_skipped_and_changed_ones_streamed() {
+        let a = [0x11; 64];
+        let mut b = a;
+        b[63] = 0x12;
+
+        let mut filter = UnchangedFilter {
+            enabled: true,
+            front_hash: None,
+        };
+        assert!(filter.should_present(&a), "first frame");
+        assert!(!filter.should_present(&a), "unchanged");
+        assert!(filter.should_present(&b), "changed");
+        assert!(!filter.should_present(&b), "unchanged again");
+        assert!(filter.should_present(&a), "changed back");
+
+        // After a plain `swap` the front buffer is unknown.
+        filter.front_hash = None;
+        assert!(filter.should_present(&a), "after a swap");
+    }
+
+    #[test]
+    fn every_frame_is_streamed_with_the_filter_off() {
+        let mut filter = UnchangedFilter::default();
+        let frame = [0x11; 64];
+        for _ in 0..3 {
+            assert!(filter.should_present(&frame));
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! Event-driven rendering: the panel is refreshed from a looping frame with
+//! no CPU involvement, and a new frame is only rendered on request.
+//!
+//! Built on [PsramDoubleBuffer], whose descriptor chains loop over the front
+//! buffer and whose swaps land on a frame boundary. Between requests nothing
+//! is pushed or written back, so [RedrawStats] stays flat while idle.
+//!
+//! ```ignore
+//! #[handler]
+//! fn on_button() {
+//!     critical_section::with(|cs| BUTTON.borrow_ref_mut(cs).as_mut().unwrap().clear_interrupt());
+//!     redraw::request_redraw();
+//! }
+//!
+//! let mut transfer = dpi.send(true, double_buffer).map_err(|e| e.0).unwrap();
+//! let mut redraw = Redraw::new();
+//! let mut pattern = false;
+//! loop {
+//!     redraw.poll(&mut transfer, |back| {
+//!         pattern = !pattern;
+//!         draw_pattern(back, pattern);
+//!     });
+//! }
+//! ```
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::{psram::PsramDoubleBuffer, stats::RedrawStats};
+
+static REDRAW_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Asks for one new frame. Safe to call from an interrupt handler; requests
+/// made before the next [Redraw::poll] collapse into one.
+pub fn request_redraw() {
+    REDRAW_REQUESTED.store(true, Ordering::Release);
+}
+
+/// The render side of event-driven rendering, see the [module](self) docs.
+#[derive(Default)]
+pub struct Redraw {
+    stats: RedrawStats,
+}
+
+impl Redraw {
+    pub const fn new() -> Self {
+        Self {
+            stats: RedrawStats::new(),
+        }
+    }
+
+    /// If a redraw was requested, renders into the back buffer with `render`
+    /// and swaps it in at the next frame boundary. Returns whether it did.
+    ///
+    /// Waits for the previous swap first, so at most one frame per refresh
+    /// is rendered however often redraws are requested.
+    pub fn poll(&mut self, buf: &mut PsramDoubleBuffer, render: impl FnOnce(&mut [u8])) -> bool {
+        if !REDRAW_REQUESTED.swap(false, Ordering::Acquire) {
+            self.stats.idle_polls += 1;
+            return false;
+        }
+
+        buf.wait_for_swap();
+        let back = buf.back_mut();
+        render(back);
+        self.stats.bytes_written += back.len() as u64;
+        buf.swap();
+        self.stats.frames += 1;
+
+        true
+    }
+
+    pub fn stats(&self) -> &RedrawStats {
+        &self.stats
+    }
+}
@@ -0,0 +1,248 @@
+//! Drawing primitives and per-line effects.
+
+use crate::{
+    color::{ChannelLuts, DimMode, fade_in_place, wire_format},
+    framebuffer::FrameBuffer,
+    hal::{time::Duration, xtensa_lx::timer::get_cycle_count},
+    logging::RateLimit,
+    palette::Palette,
+    stats::CallbackStats,
+};
+
+/// Something pixels can be drawn into.
+pub trait RenderTarget {
+    fn width(&self) -> usize;
+    fn height(&self) -> usize;
+    /// Sets the pixel at (`x`, `y`) to the RGB565 `color`. Coordinates are
+    /// in range.
+    fn set_pixel(&mut self, x: usize, y: usize, color: u16);
+}
+
+impl<const W: usize, const H: usize> RenderTarget for FrameBuffer<W, H> {
+    fn width(&self) -> usize {
+        self.logical_size().0
+    }
+
+    fn height(&self) -> usize {
+        self.logical_size().1
+    }
+
+    fn set_pixel(&mut self, x: usize, y: usize, color: u16) {
+        FrameBuffer::set_pixel(self, x, y, color);
+    }
+}
+
+/// Fills `x0..=x1` on row `y`, clipped to the target.
+fn hline(buf: &mut impl RenderTarget, x0: i32, x1: i32, y: i32, color: u16) {
+    if y < 0 || y >= buf.height() as i32 {
+        return;
+    }
+    let x0 = x0.max(0);
+    let x1 = x1.min(buf.width() as i32 - 1);
+    for x in x0..=x1 {
+        buf.set_pixel(x as usize, y as usize, color);
+    }
+}
+
+/// Draws a filled circle centered on (`cx`, `cy`), clipped to the target.
+///
+/// The midpoint circle algorithm walks one octant and yields the half width
+/// of four rows at each step, which are filled as spans.
+pub fn draw_filled_circle(buf: &mut impl RenderTarget, cx: i32, cy: i32, radius: i32, color: u16) {
+    if radius < 0 {
+        return;
+    }
+
+    let mut x = radius;
+    let mut y = 0;
+    let mut err = 1 - radius;
+    while x >= y {
+        hline(buf, cx - x, cx + x, cy + y, color);
+        hline(buf, cx - x, cx + x, cy - y, color);
+        hline(buf, cx - y, cx + y, cy + x, color);
+        hline(buf, cx - y, cx + y, cy - x, color);
+
+        y += 1;
+        if err < 0 {
+            err += 2 * y + 1;
+        } else {
+            x -= 1;
+            err += 2 * (y - x) + 1;
+        }
+    }
+}
+
+/// Largest `x` with `x * x <= n`, by Newton's method.
+fn isqrt(n: i32) -> i32 {
+    if n < 2 {
+        return n.max(0);
+    }
+    let mut x = n;
+    let mut next = (x + 1) / 2;
+    while next < x {
+        x = next;
+        next = (x + n / x) / 2;
+    }
+    x
+}
+
+/// Draws a ring `thickness` pixels wide whose outer edge is the circle of
+/// `radius` around (`cx`, `cy`), clipped to the target.
+///
+/// Each row spans from the outer circle to the inner one, with half widths
+/// from an integer square root, so the ring has no gaps at any thickness.
+pub fn draw_circle_outline(
+    buf: &mut impl RenderTarget,
+    cx: i32,
+    cy: i32,
+    radius: i32,
+    color: u16,
+    thickness: u8,
+) {
+    if radius < 0 || thickness == 0 {
+        return;
+    }
+
+    let inner = radius - thickness as i32;
+    for dy in -radius..=radius {
+        let outer_x = isqrt(radius * radius - dy * dy);
+        if inner < 0 || dy.abs() > inner {
+            hline(buf, cx - outer_x, cx + outer_x, cy + dy, color);
+            continue;
+        }
+
+        let inner_x = isqrt(inner * inner - dy * dy);
+        hline(buf, cx - outer_x, cx - inner_x - 1, cy + dy, color);
+        hline(buf, cx + inner_x + 1, cx + outer_x, cy + dy, color);
+    }
+}
+
+/// A per-line transform, one stage of a [Pipeline].
+pub trait LineEffect {
+    /// Transforms line `y` in place. `line` holds pixels in the current
+    /// [WireFormat](crate::color::WireFormat).
+    fn apply(&mut self, y: usize, line: &mut [u8]);
+
+    /// Cycles this stage may take per line, checked when
+    /// [stream_frames_with](crate::stream::stream_frames_with) enforces
+    /// timing.
+    fn max_cycles(&self) -> u32 {
+        u32::MAX
+    }
+}
+
+/// `N` [LineEffect]s applied in order to every line, between the render
+/// callback and the push.
+///
+/// Each stage is timed separately when timing is enforced, see
+/// [Self::last_frame]. The dyn calls cost a few cycles per stage and line,
+/// nothing next to a pass over 480 pixels.
+///
+/// ```ignore
+/// let mut lut = Lut(ChannelLuts::new(RED_BLUE, GREEN));
+/// let mut dim = Dim { level: 20, mode: DimMode::Fast };
+/// let mut effects = Pipeline::new([&mut lut, &mut dim]);
+/// ```
+pub struct Pipeline<'a, const N: usize> {
+    stages: [&'a mut dyn LineEffect; N],
+    current: [CallbackStats; N],
+    last_frame: [CallbackStats; N],
+    log_limit: RateLimit,
+}
+
+impl<'a, const N: usize> Pipeline<'a, N> {
+    pub fn new(stages: [&'a mut dyn LineEffect; N]) -> Self {
+        Self {
+            stages,
+            current: [CallbackStats::new(); N],
+            last_frame: [CallbackStats::new(); N],
+            log_limit: RateLimit::new(Duration::from_secs(1)),
+        }
+    }
+
+    /// Runs every stage on line `y`.
+    pub fn apply(&mut self, y: usize, line: &mut [u8]) {
+        for stage in &mut self.stages {
+            stage.apply(y, line);
+        }
+    }
+
+    /// [Self::apply], timing each stage. Returns the cycles of all stages
+    /// together.
+    pub(crate) fn apply_timed(&mut self, y: usize, line: &mut [u8]) -> u32 {
+        let mut total = 0u32;
+        for (stage, stats) in self.stages.iter_mut().zip(&mut self.current) {
+            let started = get_cycle_count();
+            stage.apply(y, line);
+            let cycles = get_cycle_count().wrapping_sub(started);
+
+            let max_cycles = stage.max_cycles();
+            if cycles > max_cycles && self.log_limit.ready() {
+                warn!(
+                    "Effect stage took {} cycles on line {}, budget is {}",
+                    cycles, y, max_cycles
+                );
+            }
+            stats.record(cycles, cycles > max_cycles);
+            total = total.wrapping_add(cycles);
+        }
+        total
+    }
+
+    pub(crate) fn end_frame(&mut self) {
+        self.last_frame = core::mem::replace(&mut self.current, [CallbackStats::new(); N]);
+    }
+
+    /// Per stage durations of the last complete frame, in stage order.
+    pub fn last_frame(&self) -> &[CallbackStats; N] {
+        &self.last_frame
+    }
+}
+
+impl<const N: usize> LineEffect for Pipeline<'_, N> {
+    fn apply(&mut self, y: usize, line: &mut [u8]) {
+        Pipeline::apply(self, y, line);
+    }
+}
+
+/// Dims every pixel, see [fade_to_black](crate::color::fade_to_black).
+pub struct Dim<'a> {
+    pub level: u8,
+    pub mode: DimMode<'a>,
+}
+
+impl LineEffect for Dim<'_> {
+    fn apply(&mut self, _y: usize, line: &mut [u8]) {
+        fade_in_place(line, self.level, self.mode);
+    }
+}
+
+/// Maps every channel through a table, e.g. for color correction.
+pub struct Lut(pub ChannelLuts);
+
+impl LineEffect for Lut {
+    fn apply(&mut self, _y: usize, line: &mut [u8]) {
+        let wire = wire_format();
+        for pixel in line.chunks_exact_mut(2) {
+            let color = wire.decode([pixel[0], pixel[1]]);
+            pixel.copy_from_slice(&wire.encode(self.0.dim(color)));
+        }
+    }
+}
+
+/// Expands a frame stored as palette indices, `width` per row, replacing
+/// whatever the line held. A source stage, so it goes first, see
+/// [indexed_frame](crate::palette::indexed_frame).
+pub struct Indexed<'a, const N: usize> {
+    pub palette: &'a Palette<N>,
+    pub indices: &'a [u8],
+    pub width: usize,
+}
+
+impl<const N: usize> LineEffect for Indexed<'_, N> {
+    fn apply(&mut self, y: usize, line: &mut [u8]) {
+        let row = self.indices.get(y * self.width..).unwrap_or(&[]);
+        self.palette
+            .expand_line(&row[..self.width.min(row.len())], line);
+    }
+}
@@ -0,0 +1,117 @@
+//! Long-running fixed-color output for stability and EMC testing.
+
+use crate::{
+    color::wire_format,
+    display::{
+        st7701::{SpiProvider, St7701},
+        vsync,
+    },
+    dma::{is_slice_in_dram, is_slice_in_psram},
+    hal::{
+        DriverMode,
+        delay::Delay,
+        dma::{DmaBufError, DmaDescriptor, DmaError},
+        lcd_cam::lcd::dpi::Dpi,
+        time::{Duration, Instant},
+    },
+    looped::LoopedFrame,
+    psram::cache_writeback,
+};
+
+/// Fills `frame` with the RGB565 `color`, loops it on `dpi` and never
+/// returns.
+///
+/// Once the transfer runs the DMA replays the frame with no CPU involvement.
+/// Every `heartbeat` the CPU checks that VSYNC still arrives within
+/// `frame_timeout` (see [vsync::frame_timeout]) and that RDDST still reads
+/// display on and out of sleep. A stalled stream is stopped and restarted, a
+/// panel that fails its check or doesn't answer is re-initialized with
+/// [St7701::init]. Each heartbeat logs the uptime and the recoveries so far.
+///
+/// `frame` has to be exactly one frame, in internal RAM or in PSRAM aligned
+/// to 16 bytes. Only fails if it isn't DMA-capable, `descriptors` are too
+/// few for it or the first send fails. A restart that fails later is logged
+/// and tried again on the next heartbeat.
+pub fn soak_test<'d, S: SpiProvider, Dm: DriverMode>(
+    panel: &mut St7701<'_, S>,
+    dpi: Dpi<'d, Dm>,
+    descriptors: &'static mut [DmaDescriptor],
+    frame: &'static mut [u8],
+    color: u16,
+    heartbeat: Duration,
+    frame_timeout: Duration,
+) -> Result<core::convert::Infallible, DmaError> {
+    let accesses_psram = is_slice_in_psram(frame);
+    if !accesses_psram && !is_slice_in_dram(frame) {
+        return Err(DmaBufError::UnsupportedMemoryRegion.into());
+    }
+
+    let bytes = wire_format().encode(color);
+    for pixel in frame.chunks_exact_mut(2) {
+        pixel.copy_from_slice(&bytes);
+    }
+    if accesses_psram {
+        cache_writeback(frame);
+    }
+
+    let looped = LoopedFrame::from_parts(descriptors, frame, accesses_psram)?;
+    // A stopped stream keeps its parts until a restart succeeds.
+    let mut stream = Ok(dpi.send(true, looped).map_err(|(err, ..)| err)?);
+
+    let mut delay = Delay::new();
+    let start = Instant::now();
+    let mut stream_restarts = 0u32;
+    let mut panel_reinits = 0u32;
+
+    info!("Soak test started, color {:04X}", color);
+
+    loop {
+        delay.delay_millis(heartbeat.as_millis() as u32);
+
+        let stalled = stream.is_err() || {
+            vsync::clear();
+            vsync::wait_timeout(frame_timeout).is_err()
+        };
+        if stalled {
+            let (dpi, looped) = match stream {
+                Ok(transfer) => {
+                    warn!(
+                        "Soak: no VSYNC within {}ms, restarting stream",
+                        frame_timeout.as_millis()
+                    );
+                    transfer.stop()
+                }
+                Err(stopped) => stopped,
+            };
+            stream = match dpi.send(true, looped) {
+                Ok(transfer) => {
+                    stream_restarts += 1;
+                    Ok(transfer)
+                }
+                Err((err, dpi, looped)) => {
+                    warn!("Soak: restart failed, retrying next heartbeat: {:?}", err);
+                    Err((dpi, looped))
+                }
+            };
+        }
+
+        let healthy = match panel.read_status() {
+            Ok(status) => status.display_on && !status.sleep,
+            Err(_) => false,
+        };
+        if !healthy {
+            warn!("Soak: panel not displaying, re-initializing");
+            if panel.init(&mut delay).is_err() {
+                warn!("Soak: re-init failed, retrying next heartbeat");
+            }
+            panel_reinits += 1;
+        }
+
+        info!(
+            "Soak: up {}s, {} stream restarts, {} panel re-inits",
+            start.elapsed().as_secs(),
+            stream_restarts,
+            panel_reinits
+        );
+    }
+}
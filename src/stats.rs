@@ -0,0 +1,215 @@
+use crate::hal::{lcd_cam::lcd::dpi::FrameTiming, time::Instant};
+
+/// Bytes pushed into the ring and the CPU time spent doing it, split by
+/// whether the CPU or a memory-to-memory DMA channel did the copy.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PushStats {
+    cpu_bytes: u64,
+    cpu_micros: u64,
+    dma_bytes: u64,
+    dma_micros: u64,
+}
+
+impl PushStats {
+    pub const fn new() -> Self {
+        Self {
+            cpu_bytes: 0,
+            cpu_micros: 0,
+            dma_bytes: 0,
+            dma_micros: 0,
+        }
+    }
+
+    pub(crate) fn record_cpu(&mut self, bytes: usize, micros: u64) {
+        self.cpu_bytes += bytes as u64;
+        self.cpu_micros += micros;
+    }
+
+    pub(crate) fn record_dma(&mut self, bytes: usize, micros: u64) {
+        self.dma_bytes += bytes as u64;
+        self.dma_micros += micros;
+    }
+
+    /// Bytes copied by the CPU.
+    pub fn cpu_bytes(&self) -> u64 {
+        self.cpu_bytes
+    }
+
+    /// Bytes copied by DMA.
+    pub fn dma_bytes(&self) -> u64 {
+        self.dma_bytes
+    }
+
+    /// Estimated CPU time saved by DMA pushes, in microseconds.
+    ///
+    /// This is what the DMA-pushed bytes would have cost at the measured
+    /// CPU push rate, minus the time actually spent in
+    /// [DmaTxStreamBufView::push_dma] and [DmaPush::finish]. Waiting in
+    /// `finish` counts as spent, so overlapping more rendering with the copy
+    /// raises the number. Zero until a CPU push has been measured.
+    ///
+    /// [DmaTxStreamBufView::push_dma]: crate::dma::DmaTxStreamBufView::push_dma
+    /// [DmaPush::finish]: crate::dma::DmaPush::finish
+    pub fn cpu_micros_saved(&self) -> u64 {
+        if self.cpu_bytes == 0 {
+            return 0;
+        }
+
+        let cpu_equivalent = self.dma_bytes * self.cpu_micros / self.cpu_bytes;
+        cpu_equivalent.saturating_sub(self.dma_micros)
+    }
+}
+
+/// State of a [RefillController](crate::stream::RefillController).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RefillStats {
+    /// Lines to render before the next push.
+    pub batch: usize,
+    /// Queued bytes seen at the last update.
+    pub last_queued: usize,
+    /// Times the batch size was increased because the queue ran low.
+    pub ramp_ups: u32,
+    /// Times the batch size was decreased because the queue was full enough.
+    pub back_offs: u32,
+}
+
+/// A throughput measurement started by
+/// [DmaTxStreamBuf::start_throughput_benchmark].
+///
+/// [DmaTxStreamBuf::start_throughput_benchmark]: crate::dma::DmaTxStreamBuf::start_throughput_benchmark
+#[derive(Debug, Clone, Copy)]
+pub struct ThroughputBenchmark {
+    start: Instant,
+    bytes_pushed: u64,
+}
+
+impl ThroughputBenchmark {
+    pub(crate) fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            bytes_pushed: 0,
+        }
+    }
+
+    pub(crate) fn record(&mut self, bytes: usize) {
+        self.bytes_pushed += bytes as u64;
+    }
+
+    /// Bytes pushed since the benchmark started.
+    pub fn bytes_pushed(&self) -> u64 {
+        self.bytes_pushed
+    }
+
+    /// Average push rate since the benchmark started, in MB/s.
+    pub fn megabytes_per_second(&self) -> f32 {
+        let micros = self.start.elapsed().as_micros();
+        if micros == 0 {
+            return 0.0;
+        }
+
+        // Bytes per microsecond is MB/s.
+        self.bytes_pushed as f32 / micros as f32
+    }
+}
+
+/// Bandwidth in MB/s the application has to push to refresh the active area
+/// of `timing` at `fps` frames per second with `bpp` bits per pixel.
+///
+/// Blanking isn't counted since it isn't read from the buffer, so a push rate
+/// below this is guaranteed to underrun.
+pub fn theoretical_min_bandwidth(timing: &FrameTiming, bpp: u8, fps: u32) -> f32 {
+    let pixels = timing.horizontal_active_width * timing.vertical_active_height;
+    let bytes_per_frame = pixels as f32 * bpp as f32 / 8.0;
+    bytes_per_frame * fps as f32 / 1_000_000.0
+}
+
+/// Render callback durations over one frame, see
+/// [LineBudget](crate::stream::LineBudget).
+#[derive(Debug, Clone, Copy)]
+pub struct CallbackStats {
+    min_cycles: u32,
+    max_cycles: u32,
+    total_cycles: u64,
+    count: u32,
+    overruns: u32,
+}
+
+impl CallbackStats {
+    pub const fn new() -> Self {
+        Self {
+            min_cycles: u32::MAX,
+            max_cycles: 0,
+            total_cycles: 0,
+            count: 0,
+            overruns: 0,
+        }
+    }
+
+    pub(crate) fn record(&mut self, cycles: u32, over_budget: bool) {
+        self.min_cycles = self.min_cycles.min(cycles);
+        self.max_cycles = self.max_cycles.max(cycles);
+        self.total_cycles += cycles as u64;
+        self.count += 1;
+        self.overruns += over_budget as u32;
+    }
+
+    /// Shortest callback, in CPU cycles.
+    pub fn min_cycles(&self) -> u32 {
+        if self.count == 0 { 0 } else { self.min_cycles }
+    }
+
+    /// Longest callback, in CPU cycles.
+    pub fn max_cycles(&self) -> u32 {
+        self.max_cycles
+    }
+
+    /// Average callback, in CPU cycles.
+    pub fn avg_cycles(&self) -> u32 {
+        if self.count == 0 {
+            return 0;
+        }
+        (self.total_cycles / self.count as u64) as u32
+    }
+
+    /// Callbacks that went over budget.
+    pub fn overruns(&self) -> u32 {
+        self.overruns
+    }
+}
+
+impl Default for CallbackStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What one [flush](crate::flush::flush) call did.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FlushStats {
+    /// Separate copies made, after coalescing.
+    pub ranges: u32,
+    pub bytes: usize,
+    pub micros: u64,
+}
+
+/// Work done by a [Redraw](crate::redraw::Redraw).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RedrawStats {
+    /// Frames rendered and swapped in.
+    pub frames: u32,
+    /// Bytes rendered and written back to PSRAM, the only bandwidth used on
+    /// top of the DMA's own scan-out.
+    pub bytes_written: u64,
+    /// Polls that found no request.
+    pub idle_polls: u32,
+}
+
+impl RedrawStats {
+    pub const fn new() -> Self {
+        Self {
+            frames: 0,
+            bytes_written: 0,
+            idle_polls: 0,
+        }
+    }
+}
@@ -0,0 +1,490 @@
+//! Helpers for keeping the ring fed while streaming.
+
+use crate::{
+    color::WireBytes,
+    dma::{DmaTxStreamBufView, Underrun},
+    geometry::FrameGeometry,
+    hal::{time::Duration, xtensa_lx::timer::get_cycle_count},
+    logging::RateLimit,
+    render::Pipeline,
+    stats::{CallbackStats, RefillStats},
+};
+
+/// Parameters of a [RefillController].
+#[derive(Debug, Clone, Copy)]
+pub struct RefillConfig {
+    /// Below this many queued bytes the batch size is doubled.
+    pub low_watermark: usize,
+    /// Above this many queued bytes the batch size is decreased by one.
+    pub high_watermark: usize,
+    pub min_batch: usize,
+    pub max_batch: usize,
+}
+
+/// Picks how many lines to render before the next push, from how much data
+/// is still queued in the ring.
+///
+/// Close to an underrun the batch size ramps up quickly; while the renderer
+/// is comfortably ahead it backs off one line at a time. This keeps the
+/// queue inside the watermarks whether pclk is 6MHz or 18MHz, without
+/// retuning.
+///
+/// ```ignore
+/// let mut refill = RefillController::new(config);
+/// loop {
+///     let lines = refill.update(transfer.queued_bytes());
+///     for _ in 0..lines {
+///         render_line(&mut line);
+///         transfer.push_wire(WireBytes(&line), false);
+///     }
+/// }
+/// ```
+pub struct RefillController {
+    config: RefillConfig,
+    stats: RefillStats,
+}
+
+impl RefillController {
+    pub const fn new(config: RefillConfig) -> Self {
+        Self {
+            stats: RefillStats {
+                batch: config.min_batch,
+                last_queued: 0,
+                ramp_ups: 0,
+                back_offs: 0,
+            },
+            config,
+        }
+    }
+
+    /// Feeds in the currently queued bytes and returns the batch size to use
+    /// for this iteration.
+    pub fn update(&mut self, queued: usize) -> usize {
+        let stats = &mut self.stats;
+        stats.last_queued = queued;
+
+        if queued < self.config.low_watermark && stats.batch < self.config.max_batch {
+            stats.batch = (stats.batch * 2).clamp(1, self.config.max_batch);
+            stats.ramp_ups += 1;
+        } else if queued > self.config.high_watermark && stats.batch > self.config.min_batch {
+            stats.batch -= 1;
+            stats.back_offs += 1;
+        }
+
+        stats.batch
+    }
+
+    /// The current batch size.
+    pub fn batch(&self) -> usize {
+        self.stats.batch
+    }
+
+    pub fn config(&self) -> &RefillConfig {
+        &self.config
+    }
+
+    pub fn stats(&self) -> &RefillStats {
+        &self.stats
+    }
+}
+
+/// A time limit for each render callback of [stream_frames].
+pub struct LineBudget {
+    /// Longest a single callback may take, in CPU cycles.
+    pub max_cycles: u32,
+    /// Replace lines whose callback went over budget with magenta, so the
+    /// offender is visible on screen.
+    pub mark_overruns: bool,
+    current: CallbackStats,
+    last_frame: CallbackStats,
+    log_limit: RateLimit,
+}
+
+impl LineBudget {
+    pub const fn new(max_cycles: u32, mark_overruns: bool) -> Self {
+        Self {
+            max_cycles,
+            mark_overruns,
+            current: CallbackStats::new(),
+            last_frame: CallbackStats::new(),
+            log_limit: RateLimit::new(Duration::from_secs(1)),
+        }
+    }
+
+    /// Callback durations of the last complete frame.
+    pub fn last_frame(&self) -> &CallbackStats {
+        &self.last_frame
+    }
+
+    /// Records one callback, returns whether it went over budget.
+    fn record(&mut self, y: usize, cycles: u32) -> bool {
+        let over = cycles > self.max_cycles;
+        self.current.record(cycles, over);
+
+        if over && self.log_limit.ready() {
+            warn!(
+                "Line {} took {} cycles, budget is {}",
+                y, cycles, self.max_cycles
+            );
+        }
+
+        over
+    }
+
+    fn end_frame(&mut self) {
+        self.last_frame = core::mem::take(&mut self.current);
+    }
+}
+
+/// Options for [stream_frames].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Config {
+    leading_blank_lines: usize,
+}
+
+impl Config {
+    /// Prepends `n` black lines to every frame, for panels that show junk in
+    /// their first rows after DPI startup.
+    ///
+    /// The blank lines are part of the active region, so the DPI timing's
+    /// `vertical_active_height` has to grow by `n` for the content to keep
+    /// its height. At the same pclk every frame then takes `n` lines longer,
+    /// lowering the refresh rate by `n / vertical_total_height`.
+    pub const fn with_leading_blank_lines(mut self, n: usize) -> Self {
+        self.leading_blank_lines = n;
+        self
+    }
+
+    pub const fn leading_blank_lines(&self) -> usize {
+        self.leading_blank_lines
+    }
+}
+
+/// A one-shot stall armed by [inject_stall_at].
+#[cfg(feature = "fault-inject")]
+#[derive(Clone, Copy)]
+struct Stall {
+    frame: u32,
+    line: u16,
+    duration_us: u32,
+}
+
+#[cfg(feature = "fault-inject")]
+static STALL: critical_section::Mutex<core::cell::Cell<Option<Stall>>> =
+    critical_section::Mutex::new(core::cell::Cell::new(None));
+
+/// Makes [stream_frames] stop pushing for `duration_us` right before line
+/// `line` of frame `frame`, counted from the start of the call, once.
+///
+/// The wait happens in the refill path exactly where a slow render callback
+/// would stall it, so with a `duration_us` longer than the ring holds the
+/// stream underruns at a known point. Arming again replaces a stall that
+/// hasn't fired yet. [check_stall_recovery] uses it to check that the stream
+/// recovers.
+#[cfg(feature = "fault-inject")]
+pub fn inject_stall_at(frame: u32, line: u16, duration_us: u32) {
+    let stall = Stall {
+        frame,
+        line,
+        duration_us,
+    };
+    critical_section::with(|cs| STALL.borrow(cs).set(Some(stall)));
+}
+
+/// Waits out the injected stall if it's due at `frame`, `line`.
+#[cfg(feature = "fault-inject")]
+fn maybe_stall(frame: usize, line: usize) {
+    let due = critical_section::with(|cs| {
+        let cell = STALL.borrow(cs);
+        match cell.get() {
+            Some(stall) if stall.frame as usize == frame && stall.line as usize == line => {
+                cell.set(None);
+                Some(stall)
+            }
+            _ => None,
+        }
+    });
+
+    if let Some(stall) = due {
+        warn!(
+            "Injected stall of {}us at frame {} line {}",
+            stall.duration_us, frame, line
+        );
+        crate::hal::delay::Delay::new().delay_micros(stall.duration_us);
+    }
+}
+
+/// Errors of [check_stall_recovery].
+#[cfg(feature = "fault-inject")]
+#[derive(Debug)]
+pub enum RecoveryError {
+    /// The stream survived the stall, make it longer than the ring holds.
+    NoUnderrun,
+    /// The restarted transfer didn't start.
+    Dma(crate::hal::dma::DmaError),
+    /// The first frame after the restart underran again or the DMA didn't
+    /// get through it within the timeout.
+    NotRealigned,
+}
+
+/// On-target check of underrun recovery: streams with a stall of
+/// `duration_us` armed in the middle of the second frame, expects
+/// [stream_frames] to fail with [Underrun], restarts the transfer and
+/// checks that the next frame is sent whole, from its first line, within
+/// `timeout`, e.g. a
+/// [vsync::frame_timeout](crate::display::vsync::frame_timeout).
+///
+/// Restarting drops the partial frame, so the DMA starts the next one at a
+/// frame boundary and the image realigns within that frame. The transfer is
+/// handed back with the ring drained. `render` is called like for
+/// [stream_frames].
+#[cfg(feature = "fault-inject")]
+pub fn check_stall_recovery<'d, Dm: crate::hal::DriverMode>(
+    mut transfer: crate::hal::lcd_cam::lcd::dpi::DpiTransfer<'d, crate::dma::DmaTxStreamBuf, Dm>,
+    line: &mut [u8],
+    geometry: &FrameGeometry,
+    duration_us: u32,
+    timeout: Duration,
+    mut render: impl FnMut(usize, &mut [u8]),
+) -> Result<
+    crate::hal::lcd_cam::lcd::dpi::DpiTransfer<'d, crate::dma::DmaTxStreamBuf, Dm>,
+    RecoveryError,
+> {
+    let config = Config::default();
+    let mut budget = LineBudget::new(u32::MAX, false);
+
+    inject_stall_at(1, (geometry.height / 2) as u16, duration_us);
+    let stalled = stream_frames::<false>(
+        &mut transfer,
+        &config,
+        line,
+        geometry,
+        3,
+        &mut budget,
+        &mut render,
+    );
+    if stalled.is_ok() {
+        return Err(RecoveryError::NoUnderrun);
+    }
+    let underruns = transfer.underruns();
+
+    // The DMA can't start on an empty ring, so prefill it with the top of
+    // the next frame. A resumed ring already starts at a frame boundary and
+    // has no room.
+    let (dpi, mut buf) = transfer.stop();
+    let mut y = 0;
+    while y < geometry.height && buf.available_bytes() >= line.len() {
+        render(y, line);
+        buf.push_wire(WireBytes(line));
+        y += 1;
+    }
+
+    let mut transfer = dpi
+        .send(true, buf)
+        .map_err(|(err, ..)| RecoveryError::Dma(err))?;
+
+    for y in y..geometry.height {
+        render(y, line);
+        push_all(&mut transfer, line, y == geometry.height - 1)
+            .map_err(|_| RecoveryError::NotRealigned)?;
+    }
+    transfer
+        .drain(Some(timeout))
+        .map_err(|_| RecoveryError::NotRealigned)?;
+
+    info!(
+        "Stall recovery: {} underruns, next frame sent whole",
+        underruns
+    );
+    Ok(transfer)
+}
+
+/// Pushes all of `data`, waiting for the DMA to free up space as needed.
+/// Fails instead of waiting forever once the DMA has stopped, see
+/// [DmaTxStreamBufView::try_push_wire].
+fn push_all(view: &mut DmaTxStreamBufView, data: &[u8], set_eof: bool) -> Result<(), Underrun> {
+    let mut remaining = data;
+    while !remaining.is_empty() {
+        let pushed = view.try_push_wire(WireBytes(remaining), set_eof)?;
+        remaining = &remaining[pushed..];
+    }
+    Ok(())
+}
+
+/// Streams `frames` frames of `geometry`, calling `render` to fill `line`
+/// with line `y` before each push. `line` has to be
+/// [FrameGeometry::bytes_per_line] long.
+///
+/// With `ENFORCE` each callback is timed with the cycle counter against
+/// `budget`; without it `budget` is left untouched and the timing code
+/// compiles out.
+///
+/// Fails with [Underrun] as soon as a push finds the DMA stopped, e.g.
+/// after a callback took longer than the ring holds with
+/// [UnderrunPolicy::Stop](crate::dma::UnderrunPolicy::Stop). Stop the
+/// transfer and send it again to recover; the frame in progress is lost.
+///
+/// ```ignore
+/// let mut budget = LineBudget::new(20_000, true);
+/// let config = Config::default();
+/// let geometry = BOARD.panel.geometry;
+/// let mut line = [0; BOARD.panel.geometry.bytes_per_line()];
+/// stream_frames::<true>(&mut transfer, &config, &mut line, &geometry, 60, &mut budget, render_line)?;
+/// info!("max {} cycles", budget.last_frame().max_cycles());
+/// ```
+pub fn stream_frames<const ENFORCE: bool>(
+    view: &mut DmaTxStreamBufView,
+    config: &Config,
+    line: &mut [u8],
+    geometry: &FrameGeometry,
+    frames: usize,
+    budget: &mut LineBudget,
+    render: impl FnMut(usize, &mut [u8]),
+) -> Result<(), Underrun> {
+    stream_frames_with::<ENFORCE, 0>(
+        view,
+        config,
+        line,
+        geometry,
+        frames,
+        budget,
+        &mut Pipeline::new([]),
+        render,
+    )
+}
+
+/// [stream_frames], running `effects` on each line after `render`.
+///
+/// With `ENFORCE` the budget covers the callback and all stages together,
+/// so [LineBudget::last_frame] is the total per-line cost, and each stage
+/// is also checked against its own [LineEffect::max_cycles], see
+/// [Pipeline::last_frame].
+///
+/// Fails with [Underrun] like [stream_frames]. With `ENFORCE` the frame cut
+/// short still ends up in both `last_frame`s, so the stage or line that
+/// starved the ring can be looked up afterwards.
+///
+/// [LineEffect::max_cycles]: crate::render::LineEffect::max_cycles
+#[allow(clippy::too_many_arguments)]
+pub fn stream_frames_with<const ENFORCE: bool, const N: usize>(
+    view: &mut DmaTxStreamBufView,
+    config: &Config,
+    line: &mut [u8],
+    geometry: &FrameGeometry,
+    frames: usize,
+    budget: &mut LineBudget,
+    effects: &mut Pipeline<'_, N>,
+    render: impl FnMut(usize, &mut [u8]),
+) -> Result<(), Underrun> {
+    stream_lines::<ENFORCE, N>(
+        |line, set_eof| push_all(view, line, set_eof),
+        config,
+        line,
+        geometry,
+        frames,
+        budget,
+        effects,
+        render,
+    )
+}
+
+/// [stream_frames_with] with every line handed to `push` instead of a view.
+#[allow(clippy::too_many_arguments)]
+fn stream_lines<const ENFORCE: bool, const N: usize>(
+    mut push: impl FnMut(&[u8], bool) -> Result<(), Underrun>,
+    config: &Config,
+    line: &mut [u8],
+    geometry: &FrameGeometry,
+    frames: usize,
+    budget: &mut LineBudget,
+    effects: &mut Pipeline<'_, N>,
+    mut render: impl FnMut(usize, &mut [u8]),
+) -> Result<(), Underrun> {
+    debug_assert_eq!(line.len(), geometry.bytes_per_line());
+    let height = geometry.height;
+
+    #[cfg_attr(not(feature = "fault-inject"), allow(unused_variables))]
+    for frame in 0..frames {
+        if config.leading_blank_lines > 0 {
+            line.fill(0);
+            for _ in 0..config.leading_blank_lines {
+                push(line, false).inspect_err(|_| end_frame::<ENFORCE, N>(budget, effects))?;
+            }
+        }
+
+        for y in 0..height {
+            if ENFORCE {
+                let started = get_cycle_count();
+                render(y, line);
+                let cycles = get_cycle_count().wrapping_sub(started);
+                let cycles = cycles.wrapping_add(effects.apply_timed(y, line));
+
+                if budget.record(y, cycles) && budget.mark_overruns {
+                    let format = geometry.pixel_format;
+                    for pixel in line.chunks_exact_mut(format.bytes_per_pixel()) {
+                        format.encode(0xFF, 0x00, 0xFF, pixel);
+                    }
+                }
+            } else {
+                render(y, line);
+                effects.apply(y, line);
+            }
+
+            #[cfg(feature = "fault-inject")]
+            maybe_stall(frame, y);
+
+            push(line, y == height - 1)
+                .inspect_err(|_| end_frame::<ENFORCE, N>(budget, effects))?;
+        }
+
+        end_frame::<ENFORCE, N>(budget, effects);
+    }
+
+    Ok(())
+}
+
+/// Closes the per-frame stats of [stream_frames_with].
+fn end_frame<const ENFORCE: bool, const N: usize>(
+    budget: &mut LineBudget,
+    effects: &mut Pipeline<'_, N>,
+) {
+    if ENFORCE {
+        budget.end_frame();
+        effects.end_frame();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::color::PixelFormat;
+
+    #[test]
+    fn leading_blank_lines_come_before_each_frame() {
+        let geometry = FrameGeometry::new(2, 3, PixelFormat::Rgb565);
+        let config = Config::default().with_leading_blank_lines(2);
+        let mut line = [0; 4];
+        let mut pushed = Vec::new();
+
+        stream_lines::<false, 0>(
+            |line, set_eof| {
+                pushed.push((line[0], set_eof));
+                Ok(())
+            },
+            &config,
+            &mut line,
+            &geometry,
+            2,
+            &mut LineBudget::new(u32::MAX, false),
+            &mut Pipeline::new([]),
+            |y, line| line.fill(y as u8 + 1),
+        )
+        .unwrap();
+
+        // Two black lines, then rows 1 to 3 with EOF on the last, twice.
+        let frame = [(0, false), (0, false), (1, false), (2, false), (3, true)];
+        assert_eq!(pushed, [frame, frame].concat());
+    }
+}